@@ -0,0 +1,183 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! A handler process for the `SubprocessExecutionAdapter`, driven by the transaction's
+//! payload:
+//!
+//! * `invalid` - the transaction is invalid
+//! * `increment <address>` - add one to the byte stored at the address
+//! * `hang` - never respond
+//! * `crash` - exit without responding
+//! * anything else - the transaction is valid
+
+use std::io::{self, BufReader, Read, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use protobuf::Message;
+
+use transact::execution::adapter::subprocess::protocol::{
+    read_message, wrap_message, write_message,
+};
+use transact::execution::adapter::subprocess::SubprocessProtocolError;
+use transact::protos::subprocess::{
+    SubprocessExecuteRequest, SubprocessExecuteResponse, SubprocessExecuteResponse_Status,
+    SubprocessMessage, SubprocessMessage_MessageType, SubprocessStateEntry,
+    SubprocessStateGetRequest, SubprocessStateGetResponse, SubprocessStateGetResponse_Status,
+    SubprocessStateSetRequest, SubprocessStateSetResponse, SubprocessStateSetResponse_Status,
+};
+
+struct Connection<R: Read, W: Write> {
+    input: R,
+    output: W,
+    next_correlation_id: u64,
+}
+
+impl<R: Read, W: Write> Connection<R, W> {
+    /// Send a state request and wait for the response to it.
+    fn request<M: Message>(
+        &mut self,
+        message_type: SubprocessMessage_MessageType,
+        content: &M,
+    ) -> Result<SubprocessMessage, SubprocessProtocolError> {
+        let correlation_id = self.next_correlation_id;
+        self.next_correlation_id += 1;
+        write_message(
+            &mut self.output,
+            &wrap_message(message_type, correlation_id, content)?,
+        )?;
+
+        loop {
+            match read_message(&mut self.input)? {
+                Some(message) if message.get_correlation_id() == correlation_id => {
+                    return Ok(message)
+                }
+                Some(_) => (),
+                None => process::exit(0),
+            }
+        }
+    }
+
+    fn increment(
+        &mut self,
+        context_id: &[u8],
+        address: &str,
+    ) -> Result<bool, SubprocessProtocolError> {
+        let mut get_request = SubprocessStateGetRequest::new();
+        get_request.set_context_id(context_id.to_vec());
+        get_request.set_addresses(vec![address.to_string()].into());
+        let message = self.request(
+            SubprocessMessage_MessageType::STATE_GET_REQUEST,
+            &get_request,
+        )?;
+        let get_response: SubprocessStateGetResponse =
+            Message::parse_from_bytes(message.get_content())?;
+        if get_response.get_status() != SubprocessStateGetResponse_Status::OK {
+            return Ok(false);
+        }
+
+        let value = get_response
+            .get_entries()
+            .iter()
+            .find(|entry| entry.get_address() == address)
+            .and_then(|entry| entry.get_data().first().cloned())
+            .unwrap_or(0);
+
+        let mut entry = SubprocessStateEntry::new();
+        entry.set_address(address.to_string());
+        entry.set_data(vec![value.wrapping_add(1)]);
+        let mut set_request = SubprocessStateSetRequest::new();
+        set_request.set_context_id(context_id.to_vec());
+        set_request.set_entries(vec![entry].into());
+        let message = self.request(
+            SubprocessMessage_MessageType::STATE_SET_REQUEST,
+            &set_request,
+        )?;
+        let set_response: SubprocessStateSetResponse =
+            Message::parse_from_bytes(message.get_content())?;
+
+        Ok(set_response.get_status() == SubprocessStateSetResponse_Status::OK)
+    }
+
+    fn execute(
+        &mut self,
+        request: &SubprocessExecuteRequest,
+    ) -> Result<SubprocessExecuteResponse, SubprocessProtocolError> {
+        let payload = String::from_utf8_lossy(request.get_transaction().get_payload()).to_string();
+        let mut words = payload.split_whitespace();
+
+        let mut response = SubprocessExecuteResponse::new();
+        match words.next() {
+            Some("invalid") => {
+                response.set_status(SubprocessExecuteResponse_Status::INVALID_TRANSACTION);
+                response.set_message("The payload is invalid".to_string());
+                response.set_extended_data(payload.clone().into_bytes());
+            }
+            Some("increment") => {
+                let address = words.next().unwrap_or_default();
+                if self.increment(request.get_context_id(), address)? {
+                    response.set_status(SubprocessExecuteResponse_Status::OK);
+                } else {
+                    response.set_status(SubprocessExecuteResponse_Status::INVALID_TRANSACTION);
+                    response.set_message(format!("Unable to increment {}", address));
+                }
+            }
+            Some("hang") => loop {
+                thread::sleep(Duration::from_secs(60));
+            },
+            Some("crash") => process::exit(1),
+            _ => response.set_status(SubprocessExecuteResponse_Status::OK),
+        }
+        Ok(response)
+    }
+
+    fn run(&mut self) -> Result<(), SubprocessProtocolError> {
+        while let Some(message) = read_message(&mut self.input)? {
+            if message.get_message_type() != SubprocessMessage_MessageType::EXECUTE_REQUEST {
+                continue;
+            }
+            let request: SubprocessExecuteRequest =
+                Message::parse_from_bytes(message.get_content())?;
+            let response = self.execute(&request)?;
+            write_message(
+                &mut self.output,
+                &wrap_message(
+                    SubprocessMessage_MessageType::EXECUTE_RESPONSE,
+                    message.get_correlation_id(),
+                    &response,
+                )?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut connection = Connection {
+        input: BufReader::new(stdin.lock()),
+        output: stdout.lock(),
+        next_correlation_id: 0,
+    };
+
+    if let Err(err) = connection.run() {
+        eprintln!("subprocess_handler: {}", err);
+        process::exit(1);
+    }
+}
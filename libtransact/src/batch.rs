@@ -5,7 +5,7 @@ use std::error::Error as StdError;
 
 use crate::protos::{FromNative, FromProto, IntoNative, IntoProto, ProtoConversionError};
 use crate::signing;
-use crate::transaction::Transaction;
+use crate::transaction::{Transaction, TransactionParseError};
 
 use super::protos;
 
@@ -57,6 +57,7 @@ impl FromNative<BatchHeader> for protos::batch::BatchHeader {
 impl IntoProto<protos::batch::BatchHeader> for BatchHeader {}
 impl IntoNative<BatchHeader> for protos::batch::BatchHeader {}
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Batch {
     header: Vec<u8>,
     header_signature: String,
@@ -80,6 +81,79 @@ impl Batch {
     pub fn trace(&self) -> bool {
         self.trace
     }
+
+    /// Decode the header and the headers of the transactions, making the pair of this batch
+    /// and its header. The header's transaction ids must be the header signatures of the
+    /// transactions, in order, and each transaction must name the batch's signer as its
+    /// batcher. Signatures are not checked.
+    pub fn into_pair(self) -> Result<BatchPair, BatchParseError> {
+        let proto_header = protos::batch::BatchHeader::parse_from_bytes(&self.header)
+            .map_err(|err| BatchParseError::MalformedHeader(err.to_string()))?;
+        // Kept as the header has them, as decoding them would accept any case of hex.
+        let transaction_ids = proto_header.get_transaction_ids().to_vec();
+        let header: BatchHeader = proto_header
+            .into_native()
+            .map_err(|err| BatchParseError::MalformedHeader(err.to_string()))?;
+
+        if transaction_ids.len() != self.transactions.len() {
+            return Err(BatchParseError::TransactionIdMismatch(format!(
+                "Header has {} transaction ids, but the batch has {} transactions",
+                transaction_ids.len(),
+                self.transactions.len()
+            )));
+        }
+
+        let Batch {
+            header: header_bytes,
+            header_signature,
+            transactions,
+            trace,
+        } = self;
+
+        let transactions = transactions
+            .into_iter()
+            .zip(transaction_ids.iter())
+            .enumerate()
+            .map(|(index, (transaction, transaction_id))| {
+                if transaction_id != transaction.header_signature() {
+                    return Err(BatchParseError::TransactionIdMismatch(format!(
+                        "Transaction {} has header signature {}, but the header's transaction id \
+                         is {}",
+                        index,
+                        transaction.header_signature(),
+                        transaction_id
+                    )));
+                }
+
+                let (transaction, transaction_header) = transaction
+                    .into_pair()
+                    .map_err(|err| BatchParseError::InvalidTransaction(index, err))?
+                    .take();
+
+                if transaction_header.batcher_public_key() != header.signer_public_key() {
+                    return Err(BatchParseError::BatcherMismatch(format!(
+                        "Transaction {} ({}) has batcher {}, but the batch signer is {}",
+                        index,
+                        transaction.header_signature(),
+                        hex::encode(transaction_header.batcher_public_key()),
+                        hex::encode(header.signer_public_key())
+                    )));
+                }
+
+                Ok(transaction)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(BatchPair {
+            batch: Batch {
+                header: header_bytes,
+                header_signature,
+                transactions,
+                trace,
+            },
+            header,
+        })
+    }
 }
 
 pub struct BatchPair {
@@ -117,6 +191,150 @@ impl From<protos::batch::Batch> for Batch {
     }
 }
 
+impl FromProto<protos::batch::Batch> for Batch {
+    fn from_proto(batch: protos::batch::Batch) -> Result<Self, ProtoConversionError> {
+        Ok(Batch::from(batch))
+    }
+}
+
+impl FromNative<Batch> for protos::batch::Batch {
+    fn from_native(batch: Batch) -> Result<Self, ProtoConversionError> {
+        let mut proto_batch = protos::batch::Batch::new();
+        proto_batch.set_header(batch.header);
+        proto_batch.set_header_signature(batch.header_signature);
+        proto_batch.set_transactions(
+            batch
+                .transactions
+                .into_iter()
+                .map(IntoProto::into_proto)
+                .collect::<Result<_, _>>()?,
+        );
+        proto_batch.set_trace(batch.trace);
+        Ok(proto_batch)
+    }
+}
+
+impl IntoProto<protos::batch::Batch> for Batch {}
+impl IntoNative<Batch> for protos::batch::Batch {}
+
+impl Batch {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto: protos::batch::Batch = self.clone().into_proto()?;
+        Ok(proto.write_to_bytes()?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Batch, ProtoConversionError> {
+        protos::batch::Batch::parse_from_bytes(bytes)?.into_native()
+    }
+}
+
+/// A list of batches, as submitted to a validator.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BatchList {
+    batches: Vec<Batch>,
+}
+
+impl BatchList {
+    pub fn new(batches: Vec<Batch>) -> Self {
+        BatchList { batches }
+    }
+
+    pub fn batches(&self) -> &[Batch] {
+        &self.batches
+    }
+
+    pub fn take(self) -> Vec<Batch> {
+        self.batches
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto: protos::batch::BatchList = self.clone().into_proto()?;
+        Ok(proto.write_to_bytes()?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<BatchList, ProtoConversionError> {
+        protos::batch::BatchList::parse_from_bytes(bytes)?.into_native()
+    }
+}
+
+impl FromProto<protos::batch::BatchList> for BatchList {
+    fn from_proto(mut batch_list: protos::batch::BatchList) -> Result<Self, ProtoConversionError> {
+        Ok(BatchList {
+            batches: batch_list
+                .take_batches()
+                .into_iter()
+                .map(IntoNative::into_native)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl FromNative<BatchList> for protos::batch::BatchList {
+    fn from_native(batch_list: BatchList) -> Result<Self, ProtoConversionError> {
+        let mut proto_batch_list = protos::batch::BatchList::new();
+        proto_batch_list.set_batches(
+            batch_list
+                .batches
+                .into_iter()
+                .map(IntoProto::into_proto)
+                .collect::<Result<_, _>>()?,
+        );
+        Ok(proto_batch_list)
+    }
+}
+
+impl IntoProto<protos::batch::BatchList> for BatchList {}
+impl IntoNative<BatchList> for protos::batch::BatchList {}
+
+/// Why a received batch could not be made into a `BatchPair`.
+#[derive(Debug)]
+pub enum BatchParseError {
+    /// The header bytes are not a valid `BatchHeader`.
+    MalformedHeader(String),
+    /// The transaction at the index could not be parsed.
+    InvalidTransaction(usize, TransactionParseError),
+    /// The header's transaction ids are not the header signatures of the transactions.
+    TransactionIdMismatch(String),
+    /// A transaction's batcher is not the signer of the batch.
+    BatcherMismatch(String),
+}
+
+impl StdError for BatchParseError {
+    #[allow(deprecated)]
+    fn description(&self) -> &str {
+        match *self {
+            BatchParseError::MalformedHeader(ref msg) => msg,
+            BatchParseError::InvalidTransaction(_, ref err) => err.description(),
+            BatchParseError::TransactionIdMismatch(ref msg) => msg,
+            BatchParseError::BatcherMismatch(ref msg) => msg,
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn StdError> {
+        match *self {
+            BatchParseError::MalformedHeader(_) => None,
+            BatchParseError::InvalidTransaction(_, ref err) => Some(err),
+            BatchParseError::TransactionIdMismatch(_) => None,
+            BatchParseError::BatcherMismatch(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BatchParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            BatchParseError::MalformedHeader(ref s) => write!(f, "MalformedHeader: {}", s),
+            BatchParseError::InvalidTransaction(index, ref err) => {
+                write!(f, "InvalidTransaction: transaction {}: {}", index, err)
+            }
+            BatchParseError::TransactionIdMismatch(ref s) => {
+                write!(f, "TransactionIdMismatch: {}", s)
+            }
+            BatchParseError::BatcherMismatch(ref s) => write!(f, "BatcherMismatch: {}", s),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum BatchBuildError {
     MissingField(String),
@@ -382,8 +600,247 @@ mod tests {
         assert_eq!(true, batch.trace());
     }
 
+    /// A transaction batched by `batcher`, with the header built directly as a proto.
+    fn batched_transaction(batcher: &[u8], nonce: &str) -> Transaction {
+        let signer = HashSigner::new();
+
+        let mut proto_header = protos::transaction::TransactionHeader::new();
+        proto_header.set_batcher_public_key(hex::encode(batcher));
+        proto_header.set_nonce(nonce.to_string());
+        proto_header.set_signer_public_key(hex::encode(signer.public_key()));
+        let header = proto_header.write_to_bytes().unwrap();
+
+        let header_signature = hex::encode(signer.sign(&header).unwrap());
+        Transaction::new(header, header_signature, vec![])
+    }
+
+    /// A batch signed by `signer` with the header's transaction ids given separately from
+    /// the transactions.
+    fn batch_with_ids(
+        signer: &dyn Signer,
+        transaction_ids: Vec<String>,
+        transactions: Vec<Transaction>,
+    ) -> Batch {
+        let mut proto_header = protos::batch::BatchHeader::new();
+        proto_header.set_signer_public_key(hex::encode(signer.public_key()));
+        proto_header.set_transaction_ids(protobuf::RepeatedField::from_vec(transaction_ids));
+        let header = proto_header.write_to_bytes().unwrap();
+
+        Batch {
+            header_signature: hex::encode(signer.sign(&header).unwrap()),
+            header,
+            transactions,
+            trace: false,
+        }
+    }
+
+    #[test]
+    fn batch_into_pair() {
+        let signer = HashSigner::new();
+        let transactions = vec![
+            batched_transaction(signer.public_key(), "1"),
+            batched_transaction(signer.public_key(), "2"),
+        ];
+
+        let batch = BatchBuilder::new()
+            .with_transactions(transactions.clone())
+            .build(&signer)
+            .unwrap();
+        let pair = batch.into_pair().unwrap();
+
+        assert_eq!(signer.public_key(), pair.header().signer_public_key());
+        assert_eq!(transactions, pair.batch().transactions());
+
+        let malformed = Batch {
+            header: vec![0xff, 0xff],
+            header_signature: SIGNATURE1.to_string(),
+            transactions: vec![],
+            trace: false,
+        };
+        assert!(matches!(
+            malformed.into_pair(),
+            Err(BatchParseError::MalformedHeader(_))
+        ));
+
+        let invalid_transaction = BatchBuilder::new()
+            .with_transactions(vec![
+                transactions[0].clone(),
+                Transaction::new(
+                    vec![0xff, 0xff],
+                    transactions[1].header_signature().to_string(),
+                    vec![],
+                ),
+            ])
+            .build(&signer)
+            .unwrap();
+        assert!(matches!(
+            invalid_transaction.into_pair(),
+            Err(BatchParseError::InvalidTransaction(1, _))
+        ));
+    }
+
+    #[test]
+    fn batch_into_pair_transaction_ids() {
+        let signer = HashSigner::new();
+        let transactions = vec![
+            batched_transaction(signer.public_key(), "1"),
+            batched_transaction(signer.public_key(), "2"),
+        ];
+        let ids: Vec<String> = transactions
+            .iter()
+            .map(|t| t.header_signature().to_string())
+            .collect();
+
+        let missing = batch_with_ids(&signer, vec![ids[0].clone()], transactions.clone());
+        assert!(matches!(
+            missing.into_pair(),
+            Err(BatchParseError::TransactionIdMismatch(_))
+        ));
+
+        let extra = batch_with_ids(&signer, ids.clone(), vec![transactions[0].clone()]);
+        assert!(matches!(
+            extra.into_pair(),
+            Err(BatchParseError::TransactionIdMismatch(_))
+        ));
+
+        let reordered = batch_with_ids(
+            &signer,
+            vec![ids[1].clone(), ids[0].clone()],
+            transactions.clone(),
+        );
+        assert!(matches!(
+            reordered.into_pair(),
+            Err(BatchParseError::TransactionIdMismatch(_))
+        ));
+
+        // The same id in upper case is not the header signature.
+        let upper_case = batch_with_ids(
+            &signer,
+            ids.clone(),
+            vec![
+                Transaction::new(
+                    transactions[0].header().to_vec(),
+                    transactions[0].header_signature().to_uppercase(),
+                    transactions[0].payload().to_vec(),
+                ),
+                transactions[1].clone(),
+            ],
+        );
+        assert!(matches!(
+            upper_case.into_pair(),
+            Err(BatchParseError::TransactionIdMismatch(_))
+        ));
+
+        // Nor is an upper case id in the header.
+        let upper_case_header = batch_with_ids(
+            &signer,
+            vec![ids[0].to_uppercase(), ids[1].clone()],
+            transactions.clone(),
+        );
+        assert!(matches!(
+            upper_case_header.into_pair(),
+            Err(BatchParseError::TransactionIdMismatch(_))
+        ));
+
+        let in_order = batch_with_ids(&signer, ids, transactions);
+        assert!(in_order.into_pair().is_ok());
+    }
+
     #[test]
-    fn batch_sawtooth10_compatibility() {}
+    fn batch_into_pair_batcher() {
+        let signer = HashSigner::new();
+
+        let batch = BatchBuilder::new()
+            .with_transactions(vec![
+                batched_transaction(signer.public_key(), "1"),
+                batched_transaction(&hex::decode(KEY1).unwrap(), "2"),
+            ])
+            .build(&signer)
+            .unwrap();
+
+        match batch.into_pair() {
+            Err(BatchParseError::BatcherMismatch(msg)) => {
+                assert!(msg.starts_with("Transaction 1 "), "{}", msg)
+            }
+            _ => panic!("Expected a BatcherMismatch"),
+        }
+    }
+
+    /// The Sawtooth SDK's batch with the same fields as the batch.
+    fn sawtooth_batch(batch: &Batch) -> sawtooth_sdk::messages::batch::Batch {
+        let mut proto = sawtooth_sdk::messages::batch::Batch::new();
+        proto.set_header(batch.header().to_vec());
+        proto.set_header_signature(batch.header_signature().to_string());
+        proto.set_transactions(protobuf::RepeatedField::from_vec(
+            batch
+                .transactions()
+                .iter()
+                .map(|transaction| {
+                    let mut proto = sawtooth_sdk::messages::transaction::Transaction::new();
+                    proto.set_header(transaction.header().to_vec());
+                    proto.set_header_signature(transaction.header_signature().to_string());
+                    proto.set_payload(transaction.payload().to_vec());
+                    proto
+                })
+                .collect(),
+        ));
+        proto.set_trace(batch.trace());
+        proto
+    }
+
+    #[test]
+    fn batch_sawtooth10_compatibility() {
+        let batch = Batch {
+            header: BYTES1.to_vec(),
+            header_signature: SIGNATURE1.to_string(),
+            transactions: vec![
+                Transaction::new(BYTES2.to_vec(), SIGNATURE2.to_string(), BYTES3.to_vec()),
+                Transaction::new(BYTES4.to_vec(), SIGNATURE3.to_string(), BYTES5.to_vec()),
+            ],
+            trace: true,
+        };
+        let batch_bytes = sawtooth_batch(&batch).write_to_bytes().unwrap();
+
+        assert_eq!(batch_bytes, batch.to_bytes().unwrap());
+        assert_eq!(batch, Batch::from_bytes(&batch_bytes).unwrap());
+    }
+
+    #[test]
+    fn batch_list_sawtooth10_compatibility() {
+        let signer = HashSigner::new();
+        let batches = vec![
+            BatchBuilder::new()
+                .with_transactions(vec![Transaction::new(
+                    BYTES2.to_vec(),
+                    hex::encode(SIGNATURE2),
+                    BYTES3.to_vec(),
+                )])
+                .build(&signer)
+                .unwrap(),
+            BatchBuilder::new()
+                .with_transactions(vec![Transaction::new(
+                    BYTES4.to_vec(),
+                    hex::encode(SIGNATURE3),
+                    BYTES5.to_vec(),
+                )])
+                .with_trace(true)
+                .build(&signer)
+                .unwrap(),
+        ];
+
+        let mut proto = sawtooth_sdk::messages::batch::BatchList::new();
+        proto.set_batches(batches.iter().map(sawtooth_batch).collect());
+        let batch_list_bytes = proto.write_to_bytes().unwrap();
+
+        let batch_list = BatchList::new(batches);
+        assert_eq!(batch_list_bytes, batch_list.to_bytes().unwrap());
+
+        let parsed = BatchList::from_bytes(&batch_list_bytes).unwrap();
+        assert_eq!(batch_list, parsed);
+        assert_eq!(2, parsed.take().len());
+
+        assert!(BatchList::from_bytes(&[0xff, 0xff]).is_err());
+    }
 }
 
 #[cfg(all(feature = "nightly", test))]
@@ -0,0 +1,420 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! An async/await counterpart to `ExecutionAdapter`, along with bridges between the two.
+
+use crate::context::ContextId;
+use crate::execution::adapter::{
+    ExecutionAdapter, ExecutionAdapterError, ExecutionResult, OnDoneCallback, OnRegisterCallback,
+    OnUnregisterCallback,
+};
+use crate::transaction::TransactionPair;
+use futures::channel::{mpsc, oneshot};
+use futures::executor::block_on;
+use futures::{FutureExt, StreamExt};
+use std::future::Future;
+use std::pin::Pin;
+use std::thread;
+
+/// The eventual outcome of executing a `TransactionPair`.
+pub type ExecutionFuture =
+    Pin<Box<dyn Future<Output = Result<ExecutionResult, ExecutionAdapterError>> + Send>>;
+
+/// Implementers of this trait proxy the transaction to the correct component to execute
+/// the transaction, returning a `Future` of the result instead of firing a callback.
+pub trait AsyncExecutionAdapter: Send {
+    /// Register a callback to be fired when the execution adapter registers a new
+    /// capability.
+    fn on_register(&self, callback: Box<OnRegisterCallback>);
+
+    /// Register a callback to be fired when the execution adapter unregisters a
+    /// new capability.
+    fn on_unregister(&self, callback: Box<OnUnregisterCallback>);
+
+    /// Execute the transaction. The returned future resolves when the transaction
+    /// returns from processing or there is an error.
+    fn execute(&self, transaction_pair: TransactionPair, context_id: ContextId) -> ExecutionFuture;
+
+    /// Abandon the execution of the transaction with the `ContextId`, if it is still
+    /// underway. By default nothing is abandoned.
+    fn cancel(&self, _context_id: ContextId) {}
+
+    /// Stop the internal threads and the Executor will no longer call execute.
+    fn stop(self: Box<Self>) -> bool;
+}
+
+/// An execution's future, which fires its `on_done` when it resolves.
+type PendingExecution = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Exposes an `AsyncExecutionAdapter` as an `ExecutionAdapter`, so that it can be given
+/// to the `Executer`.
+///
+/// `execute` returns as soon as the wrapped adapter has returned the future. The futures
+/// are driven concurrently on a thread of the bridge's own, which fires each `on_done` as
+/// its future resolves.
+pub struct AsyncExecutionAdapterBridge {
+    adapter: Box<dyn AsyncExecutionAdapter>,
+    sender: mpsc::UnboundedSender<PendingExecution>,
+    driver: thread::JoinHandle<()>,
+}
+
+impl AsyncExecutionAdapterBridge {
+    pub fn new(adapter: Box<dyn AsyncExecutionAdapter>) -> Self {
+        let (sender, receiver) = mpsc::unbounded::<PendingExecution>();
+        let driver = thread::Builder::new()
+            .name("async_execution_adapter_bridge".to_string())
+            .spawn(move || block_on(receiver.for_each_concurrent(None, |pending| pending)))
+            .expect("Unable to spawn the async execution adapter bridge thread");
+
+        AsyncExecutionAdapterBridge {
+            adapter,
+            sender,
+            driver,
+        }
+    }
+}
+
+impl ExecutionAdapter for AsyncExecutionAdapterBridge {
+    fn on_register(&self, callback: Box<OnRegisterCallback>) {
+        self.adapter.on_register(callback)
+    }
+
+    fn on_unregister(&self, callback: Box<OnUnregisterCallback>) {
+        self.adapter.on_unregister(callback)
+    }
+
+    fn execute(
+        &self,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+        on_done: Box<OnDoneCallback>,
+    ) {
+        let pending: PendingExecution = Box::pin(
+            self.adapter
+                .execute(transaction_pair, context_id)
+                .map(on_done),
+        );
+
+        // The driver only stops when the bridge is stopped, or if it panicked. The
+        // execution is still driven, on this thread, so that on_done is fired.
+        if let Err(err) = self.sender.unbounded_send(pending) {
+            error!("The async execution adapter bridge thread has stopped");
+            block_on(err.into_inner());
+        }
+    }
+
+    fn cancel(&self, context_id: ContextId) {
+        self.adapter.cancel(context_id)
+    }
+
+    /// Stop the wrapped adapter, then wait for the futures it returned to resolve. The
+    /// wrapped adapter must resolve or drop its outstanding futures when it stops.
+    fn stop(self: Box<Self>) -> bool {
+        let AsyncExecutionAdapterBridge {
+            adapter,
+            sender,
+            driver,
+        } = *self;
+
+        let stopped = adapter.stop();
+        drop(sender);
+        if driver.join().is_err() {
+            error!("The async execution adapter bridge thread panicked");
+            return false;
+        }
+        stopped
+    }
+}
+
+/// Exposes an `ExecutionAdapter` as an `AsyncExecutionAdapter`.
+pub struct CallbackExecutionAdapterBridge {
+    adapter: Box<dyn ExecutionAdapter>,
+}
+
+impl CallbackExecutionAdapterBridge {
+    pub fn new(adapter: Box<dyn ExecutionAdapter>) -> Self {
+        CallbackExecutionAdapterBridge { adapter }
+    }
+}
+
+impl AsyncExecutionAdapter for CallbackExecutionAdapterBridge {
+    fn on_register(&self, callback: Box<OnRegisterCallback>) {
+        self.adapter.on_register(callback)
+    }
+
+    fn on_unregister(&self, callback: Box<OnUnregisterCallback>) {
+        self.adapter.on_unregister(callback)
+    }
+
+    fn execute(&self, transaction_pair: TransactionPair, context_id: ContextId) -> ExecutionFuture {
+        let (sender, receiver) = oneshot::channel();
+        let mut sender = Some(sender);
+
+        // If the adapter drops the callback without firing it, the transaction is
+        // handed back as a RoutingError so that the Executer retries it.
+        let retry_pair = transaction_pair.clone();
+
+        self.adapter.execute(
+            transaction_pair,
+            context_id,
+            Box::new(move |result| {
+                if let Some(sender) = sender.take() {
+                    if sender.send(result).is_err() {
+                        debug!("The ExecutionFuture was dropped before the result arrived");
+                    }
+                }
+            }),
+        );
+
+        // The closure returns the adapter's result, whose error may hold the transaction.
+        #[allow(clippy::result_large_err)]
+        let result = receiver.map(move |result| match result {
+            Ok(result) => result,
+            Err(oneshot::Canceled) => Err(ExecutionAdapterError::RoutingError(retry_pair)),
+        });
+        Box::pin(result)
+    }
+
+    fn cancel(&self, context_id: ContextId) {
+        self.adapter.cancel(context_id)
+    }
+
+    fn stop(self: Box<Self>) -> bool {
+        self.adapter.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::adapter::conformance::ConformanceSuite;
+    use crate::execution::adapter::test_adapter::TestExecutionAdapter;
+    use crate::execution::adapter::TransactionStatus;
+    use crate::signing::hash::HashSigner;
+    use crate::transaction::{HashMethod, TransactionBuilder};
+    use futures::future;
+    use std::sync::mpsc as std_mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// An `AsyncExecutionAdapter` that resolves every transaction as valid.
+    struct ReadyAsyncExecutionAdapter;
+
+    impl AsyncExecutionAdapter for ReadyAsyncExecutionAdapter {
+        fn on_register(&self, _callback: Box<OnRegisterCallback>) {}
+
+        fn on_unregister(&self, _callback: Box<OnUnregisterCallback>) {}
+
+        fn execute(
+            &self,
+            transaction_pair: TransactionPair,
+            _context_id: ContextId,
+        ) -> ExecutionFuture {
+            Box::pin(future::ready(Ok(ExecutionResult {
+                transaction_id: transaction_pair
+                    .transaction()
+                    .header_signature()
+                    .to_string(),
+                status: TransactionStatus::Valid,
+                resource_usage: None,
+            })))
+        }
+
+        fn stop(self: Box<Self>) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_async_execution_adapter_bridge() {
+        let adapter = AsyncExecutionAdapterBridge::new(Box::new(ReadyAsyncExecutionAdapter));
+
+        let transaction_pair = make_transaction();
+        let transaction_id = transaction_pair
+            .transaction()
+            .header_signature()
+            .to_string();
+
+        let (sender, receiver) = std_mpsc::channel();
+        adapter.execute(
+            transaction_pair,
+            [0; 16],
+            Box::new(move |result| {
+                sender.send(result).expect("The receiver was dropped");
+            }),
+        );
+
+        match receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("on_done was not fired")
+        {
+            Ok(result) => {
+                assert_eq!(transaction_id, result.transaction_id);
+                assert_eq!(TransactionStatus::Valid, result.status);
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+        }
+
+        assert!(Box::new(adapter).stop());
+    }
+
+    /// An `AsyncExecutionAdapter` whose executions resolve when the test releases them.
+    struct HeldAsyncExecutionAdapter {
+        held: Arc<Mutex<Vec<oneshot::Sender<()>>>>,
+    }
+
+    impl AsyncExecutionAdapter for HeldAsyncExecutionAdapter {
+        fn on_register(&self, _callback: Box<OnRegisterCallback>) {}
+
+        fn on_unregister(&self, _callback: Box<OnUnregisterCallback>) {}
+
+        fn execute(
+            &self,
+            transaction_pair: TransactionPair,
+            _context_id: ContextId,
+        ) -> ExecutionFuture {
+            let (sender, receiver) = oneshot::channel();
+            self.held
+                .lock()
+                .expect("The held lock is poisoned")
+                .push(sender);
+            let transaction_id = transaction_pair
+                .transaction()
+                .header_signature()
+                .to_string();
+            #[allow(clippy::result_large_err)]
+            let result = receiver.map(move |_| {
+                Ok(ExecutionResult {
+                    transaction_id,
+                    status: TransactionStatus::Valid,
+                    resource_usage: None,
+                })
+            });
+            Box::pin(result)
+        }
+
+        fn stop(self: Box<Self>) -> bool {
+            true
+        }
+    }
+
+    /// `execute` returns while the execution's future is pending, and a later execution
+    /// that resolves first fires its `on_done` first.
+    #[test]
+    fn test_async_execution_adapter_bridge_does_not_block() {
+        let held = Arc::new(Mutex::new(vec![]));
+        let adapter = AsyncExecutionAdapterBridge::new(Box::new(HeldAsyncExecutionAdapter {
+            held: Arc::clone(&held),
+        }));
+
+        let (sender, receiver) = std_mpsc::channel();
+        for nonce in &["first", "second"] {
+            let sender = sender.clone();
+            adapter.execute(
+                make_transaction_with_nonce(nonce.as_bytes().to_vec()),
+                [0; 16],
+                Box::new(move |result| {
+                    sender.send(result).expect("The receiver was dropped");
+                }),
+            );
+        }
+
+        let mut held = held.lock().expect("The held lock is poisoned").split_off(0);
+        assert_eq!(2, held.len(), "Both executions were started");
+        held.pop()
+            .expect("The second execution is held")
+            .send(())
+            .expect("The second execution's future was dropped");
+        let second = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("The second on_done was not fired")
+            .expect("The second execution failed");
+        assert!(receiver.try_recv().is_err(), "The first is still held");
+
+        held.pop()
+            .expect("The first execution is held")
+            .send(())
+            .expect("The first execution's future was dropped");
+        let first = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("The first on_done was not fired")
+            .expect("The first execution failed");
+        assert_ne!(first.transaction_id, second.transaction_id);
+
+        assert!(Box::new(adapter).stop());
+    }
+
+    #[test]
+    fn test_callback_execution_adapter_bridge() {
+        let test_adapter = TestExecutionAdapter::new();
+        let registrar = test_adapter.clone();
+
+        let adapter = CallbackExecutionAdapterBridge::new(Box::new(test_adapter));
+        adapter.on_register(Box::new(|_| {}));
+        adapter.on_unregister(Box::new(|_| {}));
+
+        let result = block_on(adapter.execute(make_transaction(), [0; 16]));
+        assert!(
+            matches!(result, Err(ExecutionAdapterError::RoutingError(_))),
+            "The family is not registered yet"
+        );
+
+        registrar.register("test", "1.0");
+
+        let result = block_on(adapter.execute(make_transaction(), [0; 16]))
+            .expect("The family is registered");
+        assert_eq!(TransactionStatus::Valid, result.status);
+    }
+
+    fn make_transaction() -> TransactionPair {
+        make_transaction_with_nonce(vec![])
+    }
+
+    fn make_transaction_with_nonce(nonce: Vec<u8>) -> TransactionPair {
+        let signer = HashSigner::new();
+
+        TransactionBuilder::new()
+            .with_batcher_public_key(vec![])
+            .with_dependencies(vec![vec![]])
+            .with_family_name("test".to_string())
+            .with_family_version("1.0".to_string())
+            .with_inputs(vec![vec![]])
+            .with_outputs(vec![vec![]])
+            .with_nonce(nonce)
+            .with_payload(vec![])
+            .with_payload_hash_method(HashMethod::SHA512)
+            .build_pair(&signer)
+            .expect("The TransactionBuilder was supplied all the options")
+    }
+
+    /// Bridging an `ExecutionAdapter` to an `AsyncExecutionAdapter` and back conforms.
+    #[test]
+    fn test_conformance_of_bridges() {
+        ConformanceSuite::new(
+            || {
+                let adapter = TestExecutionAdapter::new();
+                adapter.register("test", "1.0");
+                Box::new(AsyncExecutionAdapterBridge::new(Box::new(
+                    CallbackExecutionAdapterBridge::new(Box::new(adapter)),
+                )))
+            },
+            |i| make_transaction_with_nonce(i.to_string().into_bytes()),
+        )
+        .run()
+        .expect("The bridged TestExecutionAdapter does not conform");
+    }
+}
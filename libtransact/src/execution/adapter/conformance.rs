@@ -0,0 +1,530 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! A conformance suite for `ExecutionAdapter` implementations.
+//!
+//! Implementers run the suite from their own tests, giving it a way to create their
+//! adapter and transactions that the adapter is able to execute:
+//!
+//! ```ignore
+//! ConformanceSuite::new(|| Box::new(MyExecutionAdapter::new()), create_transaction)
+//!     .with_unsupported_transaction(create_other_transaction())
+//!     .run()
+//!     .expect("MyExecutionAdapter does not conform");
+//! ```
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::context::ContextId;
+use crate::execution::adapter::{
+    ExecutionAdapter, ExecutionAdapterError, ExecutionResult, TransactionFamily,
+};
+use crate::transaction::TransactionPair;
+
+/// How long the suite waits on the adapter for anything it expects.
+pub const DEFAULT_CONFORMANCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many transactions are executed without waiting on their results.
+const OUTSTANDING_EXECUTIONS: usize = 8;
+
+/// How long to watch for an `on_done` callback being fired more than once.
+const REPEAT_CALLBACK_GRACE: Duration = Duration::from_millis(100);
+
+/// A way in which an `ExecutionAdapter` does not conform.
+#[derive(Debug)]
+pub struct ConformanceFailure {
+    check: &'static str,
+    message: String,
+}
+
+impl ConformanceFailure {
+    fn new(check: &'static str, message: String) -> Self {
+        ConformanceFailure { check, message }
+    }
+
+    /// The name of the check that failed.
+    pub fn check(&self) -> &str {
+        self.check
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.check, self.message)
+    }
+}
+
+impl Error for ConformanceFailure {
+    fn description(&self) -> &str {
+        "The ExecutionAdapter does not conform."
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        None
+    }
+}
+
+type DoneReceiver = Receiver<Result<ExecutionResult, ExecutionAdapterError>>;
+
+/// Checks that an `ExecutionAdapter` behaves as the `Executer` relies on:
+///
+/// * `registration` - the family of the supported transactions is registered, including
+///   with a callback given after the family became available
+/// * `execution` - a supported transaction's `on_done` is fired exactly once, with its
+///   result
+/// * `outstanding_execution` - transactions executed without waiting on each other, each
+///   in its own context, each receive their own result
+/// * `routing_error` - an unsupported transaction is handed back in a `RoutingError`
+/// * `stop` - the adapter stops, returning true
+///
+/// Each check is run against a newly created adapter.
+pub struct ConformanceSuite {
+    adapter_factory: Box<dyn Fn() -> Box<dyn ExecutionAdapter>>,
+    transaction_factory: Box<dyn Fn(usize) -> TransactionPair>,
+    unsupported_transaction: Option<TransactionPair>,
+    context_id: ContextId,
+    timeout: Duration,
+}
+
+impl ConformanceSuite {
+    /// `transaction_factory` creates a distinct transaction, of a family that the adapter
+    /// registers, for each index given to it.
+    pub fn new<A, T>(adapter_factory: A, transaction_factory: T) -> Self
+    where
+        A: Fn() -> Box<dyn ExecutionAdapter> + 'static,
+        T: Fn(usize) -> TransactionPair + 'static,
+    {
+        ConformanceSuite {
+            adapter_factory: Box::new(adapter_factory),
+            transaction_factory: Box::new(transaction_factory),
+            unsupported_transaction: None,
+            context_id: [0; 16],
+            timeout: DEFAULT_CONFORMANCE_TIMEOUT,
+        }
+    }
+
+    /// A transaction of a family that the adapter does not register. Without one the
+    /// `routing_error` check is skipped.
+    pub fn with_unsupported_transaction(mut self, transaction_pair: TransactionPair) -> Self {
+        self.unsupported_transaction = Some(transaction_pair);
+        self
+    }
+
+    /// The context to execute the transactions in. Outstanding executions each get their
+    /// own, this context with its last byte offset by their index.
+    pub fn with_context_id(mut self, context_id: ContextId) -> Self {
+        self.context_id = context_id;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run every check, stopping at the first failure.
+    pub fn run(&self) -> Result<(), ConformanceFailure> {
+        self.check_registration()?;
+        self.check_execution()?;
+        self.check_outstanding_execution()?;
+        self.check_routing_error()?;
+        self.check_stop()
+    }
+
+    pub fn check_registration(&self) -> Result<(), ConformanceFailure> {
+        let check = "registration";
+        let adapter = (self.adapter_factory)();
+        self.await_registration(check, adapter.as_ref())?;
+
+        // A callback given later is caught up on the families already registered.
+        self.await_registration(check, adapter.as_ref())?;
+
+        self.stop(check, adapter)
+    }
+
+    pub fn check_execution(&self) -> Result<(), ConformanceFailure> {
+        let check = "execution";
+        let adapter = (self.adapter_factory)();
+        self.await_registration(check, adapter.as_ref())?;
+
+        let transaction_pair = (self.transaction_factory)(0);
+        let transaction_id = transaction_pair
+            .transaction()
+            .header_signature()
+            .to_string();
+        let receiver = self.execute(adapter.as_ref(), transaction_pair, self.context_id);
+
+        match self.await_done(check, &receiver)? {
+            Ok(result) => {
+                if result.transaction_id != transaction_id {
+                    return Err(ConformanceFailure::new(
+                        check,
+                        format!(
+                            "The result is for {}, not {}",
+                            result.transaction_id, transaction_id
+                        ),
+                    ));
+                }
+            }
+            Err(err) => {
+                return Err(ConformanceFailure::new(
+                    check,
+                    format!("A supported transaction failed with {:?}", err),
+                ));
+            }
+        }
+
+        match receiver.recv_timeout(REPEAT_CALLBACK_GRACE) {
+            Ok(_) => Err(ConformanceFailure::new(
+                check,
+                "on_done was fired more than once".to_string(),
+            )),
+            Err(_) => self.stop(check, adapter),
+        }
+    }
+
+    pub fn check_outstanding_execution(&self) -> Result<(), ConformanceFailure> {
+        let check = "outstanding_execution";
+        let adapter = (self.adapter_factory)();
+        self.await_registration(check, adapter.as_ref())?;
+
+        let mut expected: Vec<String> = vec![];
+        let mut receivers = vec![];
+        for i in 0..OUTSTANDING_EXECUTIONS {
+            let transaction_pair = (self.transaction_factory)(i);
+            expected.push(
+                transaction_pair
+                    .transaction()
+                    .header_signature()
+                    .to_string(),
+            );
+            receivers.push(self.execute(
+                adapter.as_ref(),
+                transaction_pair,
+                self.outstanding_context_id(i),
+            ));
+        }
+
+        if expected.iter().collect::<HashSet<_>>().len() != expected.len() {
+            return Err(ConformanceFailure::new(
+                check,
+                "The transaction factory must create distinct transactions".to_string(),
+            ));
+        }
+
+        for (transaction_id, receiver) in expected.iter().zip(receivers.iter()) {
+            match self.await_done(check, receiver)? {
+                Ok(ref result) if result.transaction_id == *transaction_id => (),
+                other => {
+                    return Err(ConformanceFailure::new(
+                        check,
+                        format!("{} received {:?}", transaction_id, other),
+                    ));
+                }
+            }
+        }
+
+        self.stop(check, adapter)
+    }
+
+    pub fn check_routing_error(&self) -> Result<(), ConformanceFailure> {
+        let check = "routing_error";
+        let transaction_pair = match &self.unsupported_transaction {
+            Some(transaction_pair) => transaction_pair.clone(),
+            None => return Ok(()),
+        };
+        let transaction_id = transaction_pair
+            .transaction()
+            .header_signature()
+            .to_string();
+
+        let adapter = (self.adapter_factory)();
+        self.await_registration(check, adapter.as_ref())?;
+
+        let receiver = self.execute(adapter.as_ref(), transaction_pair, self.context_id);
+        match self.await_done(check, &receiver)? {
+            Err(ExecutionAdapterError::RoutingError(ref returned))
+                if returned.transaction().header_signature() == transaction_id =>
+            {
+                self.stop(check, adapter)
+            }
+            other => Err(ConformanceFailure::new(
+                check,
+                format!(
+                    "An unsupported transaction received {:?} instead of a RoutingError",
+                    other
+                ),
+            )),
+        }
+    }
+
+    pub fn check_stop(&self) -> Result<(), ConformanceFailure> {
+        let check = "stop";
+        let adapter = (self.adapter_factory)();
+        self.await_registration(check, adapter.as_ref())?;
+        self.stop(check, adapter)
+    }
+
+    /// Wait for the family of the supported transactions to be registered with a new
+    /// callback.
+    fn await_registration(
+        &self,
+        check: &'static str,
+        adapter: &dyn ExecutionAdapter,
+    ) -> Result<(), ConformanceFailure> {
+        let transaction_family = TransactionFamily::from_pair(&(self.transaction_factory)(0));
+
+        let (sender, receiver) = channel();
+        adapter.on_register(Box::new(move |registration| {
+            let _ = sender.send(registration);
+        }));
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(remaining) {
+                Ok(registration) => {
+                    if registration.matches(&transaction_family) {
+                        return Ok(());
+                    }
+                }
+                Err(_) => {
+                    return Err(ConformanceFailure::new(
+                        check,
+                        format!("{:?} was never registered", transaction_family),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// The context of the outstanding execution with the index, as transactions executing
+    /// at the same time must have different contexts.
+    fn outstanding_context_id(&self, index: usize) -> ContextId {
+        let mut context_id = self.context_id;
+        context_id[15] = context_id[15].wrapping_add(index as u8);
+        context_id
+    }
+
+    fn execute(
+        &self,
+        adapter: &dyn ExecutionAdapter,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+    ) -> DoneReceiver {
+        let (sender, receiver) = channel();
+        adapter.execute(
+            transaction_pair,
+            context_id,
+            Box::new(move |result| {
+                let _ = sender.send(result);
+            }),
+        );
+        receiver
+    }
+
+    fn await_done(
+        &self,
+        check: &'static str,
+        receiver: &DoneReceiver,
+    ) -> Result<Result<ExecutionResult, ExecutionAdapterError>, ConformanceFailure> {
+        receiver.recv_timeout(self.timeout).map_err(|err| {
+            let message = match err {
+                RecvTimeoutError::Timeout => {
+                    format!("on_done was not fired within {:?}", self.timeout)
+                }
+                RecvTimeoutError::Disconnected => {
+                    "on_done was dropped without being fired".to_string()
+                }
+            };
+            ConformanceFailure::new(check, message)
+        })
+    }
+
+    /// Stop the adapter on another thread, so that an adapter that never stops fails the
+    /// check instead of hanging it.
+    fn stop(
+        &self,
+        check: &'static str,
+        adapter: Box<dyn ExecutionAdapter>,
+    ) -> Result<(), ConformanceFailure> {
+        let (sender, receiver) = channel();
+        thread::Builder::new()
+            .name("conformance_stop".to_string())
+            .spawn(move || {
+                let _ = sender.send(adapter.stop());
+            })
+            .map_err(|err| ConformanceFailure::new(check, err.to_string()))?;
+
+        match receiver.recv_timeout(self.timeout) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ConformanceFailure::new(
+                check,
+                "stop returned false".to_string(),
+            )),
+            Err(_) => Err(ConformanceFailure::new(
+                check,
+                format!("The adapter did not stop within {:?}", self.timeout),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::execution::adapter::test_adapter::{create_txn, TestExecutionAdapter};
+    use crate::execution::adapter::{
+        OnDoneCallback, OnRegisterCallback, OnUnregisterCallback, TransactionFamilyRegistration,
+        TransactionStatus,
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn test_adapter() -> Box<dyn ExecutionAdapter> {
+        let adapter = TestExecutionAdapter::new();
+        adapter.register("test", "1.0");
+        Box::new(adapter)
+    }
+
+    #[test]
+    fn test_conformance_of_test_adapter() {
+        ConformanceSuite::new(test_adapter, |i| create_txn("test", &i.to_string()))
+            .with_unsupported_transaction(create_txn("other", "0"))
+            .run()
+            .expect("The TestExecutionAdapter does not conform");
+    }
+
+    type HeldExecution = (TransactionPair, Box<OnDoneCallback>);
+
+    /// Fires every `on_done` from its own thread, in reverse order, once it holds two.
+    struct ThreadedExecutionAdapter {
+        held: Mutex<Vec<HeldExecution>>,
+        fire_twice: bool,
+        contexts: Arc<Mutex<Vec<ContextId>>>,
+    }
+
+    impl ExecutionAdapter for ThreadedExecutionAdapter {
+        fn on_register(&self, callback: Box<OnRegisterCallback>) {
+            let mut callback = callback;
+            callback(TransactionFamilyRegistration::from(
+                TransactionFamily::from_pair(&create_txn("test", "0")),
+            ));
+        }
+
+        fn on_unregister(&self, _callback: Box<OnUnregisterCallback>) {}
+
+        fn execute(
+            &self,
+            transaction_pair: TransactionPair,
+            context_id: ContextId,
+            on_done: Box<OnDoneCallback>,
+        ) {
+            self.contexts.lock().unwrap().push(context_id);
+            let mut held = self.held.lock().unwrap();
+            held.push((transaction_pair, on_done));
+            if held.len() < 2 && !self.fire_twice {
+                return;
+            }
+
+            let fire_twice = self.fire_twice;
+            let to_fire: Vec<_> = held.drain(..).rev().collect();
+            thread::spawn(move || {
+                for (transaction_pair, mut on_done) in to_fire {
+                    let result = ExecutionResult {
+                        transaction_id: transaction_pair
+                            .transaction()
+                            .header_signature()
+                            .to_string(),
+                        status: TransactionStatus::Valid,
+                        resource_usage: None,
+                    };
+                    if fire_twice {
+                        on_done(Ok(result.clone()));
+                    }
+                    on_done(Ok(result));
+                }
+            });
+        }
+
+        fn stop(self: Box<Self>) -> bool {
+            true
+        }
+    }
+
+    /// `on_done` may be fired from another thread, in any order, and must be fired once.
+    #[test]
+    fn test_conformance_failures() {
+        let contexts = Arc::new(Mutex::new(vec![]));
+        let suite = |fire_twice| {
+            let contexts = Arc::clone(&contexts);
+            ConformanceSuite::new(
+                move || {
+                    Box::new(ThreadedExecutionAdapter {
+                        held: Mutex::new(vec![]),
+                        fire_twice,
+                        contexts: Arc::clone(&contexts),
+                    })
+                },
+                |i| create_txn("test", &i.to_string()),
+            )
+            .with_timeout(Duration::from_secs(1))
+        };
+
+        suite(false)
+            .check_outstanding_execution()
+            .expect("Results from another thread, in any order, conform");
+        {
+            let contexts = contexts.lock().unwrap();
+            assert_eq!(OUTSTANDING_EXECUTIONS, contexts.len());
+            assert_eq!(
+                OUTSTANDING_EXECUTIONS,
+                contexts.iter().collect::<HashSet<_>>().len(),
+                "Outstanding executions have their own contexts"
+            );
+        }
+
+        let failure = suite(true)
+            .check_execution()
+            .expect_err("Firing on_done twice does not conform");
+        assert_eq!("execution", failure.check());
+
+        let failure = suite(false)
+            .check_execution()
+            .expect_err("A transaction held forever does not conform");
+        assert_eq!("execution", failure.check());
+    }
+
+    /// Every transaction of the outstanding executions must differ, not only the first.
+    #[test]
+    fn test_conformance_duplicate_transactions() {
+        let failure =
+            ConformanceSuite::new(test_adapter, |i| create_txn("test", &i.min(1).to_string()))
+                .check_outstanding_execution()
+                .expect_err("Duplicate transactions are refused");
+        assert_eq!("outstanding_execution", failure.check());
+    }
+}
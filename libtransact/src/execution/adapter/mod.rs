@@ -18,18 +18,54 @@
 //! Contains execution adapter components and interfaces that proxy the `Transaction`
 //! and its associated state.
 
+pub mod async_adapter;
+pub mod conformance;
 pub mod error;
+pub mod recording;
+pub mod resources;
+pub mod subprocess;
 #[cfg(test)]
 pub mod test_adapter;
 
+pub use crate::execution::adapter::async_adapter::{
+    AsyncExecutionAdapter, AsyncExecutionAdapterBridge, CallbackExecutionAdapterBridge,
+    ExecutionFuture,
+};
 pub use crate::execution::adapter::error::ExecutionAdapterError;
 
 use crate::context::ContextId;
+use crate::receipts::{Event, ResourceUsage, TransactionReceiptBuilder};
 use crate::transaction::TransactionPair;
+use semver::{Version, VersionReq};
+use std::collections::BTreeSet;
 
-pub type OnDoneCallback = FnMut(Result<ExecutionResult, ExecutionAdapterError>);
-pub type OnRegisterCallback = FnMut(TransactionFamily) + Send;
-pub type OnUnregisterCallback = FnMut(TransactionFamily) + Send;
+pub type OnDoneCallback =
+    dyn FnMut(Result<ExecutionResult, ExecutionAdapterError>) + Send + 'static;
+pub type OnRegisterCallback = dyn FnMut(TransactionFamilyRegistration) + Send;
+pub type OnUnregisterCallback = dyn FnMut(TransactionFamilyRegistration) + Send;
+
+/// Serves the state of a transaction's context to whatever executes the transaction, such
+/// as the handler processes of a `SubprocessExecutionAdapter`.
+pub trait ContextStateHandler: Send + Sync {
+    /// Get the values at `addresses`, leaving out the addresses that have no value.
+    fn get_state(
+        &self,
+        context_id: &ContextId,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, String>;
+
+    /// Set the value at each address.
+    fn set_state(
+        &self,
+        context_id: &ContextId,
+        entries: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), String>;
+
+    /// Add an event fired by the transaction.
+    fn add_event(&self, _context_id: &ContextId, _event: Event) -> Result<(), String> {
+        Err("Events are not supported".to_string())
+    }
+}
 
 /// Implementers of this trait proxy the transaction to the correct component to execute
 /// the transaction.
@@ -46,7 +82,8 @@ pub trait ExecutionAdapter: Send {
     ///
     ///
     /// The `on_done` callback is fired when the transaction returns from processing or there
-    /// is an error.
+    /// is an error. It is fired exactly once, and may be moved to and fired from another
+    /// thread, such as one of the adapter's own, after `execute` returns.
     fn execute(
         &self,
         transaction_pair: TransactionPair,
@@ -54,6 +91,12 @@ pub trait ExecutionAdapter: Send {
         on_done: Box<OnDoneCallback>,
     );
 
+    /// Abandon the execution of the transaction with the `ContextId`, if it is still
+    /// underway. The `Executer` drops any result that arrives for it afterwards, so
+    /// `on_done` need not be fired, and may be dropped instead. By default nothing is
+    /// abandoned.
+    fn cancel(&self, _context_id: ContextId) {}
+
     /// Stop the internal threads and the Executor will no longer call execute.
     fn stop(self: Box<Self>) -> bool;
 }
@@ -78,6 +121,107 @@ impl TransactionFamily {
             transaction_pair.header().family_version().to_string(),
         )
     }
+
+    pub fn family_name(&self) -> &str {
+        &self.family_name
+    }
+
+    pub fn family_version(&self) -> &str {
+        &self.family_version
+    }
+}
+
+/// The versions of a transaction family that an `ExecutionAdapter` is able to process.
+#[derive(Eq, PartialEq, Debug, Hash, Clone)]
+pub enum FamilyVersions {
+    /// Any of these versions, compared as exact strings.
+    Set(BTreeSet<String>),
+    /// Any version satisfying the semver requirement, such as "1.x" or ">=1.1, <2".
+    ///
+    /// Family versions that are missing a minor or patch component, such as "1.0", are
+    /// treated as if the missing components were 0.
+    Requirement(VersionReq),
+}
+
+impl FamilyVersions {
+    pub fn matches(&self, family_version: &str) -> bool {
+        match self {
+            FamilyVersions::Set(versions) => versions.contains(family_version),
+            FamilyVersions::Requirement(requirement) => parse_family_version(family_version)
+                .map(|version| requirement.matches(&version))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Parses a family version as semver, filling in a missing minor or patch component.
+fn parse_family_version(family_version: &str) -> Option<Version> {
+    let components = family_version.split('.').count();
+    let padded = match components {
+        1 => format!("{}.0.0", family_version),
+        2 => format!("{}.0", family_version),
+        _ => family_version.to_string(),
+    };
+    Version::parse(&padded).ok()
+}
+
+/// The `TransactionFamily` name along with the `FamilyVersions` that an
+/// `ExecutionAdapter` registers or unregisters.
+#[derive(Eq, PartialEq, Debug, Hash, Clone)]
+pub struct TransactionFamilyRegistration {
+    family_name: String,
+    family_versions: FamilyVersions,
+}
+
+impl TransactionFamilyRegistration {
+    pub fn new(family_name: String, family_versions: FamilyVersions) -> Self {
+        TransactionFamilyRegistration {
+            family_name,
+            family_versions,
+        }
+    }
+
+    /// A registration for a set of exact versions.
+    pub fn with_versions(family_name: String, family_versions: Vec<String>) -> Self {
+        Self::new(
+            family_name,
+            FamilyVersions::Set(family_versions.into_iter().collect()),
+        )
+    }
+
+    /// A registration for every version satisfying a semver requirement.
+    pub fn with_requirement(family_name: String, requirement: &str) -> Result<Self, semver::Error> {
+        Ok(Self::new(
+            family_name,
+            FamilyVersions::Requirement(VersionReq::parse(requirement)?),
+        ))
+    }
+
+    pub fn family_name(&self) -> &str {
+        &self.family_name
+    }
+
+    pub fn family_versions(&self) -> &FamilyVersions {
+        &self.family_versions
+    }
+
+    /// Whether a `TransactionPair` of the `TransactionFamily` can be routed to the
+    /// `ExecutionAdapter` that made this registration.
+    pub fn matches(&self, transaction_family: &TransactionFamily) -> bool {
+        self.family_name == transaction_family.family_name
+            && self
+                .family_versions
+                .matches(&transaction_family.family_version)
+    }
+}
+
+impl From<TransactionFamily> for TransactionFamilyRegistration {
+    fn from(transaction_family: TransactionFamily) -> Self {
+        Self::with_versions(
+            transaction_family.family_name,
+            vec![transaction_family.family_version],
+        )
+    }
 }
 
 /// An `InvalidTransaction` has information about why the transaction failed.
@@ -98,6 +242,11 @@ pub struct InvalidTransaction {
 pub enum TransactionStatus {
     Invalid(InvalidTransaction),
     Valid,
+    /// No `ExecutionAdapter` registered the transaction's family before the
+    /// `Executer`'s park timeout elapsed, so the transaction was never executed.
+    NoHandler(TransactionFamily),
+    /// The transaction's schedule was cancelled before the transaction had a result.
+    Cancelled,
 }
 
 /// The `ExecutionResult` provides the status for a given transaction.
@@ -105,4 +254,105 @@ pub enum TransactionStatus {
 pub struct ExecutionResult {
     pub transaction_id: String,
     pub status: TransactionStatus,
+    /// The resources used by the execution, for the transaction's receipt, if they were
+    /// counted.
+    pub resource_usage: Option<ResourceUsage>,
+}
+
+impl ExecutionResult {
+    /// A `TransactionReceiptBuilder` with the transaction id and, if it was counted, the
+    /// resource usage of this result. The state changes, events and data come from the
+    /// transaction's context, so are left to the caller.
+    pub fn receipt_builder<K, V>(&self) -> TransactionReceiptBuilder<K, V>
+    where
+        K: std::default::Default,
+        V: std::default::Default,
+    {
+        let builder =
+            TransactionReceiptBuilder::new().with_transaction_id(self.transaction_id.clone());
+        match &self.resource_usage {
+            Some(resource_usage) => builder.with_resource_usage(resource_usage.clone()),
+            None => builder,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::receipts::TransactionReceipt;
+    use std::time::Duration;
+
+    fn family(version: &str) -> TransactionFamily {
+        TransactionFamily::new("test".to_string(), version.to_string())
+    }
+
+    /// The receipt built from a result has its transaction id and resource usage.
+    #[test]
+    fn test_execution_result_receipt_builder() {
+        let resource_usage = ResourceUsage {
+            reads: 2,
+            writes: 1,
+            bytes_written: 4,
+            events: 0,
+            wall_time: Duration::from_micros(250),
+        };
+        let mut execution_result = ExecutionResult {
+            transaction_id: "1234".to_string(),
+            status: TransactionStatus::Valid,
+            resource_usage: Some(resource_usage.clone()),
+        };
+
+        let receipt: TransactionReceipt<String, Vec<u8>> = execution_result
+            .receipt_builder()
+            .build()
+            .expect("The builder was given the transaction id");
+        assert_eq!("1234", receipt.transaction_id);
+        assert_eq!(Some(resource_usage), receipt.resource_usage);
+
+        execution_result.resource_usage = None;
+        let receipt: TransactionReceipt<String, Vec<u8>> = execution_result
+            .receipt_builder()
+            .build()
+            .expect("The builder was given the transaction id");
+        assert_eq!(None, receipt.resource_usage);
+    }
+
+    #[test]
+    fn test_registration_version_set() {
+        let registration = TransactionFamilyRegistration::with_versions(
+            "test".to_string(),
+            vec!["1.0".to_string(), "1.1".to_string()],
+        );
+
+        assert!(registration.matches(&family("1.0")));
+        assert!(registration.matches(&family("1.1")));
+        assert!(!registration.matches(&family("1.2")));
+        assert!(!registration.matches(&TransactionFamily::new(
+            "other".to_string(),
+            "1.0".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_registration_semver_requirement() {
+        let registration =
+            TransactionFamilyRegistration::with_requirement("test".to_string(), "1.x")
+                .expect("The requirement is valid semver");
+
+        assert!(registration.matches(&family("1")));
+        assert!(registration.matches(&family("1.0")));
+        assert!(registration.matches(&family("1.7.3")));
+        assert!(!registration.matches(&family("2.0")));
+        assert!(
+            !registration.matches(&family("not-a-version")),
+            "Versions that are not semver never match a requirement"
+        );
+
+        assert!(
+            TransactionFamilyRegistration::with_requirement("test".to_string(), "one").is_err(),
+            "An invalid requirement is rejected"
+        );
+    }
 }
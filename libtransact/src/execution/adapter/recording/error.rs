@@ -0,0 +1,90 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use protobuf::ProtobufError;
+
+use crate::protos::ProtoConversionError;
+
+/// An error that may occur while reading a recording.
+#[derive(Debug)]
+pub enum RecordingError {
+    /// Reading the recording failed.
+    IoError(io::Error),
+    /// A recorded execution could not be decoded.
+    ProtobufError(ProtobufError),
+    /// A recorded execution is not valid.
+    ProtoConversionError(ProtoConversionError),
+    /// A recorded execution's length prefix exceeds `MAX_RECORD_SIZE`.
+    RecordTooLarge(usize),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingError::IoError(err) => write!(f, "IO Error: {}", err),
+            RecordingError::ProtobufError(err) => write!(f, "Protobuf Error: {}", err),
+            RecordingError::ProtoConversionError(err) => {
+                write!(f, "Proto Conversion Error: {}", err)
+            }
+            RecordingError::RecordTooLarge(length) => {
+                write!(f, "Recorded execution of {} bytes is too large", length)
+            }
+        }
+    }
+}
+
+impl Error for RecordingError {
+    fn description(&self) -> &str {
+        match self {
+            RecordingError::IoError(_) => "Reading the recording failed.",
+            RecordingError::ProtobufError(_) => "A recorded execution could not be decoded.",
+            RecordingError::ProtoConversionError(_) => "A recorded execution is not valid.",
+            RecordingError::RecordTooLarge(_) => "A recorded execution is too large.",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        match self {
+            RecordingError::IoError(err) => Some(err),
+            RecordingError::ProtobufError(err) => Some(err),
+            RecordingError::ProtoConversionError(err) => Some(err),
+            RecordingError::RecordTooLarge(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> Self {
+        RecordingError::IoError(err)
+    }
+}
+
+impl From<ProtobufError> for RecordingError {
+    fn from(err: ProtobufError) -> Self {
+        RecordingError::ProtobufError(err)
+    }
+}
+
+impl From<ProtoConversionError> for RecordingError {
+    fn from(err: ProtoConversionError) -> Self {
+        RecordingError::ProtoConversionError(err)
+    }
+}
@@ -0,0 +1,731 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! Recording of transaction executions, and their deterministic replay.
+//!
+//! A `RecordingExecutionAdapter` wraps any `ExecutionAdapter`, writing each transaction's
+//! result to a recording along with the state reads, state writes and events that went
+//! through a `RecordingStateHandler` while it executed. A `ReplayExecutionAdapter` then
+//! serves the recorded results without the original transaction processors, verifying
+//! that the state read when recording matches the state being replayed against.
+
+pub mod error;
+pub mod replay;
+
+pub use crate::execution::adapter::recording::error::RecordingError;
+pub use crate::execution::adapter::recording::replay::{
+    ReplayDivergence, ReplayExecutionAdapter, ReplayReport,
+};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use protobuf::Message;
+
+use crate::context::ContextId;
+use crate::execution::adapter::{
+    ContextStateHandler, ExecutionAdapter, ExecutionResult, InvalidTransaction, OnDoneCallback,
+    OnRegisterCallback, OnUnregisterCallback, TransactionFamily, TransactionStatus,
+};
+use crate::protos;
+use crate::protos::recording::{RecordedExecution_Status, RecordedStateRead, RecordedStateWrite};
+use crate::protos::{FromNative, FromProto, IntoNative, IntoProto, ProtoConversionError};
+use crate::receipts::Event;
+use crate::transaction::TransactionPair;
+
+/// The largest `RecordedExecution` a recording may hold, in bytes, so that a corrupt
+/// length prefix cannot make a reader allocate without bound.
+pub const MAX_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+/// A transaction's execution, as it happened when recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedExecution {
+    pub transaction_id: String,
+    pub transaction_family: TransactionFamily,
+    pub context_id: ContextId,
+    /// The state the transaction read before writing it, with `None` for addresses that
+    /// had no value. Each address is listed once.
+    pub reads: Vec<(String, Option<Vec<u8>>)>,
+    /// The state the transaction wrote, in the order it was written.
+    pub writes: Vec<(String, Vec<u8>)>,
+    pub events: Vec<Event>,
+    pub status: TransactionStatus,
+}
+
+impl FromProto<protos::recording::RecordedExecution> for RecordedExecution {
+    fn from_proto(
+        mut recorded: protos::recording::RecordedExecution,
+    ) -> Result<Self, ProtoConversionError> {
+        let transaction_family =
+            TransactionFamily::new(recorded.take_family_name(), recorded.take_family_version());
+
+        let status = match recorded.get_status() {
+            RecordedExecution_Status::VALID => TransactionStatus::Valid,
+            RecordedExecution_Status::INVALID => TransactionStatus::Invalid(InvalidTransaction {
+                error_message: recorded.take_error_message(),
+                error_data: recorded.take_error_data(),
+            }),
+            RecordedExecution_Status::NO_HANDLER => {
+                TransactionStatus::NoHandler(transaction_family.clone())
+            }
+            RecordedExecution_Status::CANCELLED => TransactionStatus::Cancelled,
+            RecordedExecution_Status::STATUS_UNSET => {
+                return Err(ProtoConversionError::InvalidTypeError(
+                    "Cannot convert RecordedExecution with status unset.".to_string(),
+                ));
+            }
+        };
+
+        let recorded_context_id = recorded.get_context_id();
+        if recorded_context_id.len() != std::mem::size_of::<ContextId>() {
+            return Err(ProtoConversionError::SerializationError(format!(
+                "A context id is {} bytes, not {}",
+                std::mem::size_of::<ContextId>(),
+                recorded_context_id.len()
+            )));
+        }
+        let mut context_id = ContextId::default();
+        context_id.copy_from_slice(recorded_context_id);
+
+        Ok(RecordedExecution {
+            transaction_id: recorded.take_transaction_id(),
+            transaction_family,
+            context_id,
+            reads: recorded
+                .take_reads()
+                .into_iter()
+                .map(|mut read| {
+                    let data = if read.get_found() {
+                        Some(read.take_data())
+                    } else {
+                        None
+                    };
+                    (read.take_address(), data)
+                })
+                .collect(),
+            writes: recorded
+                .take_writes()
+                .into_iter()
+                .map(|mut write| (write.take_address(), write.take_data()))
+                .collect(),
+            events: recorded
+                .take_events()
+                .into_iter()
+                .map(Event::from_proto)
+                .collect::<Result<Vec<Event>, ProtoConversionError>>()?,
+            status,
+        })
+    }
+}
+
+impl FromNative<RecordedExecution> for protos::recording::RecordedExecution {
+    fn from_native(recorded: RecordedExecution) -> Result<Self, ProtoConversionError> {
+        let mut proto_recorded = protos::recording::RecordedExecution::new();
+        proto_recorded.set_transaction_id(recorded.transaction_id);
+        proto_recorded.set_family_name(recorded.transaction_family.family_name().to_string());
+        proto_recorded.set_family_version(recorded.transaction_family.family_version().to_string());
+        proto_recorded.set_context_id(recorded.context_id.to_vec());
+        proto_recorded.set_reads(
+            recorded
+                .reads
+                .into_iter()
+                .map(|(address, data)| {
+                    let mut read = RecordedStateRead::new();
+                    read.set_address(address);
+                    if let Some(data) = data {
+                        read.set_found(true);
+                        read.set_data(data);
+                    }
+                    read
+                })
+                .collect(),
+        );
+        proto_recorded.set_writes(
+            recorded
+                .writes
+                .into_iter()
+                .map(|(address, data)| {
+                    let mut write = RecordedStateWrite::new();
+                    write.set_address(address);
+                    write.set_data(data);
+                    write
+                })
+                .collect(),
+        );
+        proto_recorded.set_events(
+            recorded
+                .events
+                .into_iter()
+                .map(protos::events::Event::from_native)
+                .collect::<Result<_, ProtoConversionError>>()?,
+        );
+
+        match recorded.status {
+            TransactionStatus::Valid => proto_recorded.set_status(RecordedExecution_Status::VALID),
+            TransactionStatus::Invalid(invalid) => {
+                proto_recorded.set_status(RecordedExecution_Status::INVALID);
+                proto_recorded.set_error_message(invalid.error_message);
+                proto_recorded.set_error_data(invalid.error_data);
+            }
+            TransactionStatus::NoHandler(_) => {
+                proto_recorded.set_status(RecordedExecution_Status::NO_HANDLER)
+            }
+            TransactionStatus::Cancelled => {
+                proto_recorded.set_status(RecordedExecution_Status::CANCELLED)
+            }
+        }
+        Ok(proto_recorded)
+    }
+}
+
+impl IntoProto<protos::recording::RecordedExecution> for RecordedExecution {}
+impl IntoNative<RecordedExecution> for protos::recording::RecordedExecution {}
+
+/// Read every `RecordedExecution` in a recording.
+pub fn read_recording<R: Read>(reader: R) -> Result<Vec<RecordedExecution>, RecordingError> {
+    let mut reader = BufReader::new(reader);
+    let mut recording = vec![];
+    loop {
+        let mut length = [0; 4];
+        match reader.read_exact(&mut length) {
+            Ok(()) => (),
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(recording),
+            Err(err) => return Err(err.into()),
+        }
+
+        let length = u32::from_be_bytes(length) as usize;
+        if length > MAX_RECORD_SIZE {
+            return Err(RecordingError::RecordTooLarge(length));
+        }
+
+        let mut bytes = vec![0; length];
+        reader.read_exact(&mut bytes)?;
+        let recorded: protos::recording::RecordedExecution = Message::parse_from_bytes(&bytes)?;
+        recording.push(recorded.into_native()?);
+    }
+}
+
+/// Read every `RecordedExecution` in a recording file.
+pub fn read_recording_file<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<RecordedExecution>, RecordingError> {
+    read_recording(File::open(path)?)
+}
+
+/// The state reads, state writes and events of a transaction that is executing.
+#[derive(Default)]
+struct StateAccesses {
+    reads: Vec<(String, Option<Vec<u8>>)>,
+    writes: Vec<(String, Vec<u8>)>,
+    events: Vec<Event>,
+}
+
+impl StateAccesses {
+    /// Whether `address` was already read or written, so that reading it does not show
+    /// the state from before the transaction.
+    fn has_accessed(&self, address: &str) -> bool {
+        self.reads.iter().any(|(read, _)| read == address)
+            || self.writes.iter().any(|(written, _)| written == address)
+    }
+}
+
+/// Writes `RecordedExecution`s, collecting the state accesses of each transaction by its
+/// context until its result is known.
+///
+/// Transactions executing at the same time must have different contexts.
+pub struct ExecutionRecorder {
+    writer: Mutex<Box<dyn Write + Send>>,
+    accesses: Mutex<HashMap<ContextId, StateAccesses>>,
+}
+
+impl ExecutionRecorder {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        ExecutionRecorder {
+            writer: Mutex::new(writer),
+            accesses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record to a new file, replacing any that exists at `path`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        Ok(Self::new(Box::new(BufWriter::new(File::create(path)?))))
+    }
+
+    fn with_accesses<F: FnOnce(&mut StateAccesses)>(&self, context_id: &ContextId, f: F) {
+        f(self
+            .accesses
+            .lock()
+            .expect("The state accesses lock is poisoned")
+            .entry(*context_id)
+            .or_default())
+    }
+
+    fn record_reads(
+        &self,
+        context_id: &ContextId,
+        addresses: &[String],
+        values: &[(String, Vec<u8>)],
+    ) {
+        self.with_accesses(context_id, |accesses| {
+            for address in addresses {
+                if accesses.has_accessed(address) {
+                    continue;
+                }
+                let value = values
+                    .iter()
+                    .find(|(found, _)| found == address)
+                    .map(|(_, value)| value.clone());
+                accesses.reads.push((address.clone(), value));
+            }
+        });
+    }
+
+    fn record_writes(&self, context_id: &ContextId, entries: &[(String, Vec<u8>)]) {
+        self.with_accesses(context_id, |accesses| {
+            accesses.writes.extend_from_slice(entries)
+        });
+    }
+
+    fn record_event(&self, context_id: &ContextId, event: &Event) {
+        self.with_accesses(context_id, |accesses| accesses.events.push(event.clone()));
+    }
+
+    /// Forget the state accesses of a transaction that will be executed again, or was
+    /// cancelled.
+    fn discard(&self, context_id: &ContextId) {
+        self.accesses
+            .lock()
+            .expect("The state accesses lock is poisoned")
+            .remove(context_id);
+    }
+
+    fn record(
+        &self,
+        transaction_family: TransactionFamily,
+        context_id: ContextId,
+        result: &ExecutionResult,
+    ) {
+        let accesses = self
+            .accesses
+            .lock()
+            .expect("The state accesses lock is poisoned")
+            .remove(&context_id)
+            .unwrap_or_default();
+
+        let recorded = RecordedExecution {
+            transaction_id: result.transaction_id.clone(),
+            transaction_family,
+            context_id,
+            reads: accesses.reads,
+            writes: accesses.writes,
+            events: accesses.events,
+            status: result.status.clone(),
+        };
+
+        if let Err(err) = self.write(recorded) {
+            warn!(
+                "Unable to record the execution of {}: {}",
+                result.transaction_id, err
+            );
+        }
+    }
+
+    fn write(&self, recorded: RecordedExecution) -> Result<(), io::Error> {
+        let bytes = recorded
+            .into_proto()
+            .and_then(|proto| {
+                proto
+                    .write_to_bytes()
+                    .map_err(|err| ProtoConversionError::SerializationError(err.to_string()))
+            })
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        if bytes.len() > MAX_RECORD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Recorded execution of {} bytes is too large", bytes.len()),
+            ));
+        }
+
+        // Flushed after every execution, so that the recording survives a crash.
+        let mut writer = self.writer.lock().expect("The writer lock is poisoned");
+        writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
+        writer.write_all(&bytes)?;
+        writer.flush()
+    }
+}
+
+/// Records the state accesses made through a `ContextStateHandler`.
+pub struct RecordingStateHandler {
+    inner: Arc<dyn ContextStateHandler>,
+    recorder: Arc<ExecutionRecorder>,
+}
+
+impl RecordingStateHandler {
+    pub fn new(inner: Arc<dyn ContextStateHandler>, recorder: Arc<ExecutionRecorder>) -> Self {
+        RecordingStateHandler { inner, recorder }
+    }
+}
+
+impl ContextStateHandler for RecordingStateHandler {
+    fn get_state(
+        &self,
+        context_id: &ContextId,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let values = self.inner.get_state(context_id, addresses)?;
+        self.recorder.record_reads(context_id, addresses, &values);
+        Ok(values)
+    }
+
+    fn set_state(
+        &self,
+        context_id: &ContextId,
+        entries: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), String> {
+        self.inner.set_state(context_id, entries.clone())?;
+        self.recorder.record_writes(context_id, &entries);
+        Ok(())
+    }
+
+    fn add_event(&self, context_id: &ContextId, event: Event) -> Result<(), String> {
+        self.inner.add_event(context_id, event.clone())?;
+        self.recorder.record_event(context_id, &event);
+        Ok(())
+    }
+}
+
+/// Records the result of every transaction executed by the wrapped `ExecutionAdapter`.
+///
+/// Transactions that are retried, after a `ExecutionAdapterError`, are recorded once they
+/// have a result.
+pub struct RecordingExecutionAdapter {
+    inner: Box<dyn ExecutionAdapter>,
+    recorder: Arc<ExecutionRecorder>,
+}
+
+impl RecordingExecutionAdapter {
+    pub fn new(inner: Box<dyn ExecutionAdapter>, recorder: Arc<ExecutionRecorder>) -> Self {
+        RecordingExecutionAdapter { inner, recorder }
+    }
+}
+
+impl ExecutionAdapter for RecordingExecutionAdapter {
+    fn on_register(&self, callback: Box<OnRegisterCallback>) {
+        self.inner.on_register(callback)
+    }
+
+    fn on_unregister(&self, callback: Box<OnUnregisterCallback>) {
+        self.inner.on_unregister(callback)
+    }
+
+    fn execute(
+        &self,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+        on_done: Box<OnDoneCallback>,
+    ) {
+        let mut on_done = on_done;
+        let recorder = Arc::clone(&self.recorder);
+        let transaction_family = TransactionFamily::from_pair(&transaction_pair);
+
+        self.inner.execute(
+            transaction_pair,
+            context_id,
+            Box::new(move |result| {
+                match &result {
+                    Ok(execution_result) => {
+                        recorder.record(transaction_family.clone(), context_id, execution_result)
+                    }
+                    Err(_) => recorder.discard(&context_id),
+                }
+                on_done(result)
+            }),
+        );
+    }
+
+    fn cancel(&self, context_id: ContextId) {
+        self.recorder.discard(&context_id);
+        self.inner.cancel(context_id)
+    }
+
+    fn stop(self: Box<Self>) -> bool {
+        self.inner.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::execution::adapter::conformance::ConformanceSuite;
+    use crate::execution::adapter::test_adapter::{
+        create_txn, TestContextState, TestExecutionAdapter,
+    };
+    use crate::execution::adapter::{ExecutionAdapterError, TransactionFamilyRegistration};
+    use std::env;
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    static FAMILY_NAME: &str = "counter";
+    static FAMILY_VERSION: &str = "1.0";
+    static COUNTER: &str = "counter";
+
+    /// Adds one to the counter, after failing its first transaction once.
+    struct CounterAdapter {
+        state: Arc<dyn ContextStateHandler>,
+        failed: AtomicBool,
+    }
+
+    impl ExecutionAdapter for CounterAdapter {
+        fn on_register(&self, _callback: Box<OnRegisterCallback>) {}
+
+        fn on_unregister(&self, _callback: Box<OnUnregisterCallback>) {}
+
+        fn execute(
+            &self,
+            transaction_pair: TransactionPair,
+            context_id: ContextId,
+            on_done: Box<OnDoneCallback>,
+        ) {
+            let mut on_done = on_done;
+            if !self.failed.swap(true, Ordering::SeqCst) {
+                self.state
+                    .get_state(&context_id, &["elsewhere".to_string()])
+                    .unwrap();
+                on_done(Err(ExecutionAdapterError::RoutingError(transaction_pair)));
+                return;
+            }
+
+            let read = |state: &dyn ContextStateHandler| {
+                state
+                    .get_state(&context_id, &[COUNTER.to_string()])
+                    .unwrap()
+                    .first()
+                    .map(|(_, value)| value[0])
+                    .unwrap_or(0)
+            };
+            let value = read(self.state.as_ref());
+            self.state
+                .set_state(&context_id, vec![(COUNTER.to_string(), vec![value + 1])])
+                .unwrap();
+            assert_eq!(value + 1, read(self.state.as_ref()));
+            self.state
+                .add_event(
+                    &context_id,
+                    Event {
+                        event_type: "incremented".to_string(),
+                        attributes: vec![],
+                        data: vec![value + 1],
+                    },
+                )
+                .unwrap();
+
+            on_done(Ok(ExecutionResult {
+                transaction_id: transaction_pair
+                    .transaction()
+                    .header_signature()
+                    .to_string(),
+                status: TransactionStatus::Valid,
+                resource_usage: None,
+            }));
+        }
+
+        fn stop(self: Box<Self>) -> bool {
+            true
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn execute(
+        adapter: &dyn ExecutionAdapter,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+    ) -> Result<ExecutionResult, ExecutionAdapterError> {
+        let (sender, receiver) = channel();
+        adapter.execute(
+            transaction_pair,
+            context_id,
+            Box::new(move |result| sender.send(result).unwrap()),
+        );
+        receiver.recv().expect("on_done was not called")
+    }
+
+    fn recording_path() -> String {
+        let mut path = env::temp_dir();
+        path.push(format!("recording-{:?}.bin", thread::current().id()));
+        path.to_str().unwrap().to_string()
+    }
+
+    /// A length prefix over `MAX_RECORD_SIZE` is rejected before anything is allocated.
+    #[test]
+    fn test_read_recording_too_large() {
+        let recording = (MAX_RECORD_SIZE as u32 + 1).to_be_bytes();
+        match read_recording(&recording[..]) {
+            Err(RecordingError::RecordTooLarge(length)) => {
+                assert_eq!(MAX_RECORD_SIZE + 1, length)
+            }
+            other => panic!("Expected RecordTooLarge, got {:?}", other.map(|r| r.len())),
+        }
+    }
+
+    /// Transactions recorded from a `CounterAdapter` replay with the same results and
+    /// effects on state, without the `CounterAdapter`.
+    #[test]
+    fn test_record_and_replay() {
+        let path = recording_path();
+        let recorder =
+            Arc::new(ExecutionRecorder::create(&path).expect("Unable to create the recording"));
+        let state = Arc::new(TestContextState::with_values(vec![(
+            COUNTER.to_string(),
+            vec![5],
+        )]));
+        let adapter = RecordingExecutionAdapter::new(
+            Box::new(CounterAdapter {
+                state: Arc::new(RecordingStateHandler::new(state, Arc::clone(&recorder))),
+                failed: AtomicBool::new(false),
+            }),
+            recorder,
+        );
+
+        let first = create_txn(FAMILY_NAME, "first");
+        let second = create_txn(FAMILY_NAME, "second");
+        match execute(&adapter, first.clone(), [1; 16]) {
+            Err(ExecutionAdapterError::RoutingError(pair)) => {
+                execute(&adapter, pair, [1; 16]).expect("The retry was not executed");
+            }
+            other => panic!("Expected a RoutingError, got {:?}", other),
+        }
+        execute(&adapter, second.clone(), [2; 16]).expect("The transaction was not executed");
+        assert!(Box::new(adapter).stop());
+
+        let recording = read_recording_file(&path).expect("Unable to read the recording");
+        assert_eq!(2, recording.len());
+        assert_eq!(
+            RecordedExecution {
+                transaction_id: first.transaction().header_signature().to_string(),
+                transaction_family: TransactionFamily::new(
+                    FAMILY_NAME.to_string(),
+                    FAMILY_VERSION.to_string()
+                ),
+                context_id: [1; 16],
+                // The retried attempt's read is discarded, and the read after the write
+                // is not state from before the transaction.
+                reads: vec![(COUNTER.to_string(), Some(vec![5]))],
+                writes: vec![(COUNTER.to_string(), vec![6])],
+                events: vec![Event {
+                    event_type: "incremented".to_string(),
+                    attributes: vec![],
+                    data: vec![6],
+                }],
+                status: TransactionStatus::Valid,
+            },
+            recording[0]
+        );
+        assert_eq!(
+            vec![(COUNTER.to_string(), Some(vec![6]))],
+            recording[1].reads
+        );
+
+        let replay_state = Arc::new(TestContextState::with_values(vec![(
+            COUNTER.to_string(),
+            vec![5],
+        )]));
+        let replay = ReplayExecutionAdapter::from_file(&path)
+            .expect("Unable to read the recording")
+            .with_state_handler(replay_state.clone());
+        let report = replay.report();
+
+        let (sender, receiver) = channel();
+        replay.on_register(Box::new(move |registration| {
+            sender.send(registration).unwrap()
+        }));
+        assert_eq!(
+            vec![TransactionFamilyRegistration::with_versions(
+                FAMILY_NAME.to_string(),
+                vec![FAMILY_VERSION.to_string()]
+            )],
+            receiver.try_iter().collect::<Vec<_>>()
+        );
+
+        for (transaction_pair, context_id) in [(first, [1; 16]), (second, [2; 16])] {
+            let result = execute(&replay, transaction_pair, context_id)
+                .expect("The transaction was not replayed");
+            assert_eq!(TransactionStatus::Valid, result.status);
+        }
+        assert!(report.is_clean(), "{:?}", report.divergences());
+        assert_eq!(Some(vec![7]), replay_state.value(COUNTER));
+        assert_eq!(2, replay_state.events().len());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// The accesses of a cancelled transaction are not recorded with the next execution
+    /// on its context.
+    #[test]
+    fn test_recording_cancel_discards_accesses() {
+        let recorder = Arc::new(ExecutionRecorder::new(Box::new(io::sink())));
+        let state = RecordingStateHandler::new(
+            Arc::new(TestContextState::with_values(vec![(
+                COUNTER.to_string(),
+                vec![5],
+            )])),
+            Arc::clone(&recorder),
+        );
+        let adapter = RecordingExecutionAdapter::new(
+            Box::new(TestExecutionAdapter::new()),
+            Arc::clone(&recorder),
+        );
+
+        state
+            .get_state(&[1; 16], &[COUNTER.to_string()])
+            .expect("Unable to read state");
+        assert!(recorder
+            .accesses
+            .lock()
+            .expect("The state accesses lock is poisoned")
+            .contains_key(&[1; 16]));
+
+        adapter.cancel([1; 16]);
+        assert!(recorder
+            .accesses
+            .lock()
+            .expect("The state accesses lock is poisoned")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_conformance_of_recording_adapter() {
+        ConformanceSuite::new(
+            || {
+                let adapter = TestExecutionAdapter::new();
+                adapter.register(FAMILY_NAME, FAMILY_VERSION);
+                Box::new(RecordingExecutionAdapter::new(
+                    Box::new(adapter),
+                    Arc::new(ExecutionRecorder::new(Box::new(io::sink()))),
+                ))
+            },
+            |i| create_txn(FAMILY_NAME, &i.to_string()),
+        )
+        .run()
+        .expect("The RecordingExecutionAdapter does not conform");
+    }
+}
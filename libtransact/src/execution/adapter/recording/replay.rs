@@ -0,0 +1,361 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::context::ContextId;
+use crate::execution::adapter::recording::{
+    read_recording_file, RecordedExecution, RecordingError,
+};
+use crate::execution::adapter::{
+    ContextStateHandler, ExecutionAdapter, ExecutionAdapterError, ExecutionResult,
+    InvalidTransaction, OnDoneCallback, OnRegisterCallback, OnUnregisterCallback,
+    TransactionFamily, TransactionFamilyRegistration, TransactionStatus,
+};
+use crate::transaction::TransactionPair;
+
+/// A way in which a replay differed from the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayDivergence {
+    /// The transaction is not in the recording, though its family is, so it was found
+    /// invalid.
+    NotRecorded { transaction_id: String },
+    /// The transaction read a different value when it was recorded, with `None` for an
+    /// address that had no value.
+    ReadMismatch {
+        transaction_id: String,
+        address: String,
+        recorded: Option<Vec<u8>>,
+        replayed: Option<Vec<u8>>,
+    },
+    /// The state being replayed against could not be read or written.
+    StateError {
+        transaction_id: String,
+        error: String,
+    },
+}
+
+/// The divergences found while replaying, shared with the `ReplayExecutionAdapter` so it
+/// can be inspected after the adapter is given to an `Executer`.
+#[derive(Clone, Default)]
+pub struct ReplayReport {
+    divergences: Arc<Mutex<Vec<ReplayDivergence>>>,
+}
+
+impl ReplayReport {
+    pub fn divergences(&self) -> Vec<ReplayDivergence> {
+        self.lock().clone()
+    }
+
+    /// Whether the replay has matched the recording so far.
+    pub fn is_clean(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn add(&self, divergence: ReplayDivergence) {
+        warn!("Replay diverged from the recording: {:?}", divergence);
+        self.lock().push(divergence);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ReplayDivergence>> {
+        self.divergences
+            .lock()
+            .expect("The replay divergences lock is poisoned")
+    }
+}
+
+/// Serves the results of a recording in place of the original transaction processors.
+///
+/// Given a `ContextStateHandler`, the state each transaction read when recorded is
+/// compared with the state it is replayed against, and the recorded writes and events
+/// are applied, so that later transactions see the state they saw when recorded. A
+/// transaction is given its recorded result even if its reads do not match.
+pub struct ReplayExecutionAdapter {
+    recorded: Mutex<HashMap<String, VecDeque<RecordedExecution>>>,
+    registrations: Vec<TransactionFamilyRegistration>,
+    state_handler: Option<Arc<dyn ContextStateHandler>>,
+    report: ReplayReport,
+}
+
+impl ReplayExecutionAdapter {
+    pub fn new(recording: Vec<RecordedExecution>) -> Self {
+        let mut registrations: Vec<TransactionFamilyRegistration> = vec![];
+        let mut recorded: HashMap<String, VecDeque<RecordedExecution>> = HashMap::new();
+        for execution in recording {
+            let registration =
+                TransactionFamilyRegistration::from(execution.transaction_family.clone());
+            if !registrations.contains(&registration) {
+                registrations.push(registration);
+            }
+            recorded
+                .entry(execution.transaction_id.clone())
+                .or_default()
+                .push_back(execution);
+        }
+
+        ReplayExecutionAdapter {
+            recorded: Mutex::new(recorded),
+            registrations,
+            state_handler: None,
+            report: ReplayReport::default(),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, RecordingError> {
+        Ok(Self::new(read_recording_file(path)?))
+    }
+
+    /// Verify the recorded reads against, and apply the recorded writes and events to,
+    /// the state of each transaction's context.
+    pub fn with_state_handler(mut self, state_handler: Arc<dyn ContextStateHandler>) -> Self {
+        self.state_handler = Some(state_handler);
+        self
+    }
+
+    pub fn report(&self) -> ReplayReport {
+        self.report.clone()
+    }
+
+    fn replay_state(
+        &self,
+        state_handler: &dyn ContextStateHandler,
+        context_id: &ContextId,
+        recorded: RecordedExecution,
+    ) -> Result<(), String> {
+        let addresses: Vec<String> = recorded
+            .reads
+            .iter()
+            .map(|(address, _)| address.clone())
+            .collect();
+        let values = state_handler.get_state(context_id, &addresses)?;
+
+        for (address, recorded_value) in recorded.reads {
+            let replayed = values
+                .iter()
+                .find(|(found, _)| *found == address)
+                .map(|(_, value)| value.clone());
+            if replayed != recorded_value {
+                self.report.add(ReplayDivergence::ReadMismatch {
+                    transaction_id: recorded.transaction_id.clone(),
+                    address,
+                    recorded: recorded_value,
+                    replayed,
+                });
+            }
+        }
+
+        if !recorded.writes.is_empty() {
+            state_handler.set_state(context_id, recorded.writes)?;
+        }
+        for event in recorded.events {
+            state_handler.add_event(context_id, event)?;
+        }
+        Ok(())
+    }
+}
+
+impl ExecutionAdapter for ReplayExecutionAdapter {
+    /// Every family in the recording is registered, for as long as the adapter runs.
+    fn on_register(&self, callback: Box<OnRegisterCallback>) {
+        let mut callback = callback;
+        for registration in &self.registrations {
+            callback(registration.clone());
+        }
+    }
+
+    fn on_unregister(&self, _callback: Box<OnUnregisterCallback>) {}
+
+    fn execute(
+        &self,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+        on_done: Box<OnDoneCallback>,
+    ) {
+        let mut on_done = on_done;
+
+        let transaction_family = TransactionFamily::from_pair(&transaction_pair);
+        if !self
+            .registrations
+            .iter()
+            .any(|registration| registration.matches(&transaction_family))
+        {
+            on_done(Err(ExecutionAdapterError::RoutingError(transaction_pair)));
+            return;
+        }
+
+        let transaction_id = transaction_pair
+            .transaction()
+            .header_signature()
+            .to_string();
+
+        let recorded = self
+            .recorded
+            .lock()
+            .expect("The recorded executions lock is poisoned")
+            .get_mut(&transaction_id)
+            .and_then(VecDeque::pop_front);
+
+        let status = match recorded {
+            Some(recorded) => {
+                let status = recorded.status.clone();
+                if let Some(state_handler) = &self.state_handler {
+                    if let Err(error) =
+                        self.replay_state(state_handler.as_ref(), &context_id, recorded)
+                    {
+                        self.report.add(ReplayDivergence::StateError {
+                            transaction_id: transaction_id.clone(),
+                            error,
+                        });
+                    }
+                }
+                status
+            }
+            None => {
+                self.report.add(ReplayDivergence::NotRecorded {
+                    transaction_id: transaction_id.clone(),
+                });
+                TransactionStatus::Invalid(InvalidTransaction {
+                    error_message: "The transaction is not in the recording".to_string(),
+                    error_data: vec![],
+                })
+            }
+        };
+
+        on_done(Ok(ExecutionResult {
+            transaction_id,
+            status,
+            resource_usage: None,
+        }));
+    }
+
+    fn stop(self: Box<Self>) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::execution::adapter::conformance::ConformanceSuite;
+    use crate::execution::adapter::test_adapter::{create_txn, TestContextState};
+    use std::sync::mpsc::channel;
+
+    static FAMILY_NAME: &str = "replay";
+
+    fn replay(
+        adapter: &ReplayExecutionAdapter,
+        transaction_pair: TransactionPair,
+    ) -> TransactionStatus {
+        let (sender, receiver) = channel();
+        adapter.execute(
+            transaction_pair,
+            [0; 16],
+            Box::new(move |result| sender.send(result).unwrap()),
+        );
+        receiver
+            .recv()
+            .expect("on_done was not called")
+            .expect("Replay never fails")
+            .status
+    }
+
+    /// Reads that differ from the recording, and transactions missing from it, are
+    /// reported.
+    #[test]
+    fn test_replay_divergence() {
+        let recorded_txn = create_txn(FAMILY_NAME, "recorded");
+        let invalid = TransactionStatus::Invalid(InvalidTransaction {
+            error_message: "Too high".to_string(),
+            error_data: vec![1],
+        });
+
+        let state = Arc::new(TestContextState::with_values(vec![(
+            "a".to_string(),
+            vec![2],
+        )]));
+        let adapter = ReplayExecutionAdapter::new(vec![RecordedExecution {
+            transaction_id: recorded_txn.transaction().header_signature().to_string(),
+            transaction_family: TransactionFamily::new(FAMILY_NAME.to_string(), "1.0".to_string()),
+            context_id: [9; 16],
+            reads: vec![("a".to_string(), Some(vec![1])), ("b".to_string(), None)],
+            writes: vec![("a".to_string(), vec![3])],
+            events: vec![],
+            status: invalid.clone(),
+        }])
+        .with_state_handler(state.clone());
+        let report = adapter.report();
+
+        assert_eq!(invalid, replay(&adapter, recorded_txn.clone()));
+        assert_eq!(Some(vec![3]), state.value("a"));
+
+        // Each recorded execution is replayed once.
+        match replay(&adapter, recorded_txn.clone()) {
+            TransactionStatus::Invalid(_) => (),
+            status => panic!("Expected an invalid transaction, got {:?}", status),
+        }
+
+        let transaction_id = recorded_txn.transaction().header_signature().to_string();
+        assert_eq!(
+            vec![
+                ReplayDivergence::ReadMismatch {
+                    transaction_id: transaction_id.clone(),
+                    address: "a".to_string(),
+                    recorded: Some(vec![1]),
+                    replayed: Some(vec![2]),
+                },
+                ReplayDivergence::NotRecorded { transaction_id },
+            ],
+            report.divergences()
+        );
+        assert!(!report.is_clean());
+    }
+
+    fn record(transaction_pair: &TransactionPair) -> RecordedExecution {
+        RecordedExecution {
+            transaction_id: transaction_pair
+                .transaction()
+                .header_signature()
+                .to_string(),
+            transaction_family: TransactionFamily::from_pair(transaction_pair),
+            context_id: [0; 16],
+            reads: vec![],
+            writes: vec![],
+            events: vec![],
+            status: TransactionStatus::Valid,
+        }
+    }
+
+    #[test]
+    fn test_conformance_of_replay_adapter() {
+        ConformanceSuite::new(
+            || {
+                Box::new(ReplayExecutionAdapter::new(
+                    (0..8)
+                        .map(|i| record(&create_txn(FAMILY_NAME, &i.to_string())))
+                        .collect(),
+                ))
+            },
+            |i| create_txn(FAMILY_NAME, &i.to_string()),
+        )
+        .with_unsupported_transaction(create_txn("other", "0"))
+        .run()
+        .expect("The ReplayExecutionAdapter does not conform");
+    }
+}
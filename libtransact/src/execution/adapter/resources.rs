@@ -0,0 +1,780 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! Accounting and limits for the resources a transaction uses while it executes.
+//!
+//! A `MeteredStateHandler` counts the state reads, state writes, bytes written and events
+//! that go through a `ContextStateHandler`, refusing those over the limits of the
+//! transaction's family. A `MeteredExecutionAdapter` wraps any `ExecutionAdapter`, timing
+//! each execution and turning a violated limit into a `TransactionStatus::Invalid`, whose
+//! extended data is the encoded `ResourceLimitExceeded`. Either way, the counts are given
+//! to the receipt as the `ExecutionResult`'s `resource_usage`.
+//!
+//! The wall time limit is not a deadline: a transaction over it is refused further state
+//! accesses, but is only made invalid once its `ExecutionAdapter` returns a result.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use protobuf::Message;
+
+use crate::context::ContextId;
+use crate::execution::adapter::{
+    ContextStateHandler, ExecutionAdapter, ExecutionResult, InvalidTransaction, OnDoneCallback,
+    OnRegisterCallback, OnUnregisterCallback, TransactionStatus,
+};
+use crate::protos;
+use crate::protos::transaction_receipt::ResourceLimitExceeded_Resource;
+use crate::protos::{FromNative, FromProto, IntoNative, IntoProto, ProtoConversionError};
+use crate::receipts::{Event, ResourceUsage};
+use crate::transaction::TransactionPair;
+
+/// A resource counted in `ResourceUsage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Reads,
+    Writes,
+    BytesWritten,
+    Events,
+    /// Counted in microseconds.
+    WallTime,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Resource::Reads => write!(f, "state reads"),
+            Resource::Writes => write!(f, "state writes"),
+            Resource::BytesWritten => write!(f, "bytes written"),
+            Resource::Events => write!(f, "events"),
+            Resource::WallTime => write!(f, "microseconds of wall time"),
+        }
+    }
+}
+
+/// The most of each resource a transaction may use. Resources without a limit are not
+/// limited.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceLimits {
+    max_reads: Option<u64>,
+    max_writes: Option<u64>,
+    max_bytes_written: Option<u64>,
+    max_events: Option<u64>,
+    max_wall_time: Option<Duration>,
+}
+
+impl ResourceLimits {
+    pub fn new() -> Self {
+        ResourceLimits::default()
+    }
+
+    pub fn with_max_reads(mut self, max_reads: u64) -> Self {
+        self.max_reads = Some(max_reads);
+        self
+    }
+
+    pub fn with_max_writes(mut self, max_writes: u64) -> Self {
+        self.max_writes = Some(max_writes);
+        self
+    }
+
+    pub fn with_max_bytes_written(mut self, max_bytes_written: u64) -> Self {
+        self.max_bytes_written = Some(max_bytes_written);
+        self
+    }
+
+    pub fn with_max_events(mut self, max_events: u64) -> Self {
+        self.max_events = Some(max_events);
+        self
+    }
+
+    /// Limit how long the transaction may take to execute. This does not cut a long
+    /// execution short: state accesses after the limit are refused, but the transaction is
+    /// only made invalid once the adapter finishes executing it.
+    pub fn with_max_wall_time(mut self, max_wall_time: Duration) -> Self {
+        self.max_wall_time = Some(max_wall_time);
+        self
+    }
+
+    /// The first limit that `usage` exceeds, if any.
+    pub fn check(&self, usage: &ResourceUsage) -> Option<ResourceLimitExceeded> {
+        let counts = [
+            (Resource::Reads, self.max_reads, usage.reads),
+            (Resource::Writes, self.max_writes, usage.writes),
+            (
+                Resource::BytesWritten,
+                self.max_bytes_written,
+                usage.bytes_written,
+            ),
+            (Resource::Events, self.max_events, usage.events),
+            (
+                Resource::WallTime,
+                self.max_wall_time.map(|limit| limit.as_micros() as u64),
+                usage.wall_time.as_micros() as u64,
+            ),
+        ];
+
+        counts
+            .iter()
+            .find_map(|(resource, limit, used)| match limit {
+                Some(limit) if used > limit => Some(ResourceLimitExceeded {
+                    resource: *resource,
+                    limit: *limit,
+                    used: *used,
+                }),
+                _ => None,
+            })
+    }
+}
+
+/// Why a transaction was made invalid for using too much of a resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceLimitExceeded {
+    pub resource: Resource,
+    pub limit: u64,
+    /// How much had been used when the limit was found exceeded. For a refused state
+    /// access, this includes the refused access.
+    pub used: u64,
+}
+
+impl ResourceLimitExceeded {
+    /// The `ResourceLimitExceeded` that made the transaction invalid, if it was a resource
+    /// limit that did.
+    pub fn from_invalid_transaction(invalid_transaction: &InvalidTransaction) -> Option<Self> {
+        let proto: protos::transaction_receipt::ResourceLimitExceeded =
+            Message::parse_from_bytes(&invalid_transaction.error_data).ok()?;
+        proto.into_native().ok()
+    }
+}
+
+impl fmt::Display for ResourceLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Exceeded the limit of {} {} with {}",
+            self.limit, self.resource, self.used
+        )
+    }
+}
+
+impl From<ResourceLimitExceeded> for InvalidTransaction {
+    fn from(exceeded: ResourceLimitExceeded) -> Self {
+        let error_message = exceeded.to_string();
+        let error_data = exceeded
+            .into_proto()
+            .and_then(|proto| {
+                proto
+                    .write_to_bytes()
+                    .map_err(|err| ProtoConversionError::SerializationError(err.to_string()))
+            })
+            .unwrap_or_else(|err| {
+                warn!("Unable to encode a ResourceLimitExceeded: {}", err);
+                vec![]
+            });
+
+        InvalidTransaction {
+            error_message,
+            error_data,
+        }
+    }
+}
+
+impl FromProto<protos::transaction_receipt::ResourceLimitExceeded> for ResourceLimitExceeded {
+    fn from_proto(
+        exceeded: protos::transaction_receipt::ResourceLimitExceeded,
+    ) -> Result<Self, ProtoConversionError> {
+        let resource = match exceeded.get_resource() {
+            ResourceLimitExceeded_Resource::READS => Resource::Reads,
+            ResourceLimitExceeded_Resource::WRITES => Resource::Writes,
+            ResourceLimitExceeded_Resource::BYTES_WRITTEN => Resource::BytesWritten,
+            ResourceLimitExceeded_Resource::EVENTS => Resource::Events,
+            ResourceLimitExceeded_Resource::WALL_TIME_MICROS => Resource::WallTime,
+            ResourceLimitExceeded_Resource::RESOURCE_UNSET => {
+                return Err(ProtoConversionError::InvalidTypeError(
+                    "Cannot convert ResourceLimitExceeded with resource unset".to_string(),
+                ))
+            }
+        };
+
+        Ok(ResourceLimitExceeded {
+            resource,
+            limit: exceeded.get_limit(),
+            used: exceeded.get_used(),
+        })
+    }
+}
+
+impl FromNative<ResourceLimitExceeded> for protos::transaction_receipt::ResourceLimitExceeded {
+    fn from_native(exceeded: ResourceLimitExceeded) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::transaction_receipt::ResourceLimitExceeded::new();
+        proto.set_resource(match exceeded.resource {
+            Resource::Reads => ResourceLimitExceeded_Resource::READS,
+            Resource::Writes => ResourceLimitExceeded_Resource::WRITES,
+            Resource::BytesWritten => ResourceLimitExceeded_Resource::BYTES_WRITTEN,
+            Resource::Events => ResourceLimitExceeded_Resource::EVENTS,
+            Resource::WallTime => ResourceLimitExceeded_Resource::WALL_TIME_MICROS,
+        });
+        proto.set_limit(exceeded.limit);
+        proto.set_used(exceeded.used);
+        Ok(proto)
+    }
+}
+
+impl IntoProto<protos::transaction_receipt::ResourceLimitExceeded> for ResourceLimitExceeded {}
+impl IntoNative<ResourceLimitExceeded> for protos::transaction_receipt::ResourceLimitExceeded {}
+
+/// The resources used so far by a transaction that is executing.
+struct Metering {
+    limits: ResourceLimits,
+    usage: ResourceUsage,
+    started: Instant,
+    exceeded: Option<ResourceLimitExceeded>,
+}
+
+/// Counts the resources used by each transaction by its context, until its result is
+/// known, against the limits of its family.
+///
+/// Transactions executing at the same time must have different contexts.
+#[derive(Default)]
+pub struct ResourceMeter {
+    default_limits: ResourceLimits,
+    family_limits: HashMap<String, ResourceLimits>,
+    metering: Mutex<HashMap<ContextId, Metering>>,
+}
+
+impl ResourceMeter {
+    pub fn new() -> Self {
+        ResourceMeter::default()
+    }
+
+    /// The limits for families without their own. Without this, only the families given
+    /// to `with_family_limits` are limited.
+    pub fn with_default_limits(mut self, limits: ResourceLimits) -> Self {
+        self.default_limits = limits;
+        self
+    }
+
+    /// The limits for every version of the family, in place of the default limits.
+    pub fn with_family_limits(mut self, family_name: &str, limits: ResourceLimits) -> Self {
+        self.family_limits.insert(family_name.to_string(), limits);
+        self
+    }
+
+    fn begin(&self, context_id: ContextId, family_name: &str) {
+        let limits = self
+            .family_limits
+            .get(family_name)
+            .unwrap_or(&self.default_limits)
+            .clone();
+
+        self.metering
+            .lock()
+            .expect("The metering lock is poisoned")
+            .insert(
+                context_id,
+                Metering {
+                    limits,
+                    usage: ResourceUsage::default(),
+                    started: Instant::now(),
+                    exceeded: None,
+                },
+            );
+    }
+
+    /// Count a state access, refusing it if it would exceed a limit, or if the transaction
+    /// has already run for longer than it may. Contexts that are not being metered are not
+    /// counted.
+    fn count<F: FnOnce(&mut ResourceUsage)>(
+        &self,
+        context_id: &ContextId,
+        add: F,
+    ) -> Result<(), String> {
+        let mut metering = self.metering.lock().expect("The metering lock is poisoned");
+        let metering = match metering.get_mut(context_id) {
+            Some(metering) => metering,
+            None => return Ok(()),
+        };
+
+        let mut usage = metering.usage.clone();
+        usage.wall_time = metering.started.elapsed();
+        add(&mut usage);
+        match metering.limits.check(&usage) {
+            Some(exceeded) => {
+                let message = exceeded.to_string();
+                metering.exceeded.get_or_insert(exceeded);
+                Err(message)
+            }
+            None => {
+                metering.usage = usage;
+                Ok(())
+            }
+        }
+    }
+
+    /// Forget the usage of a transaction that will not get a result, or will be executed
+    /// again.
+    fn discard(&self, context_id: &ContextId) {
+        self.metering
+            .lock()
+            .expect("The metering lock is poisoned")
+            .remove(context_id);
+    }
+
+    /// Give the result its usage, making the transaction invalid if it exceeded a limit.
+    ///
+    /// This is where the wall time is checked for certain, so a transaction over its wall
+    /// time limit is only found invalid once the adapter has finished with it.
+    fn finish(&self, context_id: &ContextId, execution_result: &mut ExecutionResult) {
+        let metering = self
+            .metering
+            .lock()
+            .expect("The metering lock is poisoned")
+            .remove(context_id);
+        let mut metering = match metering {
+            Some(metering) => metering,
+            None => return,
+        };
+
+        metering.usage.wall_time = metering.started.elapsed();
+        let limits = &metering.limits;
+        let usage = &metering.usage;
+        if let Some(exceeded) = metering.exceeded.or_else(|| limits.check(usage)) {
+            debug!(
+                "Transaction {} is invalid: {}",
+                execution_result.transaction_id, exceeded
+            );
+            execution_result.status = TransactionStatus::Invalid(exceeded.into());
+        }
+        execution_result.resource_usage = Some(metering.usage);
+    }
+}
+
+/// Counts the state accesses made through a `ContextStateHandler`, refusing those that
+/// exceed a limit.
+pub struct MeteredStateHandler {
+    inner: Arc<dyn ContextStateHandler>,
+    meter: Arc<ResourceMeter>,
+}
+
+impl MeteredStateHandler {
+    pub fn new(inner: Arc<dyn ContextStateHandler>, meter: Arc<ResourceMeter>) -> Self {
+        MeteredStateHandler { inner, meter }
+    }
+}
+
+impl ContextStateHandler for MeteredStateHandler {
+    fn get_state(
+        &self,
+        context_id: &ContextId,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, String> {
+        self.meter
+            .count(context_id, |usage| usage.reads += addresses.len() as u64)?;
+        self.inner.get_state(context_id, addresses)
+    }
+
+    fn set_state(
+        &self,
+        context_id: &ContextId,
+        entries: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), String> {
+        self.meter.count(context_id, |usage| {
+            usage.writes += entries.len() as u64;
+            usage.bytes_written += entries
+                .iter()
+                .map(|(_, value)| value.len() as u64)
+                .sum::<u64>();
+        })?;
+        self.inner.set_state(context_id, entries)
+    }
+
+    fn add_event(&self, context_id: &ContextId, event: Event) -> Result<(), String> {
+        self.meter.count(context_id, |usage| usage.events += 1)?;
+        self.inner.add_event(context_id, event)
+    }
+}
+
+/// Meters every transaction executed by the wrapped `ExecutionAdapter`, which must make
+/// its state accesses through a `MeteredStateHandler` sharing the `ResourceMeter`.
+pub struct MeteredExecutionAdapter {
+    inner: Box<dyn ExecutionAdapter>,
+    meter: Arc<ResourceMeter>,
+}
+
+impl MeteredExecutionAdapter {
+    pub fn new(inner: Box<dyn ExecutionAdapter>, meter: Arc<ResourceMeter>) -> Self {
+        MeteredExecutionAdapter { inner, meter }
+    }
+}
+
+impl ExecutionAdapter for MeteredExecutionAdapter {
+    fn on_register(&self, callback: Box<OnRegisterCallback>) {
+        self.inner.on_register(callback)
+    }
+
+    fn on_unregister(&self, callback: Box<OnUnregisterCallback>) {
+        self.inner.on_unregister(callback)
+    }
+
+    fn execute(
+        &self,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+        on_done: Box<OnDoneCallback>,
+    ) {
+        let mut on_done = on_done;
+        let meter = Arc::clone(&self.meter);
+        meter.begin(context_id, transaction_pair.header().family_name());
+
+        self.inner.execute(
+            transaction_pair,
+            context_id,
+            Box::new(move |result| match result {
+                Ok(mut execution_result) => {
+                    meter.finish(&context_id, &mut execution_result);
+                    on_done(Ok(execution_result))
+                }
+                Err(err) => {
+                    meter.discard(&context_id);
+                    on_done(Err(err))
+                }
+            }),
+        );
+    }
+
+    fn cancel(&self, context_id: ContextId) {
+        self.meter.discard(&context_id);
+        self.inner.cancel(context_id)
+    }
+
+    fn stop(self: Box<Self>) -> bool {
+        self.inner.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::execution::adapter::conformance::ConformanceSuite;
+    use crate::execution::adapter::test_adapter::{
+        create_txn, TestContextState, TestExecutionAdapter,
+    };
+    use crate::execution::adapter::ExecutionAdapterError;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    static FAMILY_NAME: &str = "metered";
+    static LIMITED_FAMILY_NAME: &str = "limited";
+    static FAMILY_VERSION: &str = "1.0";
+
+    /// Runs the whitespace separated commands of the payload against state: `read`,
+    /// `write` of four bytes, `event` and `sleep` for 50ms. A refused state access makes
+    /// the transaction invalid.
+    struct CommandAdapter {
+        state: Arc<dyn ContextStateHandler>,
+    }
+
+    impl CommandAdapter {
+        fn run(&self, command: &str, context_id: &ContextId) -> Result<(), String> {
+            match command {
+                "read" => self
+                    .state
+                    .get_state(context_id, &["address".to_string()])
+                    .map(|_| ()),
+                "write" => self
+                    .state
+                    .set_state(context_id, vec![("address".to_string(), vec![1; 4])]),
+                "event" => self.state.add_event(
+                    context_id,
+                    Event {
+                        event_type: "metered".to_string(),
+                        attributes: vec![],
+                        data: vec![],
+                    },
+                ),
+                "sleep" => {
+                    thread::sleep(Duration::from_millis(50));
+                    Ok(())
+                }
+                _ => Err(format!("Unknown command {}", command)),
+            }
+        }
+    }
+
+    impl ExecutionAdapter for CommandAdapter {
+        fn on_register(&self, _callback: Box<OnRegisterCallback>) {}
+
+        fn on_unregister(&self, _callback: Box<OnUnregisterCallback>) {}
+
+        fn execute(
+            &self,
+            transaction_pair: TransactionPair,
+            context_id: ContextId,
+            on_done: Box<OnDoneCallback>,
+        ) {
+            let mut on_done = on_done;
+            let payload = String::from_utf8(transaction_pair.transaction().payload().to_vec())
+                .expect("The payload is a string");
+
+            let status = match payload
+                .split_whitespace()
+                .try_for_each(|command| self.run(command, &context_id))
+            {
+                Ok(()) => TransactionStatus::Valid,
+                Err(error_message) => TransactionStatus::Invalid(InvalidTransaction {
+                    error_message,
+                    error_data: vec![],
+                }),
+            };
+
+            on_done(Ok(ExecutionResult {
+                transaction_id: transaction_pair
+                    .transaction()
+                    .header_signature()
+                    .to_string(),
+                status,
+                resource_usage: None,
+            }));
+        }
+
+        fn stop(self: Box<Self>) -> bool {
+            true
+        }
+    }
+
+    fn metered_adapter(meter: ResourceMeter) -> MeteredExecutionAdapter {
+        let meter = Arc::new(meter);
+        let state = Arc::new(MeteredStateHandler::new(
+            Arc::new(TestContextState::with_values(vec![])),
+            Arc::clone(&meter),
+        ));
+        MeteredExecutionAdapter::new(Box::new(CommandAdapter { state }), meter)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn execute(
+        adapter: &dyn ExecutionAdapter,
+        transaction_pair: TransactionPair,
+    ) -> Result<ExecutionResult, ExecutionAdapterError> {
+        let (sender, receiver) = channel();
+        adapter.execute(
+            transaction_pair,
+            [0; 16],
+            Box::new(move |result| sender.send(result).expect("The receiver was dropped")),
+        );
+        receiver
+            .recv_timeout(Duration::from_secs(1))
+            .expect("on_done was called")
+    }
+
+    fn exceeded(result: &ExecutionResult) -> Option<ResourceLimitExceeded> {
+        match &result.status {
+            TransactionStatus::Invalid(invalid_transaction) => {
+                ResourceLimitExceeded::from_invalid_transaction(invalid_transaction)
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_resource_limits_check() {
+        let limits = ResourceLimits::new()
+            .with_max_reads(2)
+            .with_max_bytes_written(4)
+            .with_max_wall_time(Duration::from_millis(1));
+
+        let mut usage = ResourceUsage {
+            reads: 2,
+            writes: 100,
+            bytes_written: 4,
+            events: 100,
+            wall_time: Duration::from_micros(1000),
+        };
+        assert_eq!(None, limits.check(&usage));
+
+        usage.bytes_written = 5;
+        usage.wall_time = Duration::from_micros(1001);
+        assert_eq!(
+            Some(ResourceLimitExceeded {
+                resource: Resource::BytesWritten,
+                limit: 4,
+                used: 5,
+            }),
+            limits.check(&usage)
+        );
+
+        usage.bytes_written = 4;
+        assert_eq!(
+            Some(ResourceLimitExceeded {
+                resource: Resource::WallTime,
+                limit: 1000,
+                used: 1001,
+            }),
+            limits.check(&usage)
+        );
+    }
+
+    /// The reason survives being made the extended data of an `InvalidTransaction`.
+    #[test]
+    fn test_resource_limit_exceeded_invalid_transaction() {
+        let exceeded = ResourceLimitExceeded {
+            resource: Resource::Events,
+            limit: 3,
+            used: 4,
+        };
+
+        let invalid_transaction = InvalidTransaction::from(exceeded.clone());
+        assert_eq!(
+            "Exceeded the limit of 3 events with 4",
+            invalid_transaction.error_message
+        );
+        assert_eq!(
+            Some(exceeded),
+            ResourceLimitExceeded::from_invalid_transaction(&invalid_transaction)
+        );
+
+        assert_eq!(
+            None,
+            ResourceLimitExceeded::from_invalid_transaction(&InvalidTransaction {
+                error_message: "invalid".to_string(),
+                error_data: vec![],
+            })
+        );
+    }
+
+    /// Transactions within their limits keep their status, with their usage counted.
+    #[test]
+    fn test_metered_usage() {
+        let adapter = metered_adapter(
+            ResourceMeter::new()
+                .with_default_limits(ResourceLimits::new().with_max_reads(2).with_max_writes(2)),
+        );
+
+        let result = execute(
+            &adapter,
+            create_txn(FAMILY_NAME, "read write read write event"),
+        )
+        .expect("The transaction was executed");
+
+        assert_eq!(TransactionStatus::Valid, result.status);
+        let usage = result.resource_usage.expect("The usage was counted");
+        assert_eq!(2, usage.reads);
+        assert_eq!(2, usage.writes);
+        assert_eq!(8, usage.bytes_written);
+        assert_eq!(1, usage.events);
+    }
+
+    /// The access over a limit is refused, and the transaction made invalid with the
+    /// reason, even though the handler gave its own.
+    #[test]
+    fn test_metered_state_limit() {
+        let adapter = metered_adapter(
+            ResourceMeter::new()
+                .with_default_limits(ResourceLimits::new().with_max_events(1))
+                .with_family_limits(
+                    LIMITED_FAMILY_NAME,
+                    ResourceLimits::new().with_max_bytes_written(6),
+                ),
+        );
+
+        let result = execute(&adapter, create_txn(FAMILY_NAME, "write write event"))
+            .expect("The transaction was executed");
+        assert_eq!(
+            TransactionStatus::Valid,
+            result.status,
+            "The family limits apply to their family only"
+        );
+
+        let result = execute(
+            &adapter,
+            create_txn(LIMITED_FAMILY_NAME, "write write event"),
+        )
+        .expect("The transaction was executed");
+        assert_eq!(
+            Some(ResourceLimitExceeded {
+                resource: Resource::BytesWritten,
+                limit: 6,
+                used: 8,
+            }),
+            exceeded(&result)
+        );
+        let usage = result.resource_usage.expect("The usage was counted");
+        assert_eq!(1, usage.writes, "The refused write is not counted");
+        assert_eq!(0, usage.events);
+    }
+
+    /// A transaction that takes too long is made invalid once it finishes.
+    #[test]
+    fn test_metered_wall_time_limit() {
+        let adapter = metered_adapter(ResourceMeter::new().with_family_limits(
+            FAMILY_NAME,
+            ResourceLimits::new().with_max_wall_time(Duration::from_millis(10)),
+        ));
+
+        let result = execute(&adapter, create_txn(FAMILY_NAME, "read sleep"))
+            .expect("The transaction was executed");
+
+        let exceeded = exceeded(&result).expect("The wall time limit was exceeded");
+        assert_eq!(Resource::WallTime, exceeded.resource);
+        assert_eq!(10_000, exceeded.limit);
+        assert!(exceeded.used >= 50_000);
+        assert_eq!(
+            1,
+            result.resource_usage.expect("The usage was counted").reads
+        );
+    }
+
+    /// A transaction may not access state once its time is up.
+    #[test]
+    fn test_metered_wall_time_refuses_state() {
+        let adapter = metered_adapter(ResourceMeter::new().with_family_limits(
+            FAMILY_NAME,
+            ResourceLimits::new().with_max_wall_time(Duration::from_millis(10)),
+        ));
+
+        let result = execute(&adapter, create_txn(FAMILY_NAME, "sleep read"))
+            .expect("The transaction was executed");
+
+        let exceeded = exceeded(&result).expect("The wall time limit was exceeded");
+        assert_eq!(Resource::WallTime, exceeded.resource);
+        assert_eq!(
+            0,
+            result.resource_usage.expect("The usage was counted").reads,
+            "The read after the time was up is refused"
+        );
+    }
+
+    #[test]
+    fn test_metered_conformance() {
+        ConformanceSuite::new(
+            || {
+                let adapter = TestExecutionAdapter::new();
+                adapter.register(FAMILY_NAME, FAMILY_VERSION);
+                Box::new(MeteredExecutionAdapter::new(
+                    Box::new(adapter),
+                    Arc::new(ResourceMeter::new()),
+                ))
+            },
+            |i| create_txn(FAMILY_NAME, &i.to_string()),
+        )
+        .run()
+        .expect("The MeteredExecutionAdapter does not conform");
+    }
+}
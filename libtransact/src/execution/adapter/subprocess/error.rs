@@ -0,0 +1,79 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use protobuf::ProtobufError;
+
+/// An error that may occur while exchanging messages with a handler process.
+#[derive(Debug)]
+pub enum SubprocessProtocolError {
+    /// Reading from or writing to the handler process failed.
+    IoError(io::Error),
+    /// A message could not be encoded or decoded.
+    ProtobufError(ProtobufError),
+    /// A message's length prefix exceeds `MAX_MESSAGE_SIZE`.
+    MessageTooLarge(usize),
+}
+
+impl fmt::Display for SubprocessProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubprocessProtocolError::IoError(err) => write!(f, "IO Error: {}", err),
+            SubprocessProtocolError::ProtobufError(err) => write!(f, "Protobuf Error: {}", err),
+            SubprocessProtocolError::MessageTooLarge(size) => {
+                write!(f, "Message Too Large: {} bytes", size)
+            }
+        }
+    }
+}
+
+impl Error for SubprocessProtocolError {
+    fn description(&self) -> &str {
+        match self {
+            SubprocessProtocolError::IoError(_) => "Reading or writing a message failed.",
+            SubprocessProtocolError::ProtobufError(_) => {
+                "A message could not be encoded or decoded."
+            }
+            SubprocessProtocolError::MessageTooLarge(_) => {
+                "A message exceeded the maximum message size."
+            }
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        match self {
+            SubprocessProtocolError::IoError(err) => Some(err),
+            SubprocessProtocolError::ProtobufError(err) => Some(err),
+            SubprocessProtocolError::MessageTooLarge(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SubprocessProtocolError {
+    fn from(err: io::Error) -> Self {
+        SubprocessProtocolError::IoError(err)
+    }
+}
+
+impl From<ProtobufError> for SubprocessProtocolError {
+    fn from(err: ProtobufError) -> Self {
+        SubprocessProtocolError::ProtobufError(err)
+    }
+}
@@ -0,0 +1,877 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! An `ExecutionAdapter` that executes transactions in local handler processes.
+//!
+//! A handler process is spawned for each configured transaction family. Transactions are
+//! sent to it on its stdin and results read from its stdout, using the framing in
+//! `protocol`. While executing, the handler process may request state for the
+//! transaction's context, which is served by a `ContextStateHandler`.
+//!
+//! A handler process that exits is restarted, and its family is unregistered while it is
+//! down. One that does not answer within the timeout is killed and restarted, and the
+//! transaction fails with `ExecutionAdapterError::TimeOutError`.
+
+pub mod error;
+pub mod protocol;
+
+pub use crate::execution::adapter::subprocess::error::SubprocessProtocolError;
+
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use protobuf::Message;
+
+use crate::context::ContextId;
+use crate::execution::adapter::subprocess::protocol::{read_message, wrap_message, write_message};
+use crate::execution::adapter::{
+    ContextStateHandler, ExecutionAdapter, ExecutionAdapterError, ExecutionResult,
+    InvalidTransaction, OnDoneCallback, OnRegisterCallback, OnUnregisterCallback,
+    TransactionFamily, TransactionFamilyRegistration, TransactionStatus,
+};
+use crate::protos;
+use crate::protos::subprocess::{
+    SubprocessExecuteRequest, SubprocessExecuteResponse, SubprocessExecuteResponse_Status,
+    SubprocessMessage, SubprocessMessage_MessageType, SubprocessStateEntry,
+    SubprocessStateGetRequest, SubprocessStateGetResponse, SubprocessStateGetResponse_Status,
+    SubprocessStateSetRequest, SubprocessStateSetResponse, SubprocessStateSetResponse_Status,
+};
+use crate::transaction::TransactionPair;
+
+/// How long a handler process has to answer an execute request.
+pub const DEFAULT_EXECUTE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait before restarting a handler process that went down.
+pub const DEFAULT_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// How often the handler processes are checked for having exited.
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The handler process to run for a transaction family.
+#[derive(Debug, Clone)]
+pub struct SubprocessHandlerConfig {
+    family_name: String,
+    family_versions: Vec<String>,
+    program: PathBuf,
+    args: Vec<String>,
+}
+
+impl SubprocessHandlerConfig {
+    pub fn new(family_name: String, family_versions: Vec<String>, program: PathBuf) -> Self {
+        SubprocessHandlerConfig {
+            family_name,
+            family_versions,
+            program,
+            args: vec![],
+        }
+    }
+
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    pub fn family_name(&self) -> &str {
+        &self.family_name
+    }
+
+    pub fn family_versions(&self) -> &[String] {
+        &self.family_versions
+    }
+
+    pub fn program(&self) -> &PathBuf {
+        &self.program
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    fn registration(&self) -> TransactionFamilyRegistration {
+        TransactionFamilyRegistration::with_versions(
+            self.family_name.clone(),
+            self.family_versions.clone(),
+        )
+    }
+}
+
+/// Executes transactions in a handler process per transaction family.
+pub struct SubprocessExecutionAdapter {
+    handlers: Vec<Arc<HandlerProcess>>,
+    callbacks: Arc<Mutex<RegistrationCallbacks>>,
+    state_handler: Option<Arc<dyn ContextStateHandler>>,
+    timeout: Duration,
+    restart_delay: Duration,
+    stop: Arc<AtomicBool>,
+    supervisors: Vec<JoinHandle<()>>,
+}
+
+impl SubprocessExecutionAdapter {
+    pub fn new(configs: Vec<SubprocessHandlerConfig>) -> Self {
+        SubprocessExecutionAdapter {
+            handlers: configs
+                .into_iter()
+                .map(|config| Arc::new(HandlerProcess::new(config)))
+                .collect(),
+            callbacks: Arc::new(Mutex::new(RegistrationCallbacks::default())),
+            state_handler: None,
+            timeout: DEFAULT_EXECUTE_TIMEOUT,
+            restart_delay: DEFAULT_RESTART_DELAY,
+            stop: Arc::new(AtomicBool::new(false)),
+            supervisors: vec![],
+        }
+    }
+
+    /// Serve the handler processes' state requests. Without a `ContextStateHandler`
+    /// every state request fails.
+    pub fn with_state_handler(mut self, state_handler: Arc<dyn ContextStateHandler>) -> Self {
+        self.state_handler = Some(state_handler);
+        self
+    }
+
+    /// How long a handler process has to answer an execute request before it is killed.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long to wait before restarting a handler process that went down.
+    pub fn with_restart_delay(mut self, restart_delay: Duration) -> Self {
+        self.restart_delay = restart_delay;
+        self
+    }
+
+    /// Spawn the handler processes, failing if any of them cannot be spawned, and start
+    /// restarting them when they go down.
+    pub fn start(&mut self) -> Result<(), std::io::Error> {
+        if !self.supervisors.is_empty() {
+            return Ok(());
+        }
+
+        for handler in &self.handlers {
+            handler.spawn()?;
+        }
+
+        for handler in &self.handlers {
+            let handler = Arc::clone(handler);
+            let callbacks = Arc::clone(&self.callbacks);
+            let stop = Arc::clone(&self.stop);
+            let restart_delay = self.restart_delay;
+
+            let join_handle = thread::Builder::new()
+                .name(format!(
+                    "subprocess_supervisor_{}",
+                    handler.config.family_name
+                ))
+                .spawn(move || supervise(&handler, &callbacks, &stop, restart_delay))?;
+            self.supervisors.push(join_handle);
+        }
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for join_handle in self.supervisors.drain(..) {
+            if let Err(err) = join_handle.join() {
+                warn!("Error joining with subprocess supervisor thread: {:?}", err);
+            }
+        }
+        for handler in &self.handlers {
+            HandlerProcess::terminate(&mut handler.lock());
+        }
+    }
+}
+
+impl ExecutionAdapter for SubprocessExecutionAdapter {
+    fn on_register(&self, callback: Box<OnRegisterCallback>) {
+        let mut callbacks = self
+            .callbacks
+            .lock()
+            .expect("The registration callbacks lock is poisoned");
+
+        // Catch the callback up on the families that are already running.
+        let mut callback = callback;
+        for handler in &self.handlers {
+            if handler.lock().is_some() {
+                callback(handler.registration.clone());
+            }
+        }
+        callbacks.on_register.push(callback);
+    }
+
+    fn on_unregister(&self, callback: Box<OnUnregisterCallback>) {
+        self.callbacks
+            .lock()
+            .expect("The registration callbacks lock is poisoned")
+            .on_unregister
+            .push(callback);
+    }
+
+    fn execute(
+        &self,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+        on_done: Box<OnDoneCallback>,
+    ) {
+        let mut on_done = on_done;
+
+        let transaction_family = TransactionFamily::from_pair(&transaction_pair);
+        let handler = self
+            .handlers
+            .iter()
+            .find(|handler| handler.registration.matches(&transaction_family));
+
+        let result = match handler {
+            Some(handler) => handler.execute(
+                transaction_pair,
+                context_id,
+                self.timeout,
+                self.state_handler.as_ref().map(|handler| handler.as_ref()),
+            ),
+            None => Err(ExecutionAdapterError::RoutingError(transaction_pair)),
+        };
+        on_done(result);
+    }
+
+    fn stop(mut self: Box<Self>) -> bool {
+        self.shutdown();
+        true
+    }
+}
+
+impl Drop for SubprocessExecutionAdapter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[derive(Default)]
+struct RegistrationCallbacks {
+    on_register: Vec<Box<OnRegisterCallback>>,
+    on_unregister: Vec<Box<OnUnregisterCallback>>,
+}
+
+impl RegistrationCallbacks {
+    fn register(&mut self, registration: &TransactionFamilyRegistration) {
+        for callback in &mut self.on_register {
+            callback(registration.clone());
+        }
+    }
+
+    fn unregister(&mut self, registration: &TransactionFamilyRegistration) {
+        for callback in &mut self.on_unregister {
+            callback(registration.clone());
+        }
+    }
+}
+
+/// Restart the handler process whenever it goes down, unregistering its family until it
+/// is back up.
+fn supervise(
+    handler: &HandlerProcess,
+    callbacks: &Mutex<RegistrationCallbacks>,
+    stop: &AtomicBool,
+    restart_delay: Duration,
+) {
+    let lock_callbacks = || {
+        callbacks
+            .lock()
+            .expect("The registration callbacks lock is poisoned")
+    };
+
+    let mut down_since = None;
+    while !stop.load(Ordering::Relaxed) {
+        match down_since {
+            None => {
+                if handler.reap() {
+                    lock_callbacks().unregister(&handler.registration);
+                    down_since = Some(Instant::now());
+                }
+            }
+            Some(since) => {
+                if since.elapsed() >= restart_delay {
+                    match handler.spawn() {
+                        Ok(()) => {
+                            lock_callbacks().register(&handler.registration);
+                            down_since = None;
+                        }
+                        Err(err) => {
+                            warn!(
+                                "Unable to restart the {} handler process: {}",
+                                handler.config.family_name, err
+                            );
+                            down_since = Some(Instant::now());
+                        }
+                    }
+                }
+            }
+        }
+        thread::sleep(SUPERVISE_INTERVAL);
+    }
+}
+
+type HandlerMessages = Receiver<Result<SubprocessMessage, SubprocessProtocolError>>;
+
+/// A spawned handler process, along with the messages read from its stdout.
+struct RunningHandler {
+    child: Child,
+    stdin: ChildStdin,
+    messages: HandlerMessages,
+    next_correlation_id: u64,
+}
+
+struct HandlerProcess {
+    config: SubprocessHandlerConfig,
+    registration: TransactionFamilyRegistration,
+    running: Mutex<Option<RunningHandler>>,
+}
+
+impl HandlerProcess {
+    fn new(config: SubprocessHandlerConfig) -> Self {
+        HandlerProcess {
+            registration: config.registration(),
+            config,
+            running: Mutex::new(None),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<RunningHandler>> {
+        self.running
+            .lock()
+            .expect("The handler process lock is poisoned")
+    }
+
+    fn spawn(&self) -> Result<(), std::io::Error> {
+        let mut child = Command::new(&self.config.program)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("The child's stdin is piped");
+        let stdout = child.stdout.take().expect("The child's stdout is piped");
+
+        let (sender, messages) = channel();
+        let reader = thread::Builder::new()
+            .name(format!("subprocess_reader_{}", self.config.family_name))
+            .spawn(move || {
+                let mut stdout = BufReader::new(stdout);
+                loop {
+                    match read_message(&mut stdout) {
+                        Ok(Some(message)) => {
+                            if sender.send(Ok(message)).is_err() {
+                                break;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(err) => {
+                            let _ = sender.send(Err(err));
+                            break;
+                        }
+                    }
+                }
+            });
+        if let Err(err) = reader {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+
+        *self.lock() = Some(RunningHandler {
+            child,
+            stdin,
+            messages,
+            next_correlation_id: 0,
+        });
+        Ok(())
+    }
+
+    /// Whether the handler process has gone down, either by exiting or by being
+    /// terminated.
+    fn reap(&self) -> bool {
+        let mut running = self.lock();
+        let status = match running.as_mut() {
+            Some(handler) => handler.child.try_wait(),
+            None => return true,
+        };
+        match status {
+            Ok(None) => false,
+            Ok(Some(status)) => {
+                warn!(
+                    "The {} handler process exited: {}",
+                    self.config.family_name, status
+                );
+                *running = None;
+                true
+            }
+            Err(err) => {
+                warn!(
+                    "Unable to check on the {} handler process: {}",
+                    self.config.family_name, err
+                );
+                Self::terminate(&mut running);
+                true
+            }
+        }
+    }
+
+    fn terminate(running: &mut Option<RunningHandler>) {
+        if let Some(mut handler) = running.take() {
+            let _ = handler.child.kill();
+            let _ = handler.child.wait();
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn execute(
+        &self,
+        transaction_pair: TransactionPair,
+        context_id: ContextId,
+        timeout: Duration,
+        state_handler: Option<&dyn ContextStateHandler>,
+    ) -> Result<ExecutionResult, ExecutionAdapterError> {
+        let mut running = self.lock();
+        let handler = match running.as_mut() {
+            Some(handler) => handler,
+            None => return Err(ExecutionAdapterError::RoutingError(transaction_pair)),
+        };
+
+        let correlation_id = handler.next_correlation_id;
+        handler.next_correlation_id += 1;
+
+        let deadline = Instant::now() + timeout;
+        let outcome = execute_request(&transaction_pair, &context_id, correlation_id)
+            .and_then(|request| write_message(&mut handler.stdin, &request))
+            .map_err(HandlerFailure::Protocol)
+            .and_then(|()| {
+                await_response(
+                    handler,
+                    correlation_id,
+                    &context_id,
+                    deadline,
+                    state_handler,
+                )
+            });
+
+        match outcome {
+            Ok(response) => into_execution_result(transaction_pair, response),
+            Err(HandlerFailure::TimedOut) => {
+                warn!(
+                    "The {} handler process did not respond within {:?}, restarting it",
+                    self.config.family_name, timeout
+                );
+                Self::terminate(&mut running);
+                Err(ExecutionAdapterError::TimeOutError(transaction_pair))
+            }
+            Err(HandlerFailure::Exited) => {
+                Self::terminate(&mut running);
+                Err(ExecutionAdapterError::RoutingError(transaction_pair))
+            }
+            Err(HandlerFailure::Protocol(err)) => {
+                warn!(
+                    "Communication with the {} handler process failed, restarting it: {}",
+                    self.config.family_name, err
+                );
+                Self::terminate(&mut running);
+                Err(ExecutionAdapterError::RoutingError(transaction_pair))
+            }
+        }
+    }
+}
+
+/// Why no execute response was received from a handler process.
+enum HandlerFailure {
+    TimedOut,
+    Exited,
+    Protocol(SubprocessProtocolError),
+}
+
+fn execute_request(
+    transaction_pair: &TransactionPair,
+    context_id: &ContextId,
+    correlation_id: u64,
+) -> Result<SubprocessMessage, SubprocessProtocolError> {
+    let transaction = transaction_pair.transaction();
+    let mut proto_transaction = protos::transaction::Transaction::new();
+    proto_transaction.set_header(transaction.header().to_vec());
+    proto_transaction.set_header_signature(transaction.header_signature().to_string());
+    proto_transaction.set_payload(transaction.payload().to_vec());
+
+    let mut request = SubprocessExecuteRequest::new();
+    request.set_transaction(proto_transaction);
+    request.set_context_id(context_id.to_vec());
+
+    wrap_message(
+        SubprocessMessage_MessageType::EXECUTE_REQUEST,
+        correlation_id,
+        &request,
+    )
+}
+
+/// Wait for the response to an execute request, serving the handler process's state
+/// requests in the meantime.
+fn await_response(
+    handler: &mut RunningHandler,
+    correlation_id: u64,
+    context_id: &ContextId,
+    deadline: Instant,
+    state_handler: Option<&dyn ContextStateHandler>,
+) -> Result<SubprocessExecuteResponse, HandlerFailure> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let message = match handler.messages.recv_timeout(remaining) {
+            Ok(Ok(message)) => message,
+            Ok(Err(err)) => return Err(HandlerFailure::Protocol(err)),
+            Err(RecvTimeoutError::Timeout) => return Err(HandlerFailure::TimedOut),
+            Err(RecvTimeoutError::Disconnected) => return Err(HandlerFailure::Exited),
+        };
+
+        match message.get_message_type() {
+            SubprocessMessage_MessageType::EXECUTE_RESPONSE
+                if message.get_correlation_id() == correlation_id =>
+            {
+                return Message::parse_from_bytes(message.get_content())
+                    .map_err(|err| HandlerFailure::Protocol(err.into()));
+            }
+            SubprocessMessage_MessageType::STATE_GET_REQUEST
+            | SubprocessMessage_MessageType::STATE_SET_REQUEST => {
+                let response = serve_state_request(&message, context_id, state_handler)
+                    .map_err(HandlerFailure::Protocol)?;
+                write_message(&mut handler.stdin, &response).map_err(HandlerFailure::Protocol)?;
+            }
+            message_type => warn!(
+                "Ignoring unexpected {:?} message {} from a handler process",
+                message_type,
+                message.get_correlation_id()
+            ),
+        }
+    }
+}
+
+/// Answer a state request, which may only be made against the context of the
+/// transaction being executed.
+fn serve_state_request(
+    message: &SubprocessMessage,
+    context_id: &ContextId,
+    state_handler: Option<&dyn ContextStateHandler>,
+) -> Result<SubprocessMessage, SubprocessProtocolError> {
+    let correlation_id = message.get_correlation_id();
+
+    let state_handler_for = |requested: &[u8]| {
+        if requested == &context_id[..] {
+            state_handler.ok_or_else(|| "No state handler is configured".to_string())
+        } else {
+            Err("The context is not that of the executing transaction".to_string())
+        }
+    };
+
+    if message.get_message_type() == SubprocessMessage_MessageType::STATE_GET_REQUEST {
+        let request: SubprocessStateGetRequest = Message::parse_from_bytes(message.get_content())?;
+        let mut response = SubprocessStateGetResponse::new();
+        match state_handler_for(request.get_context_id())
+            .and_then(|handler| handler.get_state(context_id, request.get_addresses()))
+        {
+            Ok(values) => {
+                response.set_entries(
+                    values
+                        .into_iter()
+                        .map(|(address, data)| {
+                            let mut entry = SubprocessStateEntry::new();
+                            entry.set_address(address);
+                            entry.set_data(data);
+                            entry
+                        })
+                        .collect(),
+                );
+                response.set_status(SubprocessStateGetResponse_Status::OK);
+            }
+            Err(err) => {
+                warn!("Unable to get state for a handler process: {}", err);
+                response.set_status(SubprocessStateGetResponse_Status::ERROR);
+            }
+        }
+        wrap_message(
+            SubprocessMessage_MessageType::STATE_GET_RESPONSE,
+            correlation_id,
+            &response,
+        )
+    } else {
+        let mut request: SubprocessStateSetRequest =
+            Message::parse_from_bytes(message.get_content())?;
+        let entries = request
+            .take_entries()
+            .into_iter()
+            .map(|mut entry| (entry.take_address(), entry.take_data()))
+            .collect();
+
+        let mut response = SubprocessStateSetResponse::new();
+        match state_handler_for(request.get_context_id())
+            .and_then(|handler| handler.set_state(context_id, entries))
+        {
+            Ok(()) => response.set_status(SubprocessStateSetResponse_Status::OK),
+            Err(err) => {
+                warn!("Unable to set state for a handler process: {}", err);
+                response.set_status(SubprocessStateSetResponse_Status::ERROR);
+            }
+        }
+        wrap_message(
+            SubprocessMessage_MessageType::STATE_SET_RESPONSE,
+            correlation_id,
+            &response,
+        )
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn into_execution_result(
+    transaction_pair: TransactionPair,
+    response: SubprocessExecuteResponse,
+) -> Result<ExecutionResult, ExecutionAdapterError> {
+    let status = match response.get_status() {
+        SubprocessExecuteResponse_Status::OK => TransactionStatus::Valid,
+        SubprocessExecuteResponse_Status::INVALID_TRANSACTION => {
+            TransactionStatus::Invalid(InvalidTransaction {
+                error_message: response.get_message().to_string(),
+                error_data: response.get_extended_data().to_vec(),
+            })
+        }
+        // The handler process could not process the transaction, so it is retried.
+        SubprocessExecuteResponse_Status::INTERNAL_ERROR
+        | SubprocessExecuteResponse_Status::STATUS_UNSET => {
+            return Err(ExecutionAdapterError::RoutingError(transaction_pair));
+        }
+    };
+
+    Ok(ExecutionResult {
+        transaction_id: transaction_pair
+            .transaction()
+            .header_signature()
+            .to_string(),
+        status,
+        resource_usage: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::execution::adapter::conformance::ConformanceSuite;
+    use crate::execution::adapter::test_adapter::{create_txn, TestContextState};
+    use std::sync::atomic::AtomicUsize;
+
+    static FAMILY_NAME: &str = "subprocess";
+    static FAMILY_VERSION: &str = "1.0";
+
+    const CONTEXT_ID: ContextId = [7; 16];
+
+    /// The example handler process, which `cargo test` builds along with the tests.
+    fn handler_program() -> PathBuf {
+        let mut dir = std::env::current_exe().expect("Unable to find the test executable");
+        dir.pop();
+        if dir.ends_with("deps") {
+            dir.pop();
+        }
+        let program = dir.join("examples").join("subprocess_handler");
+        assert!(
+            program.exists(),
+            "{:?} is missing; it is built by `cargo test`",
+            program
+        );
+        program
+    }
+
+    fn create_adapter() -> SubprocessExecutionAdapter {
+        SubprocessExecutionAdapter::new(vec![SubprocessHandlerConfig::new(
+            FAMILY_NAME.to_string(),
+            vec![FAMILY_VERSION.to_string()],
+            handler_program(),
+        )])
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn execute(
+        adapter: &dyn ExecutionAdapter,
+        payload: &str,
+    ) -> Result<ExecutionResult, ExecutionAdapterError> {
+        let (sender, receiver) = channel();
+        adapter.execute(
+            create_txn(FAMILY_NAME, payload),
+            CONTEXT_ID,
+            Box::new(move |result| sender.send(result).expect("The receiver was dropped")),
+        );
+        receiver.recv().expect("on_done was not called")
+    }
+
+    fn count_calls(counter: &Arc<AtomicUsize>) -> Box<OnRegisterCallback> {
+        let counter = Arc::clone(counter);
+        Box::new(move |registration: TransactionFamilyRegistration| {
+            assert_eq!(FAMILY_NAME, registration.family_name());
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    fn wait_for(counter: &AtomicUsize, expected: usize) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while counter.load(Ordering::SeqCst) != expected {
+            assert!(
+                Instant::now() < deadline,
+                "Expected {} calls, got {}",
+                expected,
+                counter.load(Ordering::SeqCst)
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Transactions are executed by the handler process, which reads and writes state
+    /// through the `ContextStateHandler`.
+    #[test]
+    fn test_subprocess_execution() {
+        let state = Arc::new(TestContextState::default());
+        let mut adapter = create_adapter().with_state_handler(state.clone());
+        adapter
+            .start()
+            .expect("Unable to start the handler process");
+
+        let registered = Arc::new(AtomicUsize::new(0));
+        adapter.on_register(count_calls(&registered));
+        assert_eq!(
+            1,
+            registered.load(Ordering::SeqCst),
+            "The running family is registered with a new callback"
+        );
+
+        let result = execute(&adapter, "valid").expect("The transaction was not executed");
+        assert_eq!(TransactionStatus::Valid, result.status);
+        assert_eq!(
+            create_txn(FAMILY_NAME, "valid")
+                .transaction()
+                .header_signature(),
+            result.transaction_id
+        );
+
+        let result = execute(&adapter, "invalid").expect("The transaction was not executed");
+        assert_eq!(
+            TransactionStatus::Invalid(InvalidTransaction {
+                error_message: "The payload is invalid".to_string(),
+                error_data: b"invalid".to_vec(),
+            }),
+            result.status
+        );
+
+        for _ in 0..2 {
+            let result =
+                execute(&adapter, "increment abcdef").expect("The transaction was not executed");
+            assert_eq!(TransactionStatus::Valid, result.status);
+        }
+        assert_eq!(Some(vec![2]), state.value("abcdef"));
+
+        let (sender, receiver) = channel();
+        adapter.execute(
+            create_txn("other", "valid"),
+            CONTEXT_ID,
+            Box::new(move |result| sender.send(result).expect("The receiver was dropped")),
+        );
+        match receiver.recv().expect("on_done was not called") {
+            Err(ExecutionAdapterError::RoutingError(_)) => (),
+            other => panic!("Expected a RoutingError, got {:?}", other),
+        }
+
+        assert!(Box::new(adapter).stop());
+    }
+
+    /// A handler process that hangs or crashes is restarted, and its family is
+    /// unregistered while it is down.
+    #[test]
+    fn test_subprocess_restart() {
+        let mut adapter = create_adapter()
+            .with_timeout(Duration::from_millis(500))
+            .with_restart_delay(Duration::from_millis(100));
+        adapter
+            .start()
+            .expect("Unable to start the handler process");
+
+        let registered = Arc::new(AtomicUsize::new(0));
+        let unregistered = Arc::new(AtomicUsize::new(0));
+        adapter.on_register(count_calls(&registered));
+        adapter.on_unregister(count_calls(&unregistered));
+        assert_eq!(1, registered.load(Ordering::SeqCst));
+
+        match execute(&adapter, "hang") {
+            Err(ExecutionAdapterError::TimeOutError(_)) => (),
+            other => panic!("Expected a TimeOutError, got {:?}", other),
+        }
+        wait_for(&unregistered, 1);
+        wait_for(&registered, 2);
+        assert_eq!(
+            TransactionStatus::Valid,
+            execute(&adapter, "valid")
+                .expect("The transaction was not executed")
+                .status
+        );
+
+        match execute(&adapter, "crash") {
+            Err(ExecutionAdapterError::RoutingError(_)) => (),
+            other => panic!("Expected a RoutingError, got {:?}", other),
+        }
+        wait_for(&unregistered, 2);
+        wait_for(&registered, 3);
+        assert_eq!(
+            TransactionStatus::Valid,
+            execute(&adapter, "valid")
+                .expect("The transaction was not executed")
+                .status
+        );
+
+        assert!(Box::new(adapter).stop());
+    }
+
+    #[test]
+    fn test_subprocess_missing_program() {
+        let mut adapter = SubprocessExecutionAdapter::new(vec![SubprocessHandlerConfig::new(
+            FAMILY_NAME.to_string(),
+            vec![FAMILY_VERSION.to_string()],
+            PathBuf::from("/nonexistent/subprocess_handler"),
+        )]);
+        assert!(adapter.start().is_err());
+    }
+
+    #[test]
+    fn test_conformance_of_subprocess_adapter() {
+        ConformanceSuite::new(
+            || {
+                let mut adapter = create_adapter();
+                adapter
+                    .start()
+                    .expect("Unable to start the handler process");
+                Box::new(adapter)
+            },
+            |i| create_txn(FAMILY_NAME, &format!("valid {}", i)),
+        )
+        .with_unsupported_transaction(create_txn("other", "valid"))
+        .run()
+        .expect("The SubprocessExecutionAdapter does not conform");
+    }
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! The framing of `SubprocessMessage`s on a handler process's stdin and stdout.
+//!
+//! Each message is a 4 byte big-endian length followed by that many bytes of the encoded
+//! `SubprocessMessage`. Handler processes written in Rust can use these functions
+//! directly.
+
+use std::io::{self, Read, Write};
+
+use protobuf::Message;
+
+use crate::execution::adapter::subprocess::error::SubprocessProtocolError;
+use crate::protos::subprocess::{SubprocessMessage, SubprocessMessage_MessageType};
+
+/// The largest message, in bytes, that will be read.
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Wrap `content` in a `SubprocessMessage` of the given type.
+pub fn wrap_message<M: Message>(
+    message_type: SubprocessMessage_MessageType,
+    correlation_id: u64,
+    content: &M,
+) -> Result<SubprocessMessage, SubprocessProtocolError> {
+    let mut message = SubprocessMessage::new();
+    message.set_message_type(message_type);
+    message.set_correlation_id(correlation_id);
+    message.set_content(content.write_to_bytes()?);
+    Ok(message)
+}
+
+/// Write a length-prefixed `SubprocessMessage` and flush the writer.
+pub fn write_message<W: Write>(
+    writer: &mut W,
+    message: &SubprocessMessage,
+) -> Result<(), SubprocessProtocolError> {
+    let bytes = message.write_to_bytes()?;
+    if bytes.len() > MAX_MESSAGE_SIZE {
+        return Err(SubprocessProtocolError::MessageTooLarge(bytes.len()));
+    }
+    writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a length-prefixed `SubprocessMessage`, returning `None` if the reader is closed
+/// before the next message starts.
+pub fn read_message<R: Read>(
+    reader: &mut R,
+) -> Result<Option<SubprocessMessage>, SubprocessProtocolError> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => (),
+        Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(SubprocessProtocolError::MessageTooLarge(length));
+    }
+
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(Message::parse_from_bytes(&bytes)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::protos::subprocess::SubprocessStateEntry;
+
+    #[test]
+    fn test_message_framing() {
+        let mut entry = SubprocessStateEntry::new();
+        entry.set_address("abcdef".to_string());
+        entry.set_data(b"value".to_vec());
+
+        let first = wrap_message(SubprocessMessage_MessageType::STATE_GET_RESPONSE, 1, &entry)
+            .expect("Unable to wrap message");
+        let second = wrap_message(SubprocessMessage_MessageType::EXECUTE_RESPONSE, 2, &entry)
+            .expect("Unable to wrap message");
+
+        let mut buffer = vec![];
+        write_message(&mut buffer, &first).expect("Unable to write message");
+        write_message(&mut buffer, &second).expect("Unable to write message");
+
+        let mut reader = &buffer[..];
+        assert_eq!(
+            Some(first),
+            read_message(&mut reader).expect("Unable to read message")
+        );
+        assert_eq!(
+            Some(second),
+            read_message(&mut reader).expect("Unable to read message")
+        );
+        assert_eq!(
+            None,
+            read_message(&mut reader).expect("Unable to read message")
+        );
+
+        // A message cut off part way through is an error, not the end of the stream.
+        let mut truncated = &buffer[..buffer.len() - 1];
+        assert!(read_message(&mut truncated)
+            .expect("Unable to read message")
+            .is_some());
+        assert!(read_message(&mut truncated).is_err());
+
+        let mut oversized = &(MAX_MESSAGE_SIZE as u32 + 1).to_be_bytes()[..];
+        match read_message(&mut oversized) {
+            Err(SubprocessProtocolError::MessageTooLarge(_)) => (),
+            other => panic!("Expected MessageTooLarge, got {:?}", other),
+        }
+    }
+}
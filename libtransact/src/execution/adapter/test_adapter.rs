@@ -17,16 +17,20 @@
 
 use crate::context::ContextId;
 use crate::execution::adapter::{
-    ExecutionAdapter, ExecutionAdapterError, ExecutionResult, OnDoneCallback, OnRegisterCallback,
-    OnUnregisterCallback, TransactionFamily, TransactionStatus,
+    ContextStateHandler, ExecutionAdapter, ExecutionAdapterError, ExecutionResult, OnDoneCallback,
+    OnRegisterCallback, OnUnregisterCallback, TransactionFamily, TransactionFamilyRegistration,
+    TransactionStatus,
 };
-use crate::transaction::TransactionPair;
+use crate::receipts::Event;
+use crate::signing::hash::HashSigner;
+use crate::transaction::{HashMethod, TransactionBuilder, TransactionPair};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 struct TestExecutionAdapterState {
     registration_callback: Option<Box<OnRegisterCallback>>,
     unregistration_callback: Option<Box<OnUnregisterCallback>>,
-    available: bool,
+    registrations: Vec<TransactionFamilyRegistration>,
 }
 
 #[derive(Clone)]
@@ -40,23 +44,33 @@ impl TestExecutionAdapter {
             state: Arc::new(Mutex::new(TestExecutionAdapterState {
                 registration_callback: None,
                 unregistration_callback: None,
-                available: false,
+                registrations: vec![],
             })),
         }
     }
 
     pub fn register(&self, name: &str, version: &str) {
+        self.register_family(TransactionFamilyRegistration::with_versions(
+            name.to_string(),
+            vec![version.to_string()],
+        ));
+    }
+
+    pub fn register_family(&self, registration: TransactionFamilyRegistration) {
         self.state
             .lock()
             .expect("Noop mutex is poisoned")
-            .register(name, version);
+            .register(registration);
     }
 
     pub fn unregister(&self, name: &str, version: &str) {
         self.state
             .lock()
             .expect("Noop mutex is poisoned")
-            .unregister(name, version);
+            .unregister(TransactionFamilyRegistration::with_versions(
+                name.to_string(),
+                vec![version.to_string()],
+            ));
     }
 }
 
@@ -95,6 +109,10 @@ impl ExecutionAdapter for TestExecutionAdapter {
 
 impl TestExecutionAdapterState {
     fn on_register(&mut self, callback: Box<OnRegisterCallback>) {
+        let mut callback = callback;
+        for registration in &self.registrations {
+            callback(registration.clone());
+        }
         self.registration_callback = Some(callback);
     }
 
@@ -109,7 +127,12 @@ impl TestExecutionAdapterState {
         on_done: Box<OnDoneCallback>,
     ) {
         let mut on_done = on_done;
-        if self.available {
+        let transaction_family = TransactionFamily::from_pair(&transaction_pair);
+        let registered = self
+            .registrations
+            .iter()
+            .any(|registration| registration.matches(&transaction_family));
+        if registered {
             let transaction_status = TransactionStatus::Valid;
 
             let transaction_result = ExecutionResult {
@@ -118,6 +141,7 @@ impl TestExecutionAdapterState {
                     .header_signature()
                     .to_string(),
                 status: transaction_status,
+                resource_usage: None,
             };
 
             on_done(Ok(transaction_result));
@@ -126,19 +150,20 @@ impl TestExecutionAdapterState {
         }
     }
 
-    fn register(&mut self, name: &str, version: &str) {
+    fn register(&mut self, registration: TransactionFamilyRegistration) {
+        if !self.registrations.contains(&registration) {
+            self.registrations.push(registration.clone());
+        }
         if let Some(register_callback) = &mut self.registration_callback {
-            self.available = true;
-            let tf = TransactionFamily::new(name.to_string(), version.to_string());
-            register_callback(tf);
+            register_callback(registration);
         }
     }
 
-    fn unregister(&mut self, name: &str, version: &str) {
+    fn unregister(&mut self, registration: TransactionFamilyRegistration) {
+        self.registrations
+            .retain(|registered| *registered != registration);
         if let Some(unregister_callback) = &mut self.unregistration_callback {
-            self.available = false;
-            let tf = TransactionFamily::new(name.to_string(), version.to_string());
-            unregister_callback(tf)
+            unregister_callback(registration)
         }
     }
 }
@@ -149,6 +174,92 @@ impl Default for TestExecutionAdapter {
     }
 }
 
+/// In memory state, shared by every context.
+#[derive(Default)]
+pub struct TestContextState {
+    values: Mutex<HashMap<String, Vec<u8>>>,
+    events: Mutex<Vec<Event>>,
+}
+
+impl TestContextState {
+    pub fn with_values(values: Vec<(String, Vec<u8>)>) -> Self {
+        TestContextState {
+            values: Mutex::new(values.into_iter().collect()),
+            events: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn value(&self, address: &str) -> Option<Vec<u8>> {
+        self.values
+            .lock()
+            .expect("The values lock is poisoned")
+            .get(address)
+            .cloned()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events
+            .lock()
+            .expect("The events lock is poisoned")
+            .clone()
+    }
+}
+
+impl ContextStateHandler for TestContextState {
+    fn get_state(
+        &self,
+        _context_id: &ContextId,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let values = self.values.lock().expect("The values lock is poisoned");
+        Ok(addresses
+            .iter()
+            .filter_map(|address| {
+                values
+                    .get(address)
+                    .map(|value| (address.clone(), value.clone()))
+            })
+            .collect())
+    }
+
+    fn set_state(
+        &self,
+        _context_id: &ContextId,
+        entries: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), String> {
+        self.values
+            .lock()
+            .expect("The values lock is poisoned")
+            .extend(entries);
+        Ok(())
+    }
+
+    fn add_event(&self, _context_id: &ContextId, event: Event) -> Result<(), String> {
+        self.events
+            .lock()
+            .expect("The events lock is poisoned")
+            .push(event);
+        Ok(())
+    }
+}
+
+/// A transaction of version 1.0 of the family, signed by a `HashSigner`, whose nonce and
+/// payload are both `payload`.
+pub fn create_txn(family_name: &str, payload: &str) -> TransactionPair {
+    TransactionBuilder::new()
+        .with_batcher_public_key(vec![1; 33])
+        .with_dependencies(vec![])
+        .with_family_name(family_name.to_string())
+        .with_family_version("1.0".to_string())
+        .with_inputs(vec![])
+        .with_outputs(vec![])
+        .with_nonce(payload.as_bytes().to_vec())
+        .with_payload_hash_method(HashMethod::SHA512)
+        .with_payload(payload.as_bytes().to_vec())
+        .build_pair(&HashSigner::new())
+        .expect("The TransactionBuilder was not given the correct items")
+}
+
 #[cfg(test)]
 mod tests {
 
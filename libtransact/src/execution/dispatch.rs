@@ -0,0 +1,240 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! Strategies for choosing between several `ExecutionAdapter`s that have registered
+//! the same `TransactionFamily`.
+
+use crate::execution::adapter::TransactionFamily;
+use crate::execution::executer_internal::NamedExecutionEventSender;
+use std::collections::HashMap;
+
+/// Chooses which of the `NamedExecutionEventSender`s registered for a `TransactionFamily`
+/// the next `ExecutionEvent` is sent to.
+///
+/// The senders are always passed in the order in which they registered.
+pub trait DispatchStrategy: Send {
+    fn select<'a>(
+        &mut self,
+        transaction_family: &TransactionFamily,
+        senders: &'a [NamedExecutionEventSender],
+    ) -> Option<&'a NamedExecutionEventSender>;
+}
+
+/// The configurable dispatch policies that ship with the `Executer`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DispatchPolicy {
+    /// Cycle through the registered senders for each `TransactionFamily`.
+    #[default]
+    RoundRobin,
+    /// Send to the sender with the fewest `ExecutionEvent`s awaiting a result.
+    LeastInFlight,
+    /// Smooth weighted round robin. The weight at index `i` applies to the
+    /// `ExecutionAdapter` at index `i` of the list given to the `Executer`; adapters
+    /// without a weight default to 1.
+    Weighted(Vec<usize>),
+}
+
+impl DispatchPolicy {
+    pub fn into_strategy(self) -> Box<dyn DispatchStrategy> {
+        match self {
+            DispatchPolicy::RoundRobin => Box::new(RoundRobinStrategy::new()),
+            DispatchPolicy::LeastInFlight => Box::new(LeastInFlightStrategy::new()),
+            DispatchPolicy::Weighted(weights) => Box::new(WeightedStrategy::new(weights)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RoundRobinStrategy {
+    next: HashMap<TransactionFamily, usize>,
+}
+
+impl RoundRobinStrategy {
+    pub fn new() -> Self {
+        RoundRobinStrategy::default()
+    }
+}
+
+impl DispatchStrategy for RoundRobinStrategy {
+    fn select<'a>(
+        &mut self,
+        transaction_family: &TransactionFamily,
+        senders: &'a [NamedExecutionEventSender],
+    ) -> Option<&'a NamedExecutionEventSender> {
+        if senders.is_empty() {
+            return None;
+        }
+
+        let next = self.next.entry(transaction_family.clone()).or_insert(0);
+        let index = *next % senders.len();
+        *next = index + 1;
+
+        senders.get(index)
+    }
+}
+
+#[derive(Default)]
+pub struct LeastInFlightStrategy;
+
+impl LeastInFlightStrategy {
+    pub fn new() -> Self {
+        LeastInFlightStrategy
+    }
+}
+
+impl DispatchStrategy for LeastInFlightStrategy {
+    fn select<'a>(
+        &mut self,
+        _transaction_family: &TransactionFamily,
+        senders: &'a [NamedExecutionEventSender],
+    ) -> Option<&'a NamedExecutionEventSender> {
+        // min_by_key returns the first minimum, so ties go to the earliest registration.
+        senders.iter().min_by_key(|sender| sender.in_flight())
+    }
+}
+
+pub struct WeightedStrategy {
+    weights: Vec<usize>,
+    current: HashMap<TransactionFamily, HashMap<usize, i64>>,
+}
+
+impl WeightedStrategy {
+    pub fn new(weights: Vec<usize>) -> Self {
+        WeightedStrategy {
+            weights,
+            current: HashMap::new(),
+        }
+    }
+
+    fn weight(&self, name: usize) -> i64 {
+        self.weights.get(name).cloned().unwrap_or(1) as i64
+    }
+}
+
+impl DispatchStrategy for WeightedStrategy {
+    fn select<'a>(
+        &mut self,
+        transaction_family: &TransactionFamily,
+        senders: &'a [NamedExecutionEventSender],
+    ) -> Option<&'a NamedExecutionEventSender> {
+        let weights: Vec<i64> = senders.iter().map(|s| self.weight(s.name())).collect();
+        let total: i64 = weights.iter().sum();
+
+        let current = self.current.entry(transaction_family.clone()).or_default();
+        current.retain(|name, _| senders.iter().any(|s| s.name() == *name));
+
+        let mut selected: Option<(&'a NamedExecutionEventSender, i64)> = None;
+        for (sender, weight) in senders.iter().zip(weights) {
+            let value = current.entry(sender.name()).or_insert(0);
+            *value += weight;
+            if selected.map(|(_, max)| *value > max).unwrap_or(true) {
+                selected = Some((sender, *value));
+            }
+        }
+
+        selected.map(|(sender, _)| {
+            current
+                .entry(sender.name())
+                .and_modify(|value| *value -= total);
+            sender
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    fn senders(count: usize) -> Vec<NamedExecutionEventSender> {
+        (0..count)
+            .map(|name| {
+                let (sender, _) = channel();
+                NamedExecutionEventSender::new(sender, name)
+            })
+            .collect()
+    }
+
+    fn family() -> TransactionFamily {
+        TransactionFamily::new("test".to_string(), "1.0".to_string())
+    }
+
+    fn select_names(
+        strategy: &mut dyn DispatchStrategy,
+        senders: &[NamedExecutionEventSender],
+        times: usize,
+    ) -> Vec<usize> {
+        (0..times)
+            .map(|_| {
+                strategy
+                    .select(&family(), senders)
+                    .expect("There are senders to select from")
+                    .name()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_strategy() {
+        let senders = senders(3);
+        let mut strategy = DispatchPolicy::RoundRobin.into_strategy();
+
+        assert_eq!(
+            vec![0, 1, 2, 0, 1, 2],
+            select_names(strategy.as_mut(), &senders, 6)
+        );
+        assert!(
+            strategy.select(&family(), &[]).is_none(),
+            "Nothing is selected when no sender is registered"
+        );
+    }
+
+    #[test]
+    fn test_least_in_flight_strategy() {
+        let senders = senders(3);
+        senders[0].increment_in_flight();
+        senders[0].increment_in_flight();
+        senders[1].increment_in_flight();
+
+        let mut strategy = DispatchPolicy::LeastInFlight.into_strategy();
+
+        assert_eq!(vec![2], select_names(strategy.as_mut(), &senders, 1));
+
+        senders[2].increment_in_flight();
+        senders[2].increment_in_flight();
+        senders[0].decrement_in_flight();
+
+        assert_eq!(vec![0], select_names(strategy.as_mut(), &senders, 1));
+    }
+
+    #[test]
+    fn test_weighted_strategy() {
+        let senders = senders(3);
+        let mut strategy = DispatchPolicy::Weighted(vec![3, 1]).into_strategy();
+
+        let names = select_names(strategy.as_mut(), &senders, 10);
+
+        assert_eq!(
+            6,
+            names.iter().filter(|name| **name == 0).count(),
+            "Adapter 0 has weight 3 out of 5"
+        );
+        assert_eq!(2, names.iter().filter(|name| **name == 1).count());
+        assert_eq!(2, names.iter().filter(|name| **name == 2).count());
+        assert_ne!(names[0], names[1], "Dispatch is interleaved");
+    }
+}
@@ -15,44 +15,71 @@
  * -----------------------------------------------------------------------------
  */
 
-use crate::execution::adapter::ExecutionAdapter;
+use crate::context::ContextId;
+use crate::execution::adapter::{
+    AsyncExecutionAdapter, AsyncExecutionAdapterBridge, ExecutionAdapter, ExecutionResult,
+    TransactionStatus,
+};
+use crate::execution::dispatch::{DispatchPolicy, DispatchStrategy};
 use crate::execution::executer_internal::{
-    ExecuterThread, RegistrationExecutionEvent, RegistrationExecutionEventSender,
+    CancellationToken, ExecuterThread, ExecuterThreadError, ParkedCounts,
+    RegistrationExecutionEvent, RegistrationExecutionEventSender,
 };
+use crate::execution::metrics::{ExecuterMetrics, ExecutionTaskTracer};
+use crate::execution::priority::SchedulePriority;
 use crate::scheduler::SchedulePair;
 use log::debug;
 use log::warn;
 use std::collections::HashMap;
 use std::error::Error;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    mpsc::channel,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    mpsc::{channel, RecvTimeoutError},
     Arc, Mutex,
 };
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 /// The `IteratorAdapter` sends all of the `Item`s from an `Iterator` along a single channel.
 ///
 /// In the normal course of an executer there will be many `IteratorAdaptor`s, one for each `Scheduler`.
 struct IteratorAdapter {
     id: usize,
+    priority: SchedulePriority,
     threads: Option<(JoinHandle<()>, JoinHandle<()>)>,
     stop: Arc<AtomicBool>,
+    /// The transaction ids of the `ExecutionTask`s sent that have not yet received a result,
+    /// with their `ContextId`s, since a schedule may repeat a transaction.
+    outstanding: Arc<Mutex<HashMap<String, Vec<ContextId>>>>,
+    /// Whether the receive thread is still waiting for results.
+    receiving: Arc<AtomicBool>,
+    cancellation: CancellationToken,
+    schedule: Option<Arc<Mutex<Box<dyn SchedulePair>>>>,
+    tracer: Option<Arc<dyn ExecutionTaskTracer>>,
 }
 
 impl IteratorAdapter {
-    fn new(id: usize) -> Self {
+    fn new(id: usize, priority: SchedulePriority) -> Self {
         IteratorAdapter {
             id,
+            priority,
             threads: None,
             stop: Arc::new(AtomicBool::new(false)),
+            outstanding: Arc::new(Mutex::new(HashMap::new())),
+            receiving: Arc::new(AtomicBool::new(true)),
+            cancellation: CancellationToken::new(),
+            schedule: None,
+            tracer: None,
         }
     }
 
+    /// Start sending the schedule's `ExecutionTask`s. `done_callback` is called once the
+    /// iterator is exhausted and every `ExecutionTask` has received a result.
     fn start(
         &mut self,
         schedule: Box<SchedulePair>,
         internal: RegistrationExecutionEventSender,
+        tracer: Arc<dyn ExecutionTaskTracer>,
         done_callback: Box<FnMut(usize) + Send>,
     ) -> Result<(), std::io::Error> {
         let stop = Arc::clone(&self.stop);
@@ -63,6 +90,16 @@ impl IteratorAdapter {
             let (sender, receiver) = channel();
 
             let it = schedule.get_schedule_iterator();
+            let schedule = Arc::new(Mutex::new(schedule));
+            self.schedule = Some(Arc::clone(&schedule));
+            self.tracer = Some(Arc::clone(&tracer));
+            let id = self.id;
+            let priority = self.priority;
+            let send_tracer = Arc::clone(&tracer);
+            let outstanding = Arc::clone(&self.outstanding);
+            let cancellation = self.cancellation.clone();
+            let iterated = Arc::new(AtomicBool::new(false));
+            let iterated_c = Arc::clone(&iterated);
 
             let join_handle = thread::Builder::new()
                 .name(format!("iterator_adapter_{}", self.id))
@@ -72,7 +109,33 @@ impl IteratorAdapter {
                             break;
                         }
 
-                        let execution_event = (sender.clone(), execution_task);
+                        send_tracer.task_scheduled(id, &execution_task);
+                        {
+                            // Checked under the lock so that a task is either cancelled
+                            // with the others, or never sent.
+                            let mut outstanding = outstanding
+                                .lock()
+                                .expect("The outstanding tasks lock is poisoned");
+                            if cancellation.is_cancelled() {
+                                break;
+                            }
+                            outstanding
+                                .entry(
+                                    execution_task
+                                        .pair()
+                                        .transaction()
+                                        .header_signature()
+                                        .to_string(),
+                                )
+                                .or_default()
+                                .push(*execution_task.context_id());
+                        }
+                        let execution_event = (
+                            sender.clone(),
+                            execution_task,
+                            priority,
+                            cancellation.clone(),
+                        );
                         let event =
                             RegistrationExecutionEvent::Execution(Box::new(execution_event));
 
@@ -80,22 +143,62 @@ impl IteratorAdapter {
                             warn!("During sending on the internal executer channel: {}", err)
                         }
                     }
+                    iterated_c.store(true, Ordering::SeqCst);
                 })?;
 
             let stop = Arc::clone(&self.stop);
-            let id = self.id;
+            let outstanding = Arc::clone(&self.outstanding);
+            let receiving = Arc::clone(&self.receiving);
+            let cancellation = self.cancellation.clone();
 
             let join_handle_receive = thread::Builder::new()
                 .name(format!("iterator_adapter_receive_thread_{}", self.id))
-                .spawn(move || loop {
-                    while let Ok(execution_result) = receiver.recv() {
-                        schedule.add_execution_result(execution_result);
+                .spawn(move || {
+                    loop {
+                        match receiver.recv_timeout(Duration::from_millis(100)) {
+                            Ok(execution_result) => {
+                                let mut outstanding = outstanding
+                                    .lock()
+                                    .expect("The outstanding tasks lock is poisoned");
+                                if cancellation.is_cancelled() {
+                                    // The schedule was given a Cancelled result instead.
+                                    debug!(
+                                        "Dropping late result for {} of cancelled schedule {}",
+                                        execution_result.transaction_id, id
+                                    );
+                                    break;
+                                }
+                                tracer.task_finished(id, &execution_result);
+                                Self::remove_outstanding(
+                                    &mut outstanding,
+                                    &execution_result.transaction_id,
+                                );
+                                schedule
+                                    .lock()
+                                    .expect("The schedule lock is poisoned")
+                                    .add_execution_result(execution_result);
+                            }
+                            Err(RecvTimeoutError::Timeout) => (),
+                            // Every sender is gone, so no more results can arrive.
+                            Err(RecvTimeoutError::Disconnected) => break,
+                        }
 
                         if stop.load(Ordering::Relaxed) {
-                            done_callback(id);
                             break;
                         }
+
+                        let complete = iterated.load(Ordering::SeqCst)
+                            && outstanding
+                                .lock()
+                                .expect("The outstanding tasks lock is poisoned")
+                                .is_empty();
+                        if complete {
+                            receiving.store(false, Ordering::SeqCst);
+                            done_callback(id);
+                            return;
+                        }
                     }
+                    receiving.store(false, Ordering::SeqCst);
                 })?;
 
             self.threads = Some((join_handle, join_handle_receive));
@@ -103,12 +206,100 @@ impl IteratorAdapter {
         Ok(())
     }
 
-    fn stop(self) {
+    /// Whether no more results will be given to the schedule.
+    fn is_settled(&self) -> bool {
+        !self.receiving.load(Ordering::SeqCst)
+    }
+
+    /// Stop sending `ExecutionTask`s and receiving results, returning the transaction
+    /// ids of the `ExecutionTask`s that never received a result.
+    fn stop(self) -> Vec<String> {
         self.stop.store(true, Ordering::Relaxed);
         if let Some((send, receive)) = self.threads {
             Self::shutdown(send);
             Self::shutdown(receive);
         }
+        let mut outstanding: Vec<String> = self
+            .outstanding
+            .lock()
+            .expect("The outstanding tasks lock is poisoned")
+            .drain()
+            .flat_map(|(transaction_id, context_ids)| {
+                std::iter::repeat_n(transaction_id, context_ids.len())
+            })
+            .collect();
+        outstanding.sort();
+        outstanding
+    }
+
+    /// Cancel the schedule: stop sending `ExecutionTask`s, give each outstanding one a
+    /// `TransactionStatus::Cancelled` result, and have the `ExecutionAdapter`s abandon
+    /// them. Results that arrive afterwards are dropped. Returns the transaction ids of
+    /// the cancelled `ExecutionTask`s.
+    ///
+    /// The threads are not joined, as the schedule's iterator may be blocked waiting for
+    /// results that will not come; they exit once it returns.
+    fn cancel(self, internal: &RegistrationExecutionEventSender) -> Vec<String> {
+        self.stop.store(true, Ordering::Relaxed);
+        let cancelled: Vec<(String, ContextId)> = {
+            let mut outstanding = self
+                .outstanding
+                .lock()
+                .expect("The outstanding tasks lock is poisoned");
+            self.cancellation.cancel();
+            outstanding
+                .drain()
+                .flat_map(|(transaction_id, context_ids)| {
+                    context_ids
+                        .into_iter()
+                        .map(move |context_id| (transaction_id.clone(), context_id))
+                })
+                .collect()
+        };
+        self.receiving.store(false, Ordering::SeqCst);
+
+        if let (Some(schedule), Some(tracer)) = (&self.schedule, &self.tracer) {
+            let schedule = schedule.lock().expect("The schedule lock is poisoned");
+            for (transaction_id, _) in cancelled.iter() {
+                let execution_result = ExecutionResult {
+                    transaction_id: transaction_id.clone(),
+                    status: TransactionStatus::Cancelled,
+                    resource_usage: None,
+                };
+                tracer.task_finished(self.id, &execution_result);
+                schedule.add_execution_result(execution_result);
+            }
+        }
+
+        let mut context_ids: Vec<ContextId> = cancelled
+            .iter()
+            .map(|(_, context_id)| *context_id)
+            .collect();
+        context_ids.sort();
+        context_ids.dedup();
+        if let Err(err) = internal.send(RegistrationExecutionEvent::Cancellation(context_ids)) {
+            warn!("During sending on the internal executer channel: {}", err);
+        }
+
+        let mut transaction_ids: Vec<String> = cancelled
+            .into_iter()
+            .map(|(transaction_id, _)| transaction_id)
+            .collect();
+        transaction_ids.sort();
+        transaction_ids
+    }
+
+    fn remove_outstanding(outstanding: &mut HashMap<String, Vec<ContextId>>, transaction_id: &str) {
+        let remaining = match outstanding.get_mut(transaction_id) {
+            Some(context_ids) => {
+                context_ids.pop();
+                context_ids.len()
+            }
+            None => return,
+        };
+        if remaining == 0 {
+            outstanding.remove(transaction_id);
+        }
     }
 
     fn shutdown(join_handle: JoinHandle<()>) {
@@ -118,24 +309,60 @@ impl IteratorAdapter {
     }
 }
 
+/// An `ExecutionTask` that had not received a result when the `Executer` shut down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbandonedTask {
+    pub schedule_id: usize,
+    pub transaction_id: String,
+}
+
+/// What was left undone when the `Executer` shut down.
+///
+/// Only `ExecutionTask`s already taken from a schedule's iterator are reported; those
+/// the iterator had yet to produce are not known to the `Executer`.
+#[derive(Debug, Default)]
+pub struct ShutdownSummary {
+    abandoned: Vec<AbandonedTask>,
+}
+
+impl ShutdownSummary {
+    pub fn abandoned(&self) -> &[AbandonedTask] {
+        &self.abandoned
+    }
+
+    /// Whether every `ExecutionTask` received a result before shutdown.
+    pub fn is_clean(&self) -> bool {
+        self.abandoned.is_empty()
+    }
+}
+
 pub struct Executer {
     schedulers: Arc<Mutex<HashMap<usize, IteratorAdapter>>>,
+    next_schedule_id: AtomicUsize,
     executer_thread: ExecuterThread,
 }
 
 impl Executer {
-    pub fn execute(&self, schedule: Box<SchedulePair>) -> Result<(), ExecuterError> {
+    /// Execute the schedule, returning its id for `cancel`.
+    pub fn execute(&self, schedule: Box<dyn SchedulePair>) -> Result<usize, ExecuterError> {
+        self.execute_with_priority(schedule, SchedulePriority::default())
+    }
+
+    /// Execute the schedule with its `ExecutionTask`s dispatched ahead of those of lower
+    /// priority schedules, subject to the starvation limit.
+    ///
+    /// Tasks only wait for those of higher priority when the `ExecutionAdapter`s are busy,
+    /// which needs `with_max_in_flight`. Without it every task is dispatched as soon as it
+    /// arrives, so the priority has no effect.
+    pub fn execute_with_priority(
+        &self,
+        schedule: Box<dyn SchedulePair>,
+        priority: SchedulePriority,
+    ) -> Result<usize, ExecuterError> {
         if let Some(sender) = self.executer_thread.sender() {
-            let index = self
-                .schedulers
-                .lock()
-                .expect("The iterator adapters map lock is poisoned")
-                .keys()
-                .max()
-                .cloned()
-                .unwrap_or(0);
+            let index = self.next_schedule_id.fetch_add(1, Ordering::SeqCst);
 
-            let mut iterator_adapter = IteratorAdapter::new(index);
+            let mut iterator_adapter = IteratorAdapter::new(index, priority);
 
             let schedulers = Arc::clone(&self.schedulers);
 
@@ -151,51 +378,235 @@ impl Executer {
                     .remove(&index);
             });
 
+            // Hold the lock until the IteratorAdapter is in the map, so that a schedule
+            // that completes right away is still removed by the done_callback.
+            let mut schedulers = self
+                .schedulers
+                .lock()
+                .expect("The iterator adapter map lock is poisoned");
+
             iterator_adapter
-                .start(schedule, sender, done_callback)
+                .start(
+                    schedule,
+                    sender,
+                    self.executer_thread.instrumentation().tracer,
+                    done_callback,
+                )
                 .map_err(|err| {
                     ExecuterError::ResourcesUnavailable(err.description().to_string())
                 })?;
 
             debug!("Execute called, creating execution adapter {}", index);
 
-            let mut schedulers = self
-                .schedulers
-                .lock()
-                .expect("The iterator adapter map lock is poisoned");
-
             schedulers.insert(index, iterator_adapter);
 
-            Ok(())
+            Ok(index)
         } else {
             Err(ExecuterError::NotStarted)
         }
     }
 
+    /// Cancel every outstanding `ExecutionTask` of the schedule with the id returned by
+    /// `execute`. Each is given a `TransactionStatus::Cancelled` result, and the
+    /// `ExecutionAdapter`s executing them are asked to `cancel`. No further
+    /// `ExecutionTask`s are taken from the schedule's iterator. Returns the transaction
+    /// ids of the cancelled `ExecutionTask`s.
+    pub fn cancel(&self, schedule_id: usize) -> Result<Vec<String>, ExecuterError> {
+        let sender = self
+            .executer_thread
+            .sender()
+            .ok_or(ExecuterError::NotStarted)?;
+
+        let iterator_adapter = self
+            .schedulers
+            .lock()
+            .expect("The IteratorAdapter mutex is poisoned")
+            .remove(&schedule_id)
+            .ok_or(ExecuterError::UnknownSchedule(schedule_id))?;
+
+        debug!("Cancelling schedule {}", schedule_id);
+
+        Ok(iterator_adapter.cancel(&sender))
+    }
+
     pub fn start(&mut self) -> Result<(), ExecuterError> {
         self.executer_thread.start().map_err(|_| {
             ExecuterError::AlreadyStarted("The Executer has already had start called.".to_string())
         })
     }
 
+    /// Stop without waiting for outstanding `ExecutionTask`s to receive results.
     pub fn stop(self) {
-        for sched in self
+        let summary = self.shutdown(false, Instant::now());
+        if !summary.is_clean() {
+            debug!(
+                "Executer stopped with {} abandoned tasks",
+                summary.abandoned().len()
+            );
+        }
+    }
+
+    /// Shut down the `Executer`, consuming it so that no new schedules are accepted.
+    ///
+    /// With `drain`, the outstanding `ExecutionTask`s are given until `deadline` to
+    /// receive results, or to reach the park timeout if one is set. Then every
+    /// `ExecutionAdapter` is sent an `ExecutionCommand::Sentinel` and every thread is
+    /// joined. The schedules' iterators must return for their threads to be joined.
+    pub fn shutdown(self, drain: bool, deadline: Instant) -> ShutdownSummary {
+        if drain {
+            while Instant::now() < deadline && !self.is_settled() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        let iterator_adapters: Vec<(usize, IteratorAdapter)> = self
             .schedulers
             .lock()
             .expect("The IteratorAdapter mutex is poisoned")
             .drain()
-        {
-            sched.1.stop();
+            .collect();
+
+        let mut abandoned = vec![];
+        for (schedule_id, iterator_adapter) in iterator_adapters {
+            abandoned.extend(iterator_adapter.stop().into_iter().map(|transaction_id| {
+                AbandonedTask {
+                    schedule_id,
+                    transaction_id,
+                }
+            }));
         }
+        abandoned.sort_by_key(|task| task.schedule_id);
+
         self.executer_thread.stop();
+
+        if !abandoned.is_empty() {
+            warn!(
+                "Executer shut down with {} tasks that did not receive a result",
+                abandoned.len()
+            );
+        }
+
+        ShutdownSummary { abandoned }
+    }
+
+    fn is_settled(&self) -> bool {
+        self.schedulers
+            .lock()
+            .expect("The IteratorAdapter mutex is poisoned")
+            .values()
+            .all(IteratorAdapter::is_settled)
     }
 
     pub fn new(execution_adapters: Vec<Box<ExecutionAdapter>>) -> Self {
         Executer {
             schedulers: Arc::new(Mutex::new(HashMap::new())),
+            next_schedule_id: AtomicUsize::new(0),
             executer_thread: ExecuterThread::new(execution_adapters),
         }
     }
+
+    /// Add an `ExecutionAdapter`, returning the id to pass to `remove_execution_adapter`.
+    ///
+    /// Adapters given to `new` have ids counting up from 0 in the order given. After
+    /// `start`, the adapter's thread starts right away and it begins receiving
+    /// `ExecutionTask`s once it registers a family.
+    pub fn add_execution_adapter(
+        &mut self,
+        execution_adapter: Box<dyn ExecutionAdapter>,
+    ) -> Result<usize, ExecuterError> {
+        self.executer_thread
+            .add_execution_adapter(execution_adapter)
+            .map_err(|err| ExecuterError::ResourcesUnavailable(err.to_string()))
+    }
+
+    /// Remove a running `ExecutionAdapter`, unregistering its families. `ExecutionTask`s
+    /// dispatched to it but not yet executed are rerouted to the remaining adapters, or
+    /// parked until one registers their family.
+    pub fn remove_execution_adapter(&mut self, adapter_id: usize) -> Result<(), ExecuterError> {
+        self.executer_thread
+            .remove_execution_adapter(adapter_id)
+            .map_err(|err| match err {
+                ExecuterThreadError::InvalidState => ExecuterError::NotStarted,
+                ExecuterThreadError::UnknownExecutionAdapter => {
+                    ExecuterError::UnknownExecutionAdapter(adapter_id)
+                }
+                ExecuterThreadError::ResourcesUnavailable => {
+                    ExecuterError::ResourcesUnavailable(err.to_string())
+                }
+            })
+    }
+
+    /// Add an `AsyncExecutionAdapter` alongside the `ExecutionAdapter`s given to `new`.
+    /// Must be called before `start`.
+    pub fn with_async_execution_adapter(
+        mut self,
+        execution_adapter: Box<dyn AsyncExecutionAdapter>,
+    ) -> Self {
+        self.executer_thread
+            .push_execution_adapter(Box::new(AsyncExecutionAdapterBridge::new(
+                execution_adapter,
+            )));
+        self
+    }
+
+    /// Use one of the provided `DispatchPolicy`s to choose between `ExecutionAdapter`s
+    /// that registered the same `TransactionFamily`. Must be called before `start`.
+    pub fn with_dispatch_policy(self, dispatch_policy: DispatchPolicy) -> Self {
+        self.with_dispatch_strategy(dispatch_policy.into_strategy())
+    }
+
+    /// Use a custom `DispatchStrategy` to choose between `ExecutionAdapter`s that
+    /// registered the same `TransactionFamily`. Must be called before `start`.
+    pub fn with_dispatch_strategy(mut self, dispatch_strategy: Box<dyn DispatchStrategy>) -> Self {
+        self.executer_thread
+            .set_dispatch_strategy(dispatch_strategy);
+        self
+    }
+
+    /// Give each `ExecutionTask` whose `TransactionFamily` has no registered
+    /// `ExecutionAdapter` a `TransactionStatus::NoHandler` result after `park_timeout`,
+    /// rather than waiting indefinitely. Must be called before `start`.
+    pub fn with_park_timeout(mut self, park_timeout: Duration) -> Self {
+        self.executer_thread.set_park_timeout(Some(park_timeout));
+        self
+    }
+
+    /// Allow each `ExecutionAdapter` at most `max_in_flight` `ExecutionTask`s awaiting
+    /// results. The rest wait in priority order. Without a limit, tasks are dispatched as
+    /// soon as they arrive, and priority only orders those that arrive together. Must be
+    /// called before `start`.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.executer_thread.set_max_in_flight(Some(max_in_flight));
+        self
+    }
+
+    /// Serve a waiting lower priority schedule after its lane has been passed over
+    /// `starvation_limit` times in a row. Defaults to `DEFAULT_STARVATION_LIMIT`. Must be
+    /// called before `start`.
+    pub fn with_starvation_limit(mut self, starvation_limit: usize) -> Self {
+        self.executer_thread.set_starvation_limit(starvation_limit);
+        self
+    }
+
+    /// Report counts, queue depths and latencies to `metrics`. Must be called before
+    /// `start`.
+    pub fn with_metrics(mut self, metrics: Arc<dyn ExecuterMetrics>) -> Self {
+        self.executer_thread.set_metrics(metrics);
+        self
+    }
+
+    /// Follow each `ExecutionTask` from the schedule's iterator to its result with
+    /// `tracer`. Must be called before `start`.
+    pub fn with_tracer(mut self, tracer: Arc<dyn ExecutionTaskTracer>) -> Self {
+        self.executer_thread.set_tracer(tracer);
+        self
+    }
+
+    /// The number of `ExecutionTask`s waiting for an `ExecutionAdapter` to register each
+    /// `TransactionFamily`. A family that stays in this map points to a missing processor.
+    pub fn parked_counts(&self) -> ParkedCounts {
+        self.executer_thread.parked_counts()
+    }
 }
 
 #[derive(Debug)]
@@ -206,6 +617,10 @@ pub enum ExecuterError {
     AlreadyStarted(String),
 
     ResourcesUnavailable(String),
+    // There is no running ExecutionAdapter with the id.
+    UnknownExecutionAdapter(usize),
+    // There is no running schedule with the id; it may have already completed.
+    UnknownSchedule(usize),
 }
 
 #[cfg(test)]
@@ -213,10 +628,15 @@ mod tests {
 
     use super::*;
     use crate::execution::adapter::test_adapter::TestExecutionAdapter;
-    use crate::execution::adapter::ExecutionResult;
+    use crate::execution::adapter::{
+        CallbackExecutionAdapterBridge, ExecutionAdapterError, OnDoneCallback, OnRegisterCallback,
+        OnUnregisterCallback, TransactionFamily, TransactionFamilyRegistration,
+    };
+    use crate::execution::metrics::prometheus::PrometheusExecuterMetrics;
     use crate::scheduler::ExecutionTask;
     use crate::signing::{hash::HashSigner, Signer};
     use crate::transaction::{HashMethod, TransactionBuilder, TransactionPair};
+    use std::sync::atomic::AtomicUsize;
     use std::time::Duration;
 
     static FAMILY_NAME1: &str = "test1";
@@ -283,6 +703,650 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_executer_async_execution_adapter() {
+        let test_execution_adapter = TestExecutionAdapter::new();
+
+        let adapter = test_execution_adapter.clone();
+
+        let mut executer = Executer::new(vec![]).with_async_execution_adapter(Box::new(
+            CallbackExecutionAdapterBridge::new(Box::new(test_execution_adapter)),
+        ));
+
+        executer.start().expect("Executer did not correctly start");
+
+        let schedule = MockSchedule::new();
+        let schedule_results = schedule.clone();
+
+        executer
+            .execute(Box::new(schedule))
+            .expect("Start has been called so the executer can execute");
+
+        adapter.register("test1", "1.0");
+        adapter.register("test2", "1.0");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(
+            schedule_results.num_results(),
+            NUMBER_OF_TRANSACTIONS,
+            "All transactions received a result from the async adapter"
+        );
+    }
+
+    #[test]
+    fn test_executer_parked_counts() {
+        let test_execution_adapter = TestExecutionAdapter::new();
+
+        let adapter = test_execution_adapter.clone();
+
+        let mut executer = Executer::new(vec![Box::new(test_execution_adapter)]);
+
+        executer.start().expect("Executer did not correctly start");
+
+        let schedule = MockSchedule::new();
+        let schedule_results = schedule.clone();
+
+        executer
+            .execute(Box::new(schedule))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        let family1 = TransactionFamily::new(FAMILY_NAME1.to_string(), FAMILY_VERSION.to_string());
+        let family2 = TransactionFamily::new(FAMILY_NAME2.to_string(), FAMILY_VERSION.to_string());
+
+        let parked_counts = executer.parked_counts();
+        assert_eq!(
+            Some(&(NUMBER_OF_TRANSACTIONS / 2)),
+            parked_counts.get(&family1)
+        );
+        assert_eq!(
+            Some(&(NUMBER_OF_TRANSACTIONS / 2)),
+            parked_counts.get(&family2)
+        );
+
+        adapter.register("test1", "1.0");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        let parked_counts = executer.parked_counts();
+        assert_eq!(None, parked_counts.get(&family1));
+        assert_eq!(
+            Some(&(NUMBER_OF_TRANSACTIONS / 2)),
+            parked_counts.get(&family2)
+        );
+        assert_eq!(NUMBER_OF_TRANSACTIONS / 2, schedule_results.num_results());
+    }
+
+    #[test]
+    fn test_executer_park_timeout() {
+        let mut executer = Executer::new(vec![Box::new(TestExecutionAdapter::new())])
+            .with_park_timeout(Duration::from_millis(50));
+
+        executer.start().expect("Executer did not correctly start");
+
+        let schedule = MockSchedule::new();
+        let schedule_results = schedule.clone();
+
+        executer
+            .execute(Box::new(schedule))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(500));
+
+        let results = schedule_results
+            .results
+            .lock()
+            .expect("The MockScheduler lock is poisoned");
+
+        assert_eq!(
+            results.len(),
+            NUMBER_OF_TRANSACTIONS,
+            "All transactions received a result though no adapter registered"
+        );
+        assert!(results
+            .iter()
+            .all(|result| matches!(result.status, TransactionStatus::NoHandler(_))));
+        assert!(executer.parked_counts().is_empty());
+    }
+
+    #[test]
+    fn test_executer_add_and_remove_execution_adapter() {
+        let test_execution_adapter1 = TestExecutionAdapter::new();
+
+        let adapter1 = test_execution_adapter1.clone();
+
+        let mut executer = Executer::new(vec![Box::new(test_execution_adapter1)]);
+
+        executer.start().expect("Executer did not correctly start");
+
+        adapter1.register("test1", "1.0");
+
+        let schedule1 = MockSchedule::new();
+        let schedule1_results = schedule1.clone();
+
+        executer
+            .execute(Box::new(schedule1))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(NUMBER_OF_TRANSACTIONS / 2, schedule1_results.num_results());
+
+        let test_execution_adapter2 = TestExecutionAdapter::new();
+        let adapter2 = test_execution_adapter2.clone();
+
+        assert_eq!(
+            1,
+            executer
+                .add_execution_adapter(Box::new(test_execution_adapter2))
+                .expect("The executer is running")
+        );
+
+        adapter2.register("test2", "1.0");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            schedule1_results.num_results(),
+            "The added adapter processed the parked test2 transactions"
+        );
+
+        executer
+            .remove_execution_adapter(0)
+            .expect("Adapter 0 is running");
+
+        assert!(matches!(
+            executer.remove_execution_adapter(0),
+            Err(ExecuterError::UnknownExecutionAdapter(0))
+        ));
+
+        let schedule2 = MockSchedule::new();
+        let schedule2_results = schedule2.clone();
+
+        executer
+            .execute(Box::new(schedule2))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        let family1 = TransactionFamily::new(FAMILY_NAME1.to_string(), FAMILY_VERSION.to_string());
+        assert_eq!(
+            Some(&(NUMBER_OF_TRANSACTIONS / 2)),
+            executer.parked_counts().get(&family1),
+            "test1 was unregistered along with the removed adapter"
+        );
+        assert_eq!(NUMBER_OF_TRANSACTIONS / 2, schedule2_results.num_results());
+
+        adapter1.register("test1", "1.0");
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(
+            Some(&(NUMBER_OF_TRANSACTIONS / 2)),
+            executer.parked_counts().get(&family1),
+            "Registrations from a removed adapter are ignored"
+        );
+
+        executer.stop();
+    }
+
+    /// Without `with_max_in_flight`, schedules of every priority are dispatched as their
+    /// tasks arrive, and all of them complete.
+    #[test]
+    fn test_executer_priority_without_max_in_flight() {
+        let test_execution_adapter = TestExecutionAdapter::new();
+
+        test_execution_adapter.register_family(TransactionFamilyRegistration::with_versions(
+            FAMILY_NAME1.to_string(),
+            vec![FAMILY_VERSION.to_string()],
+        ));
+        test_execution_adapter.register_family(TransactionFamilyRegistration::with_versions(
+            FAMILY_NAME2.to_string(),
+            vec![FAMILY_VERSION.to_string()],
+        ));
+
+        let mut executer = Executer::new(vec![Box::new(test_execution_adapter)]);
+
+        executer.start().expect("Executer did not correctly start");
+
+        let log = Arc::new(Mutex::new(vec![]));
+
+        for priority in &[SchedulePriority::Low, SchedulePriority::High] {
+            executer
+                .execute_with_priority(Box::new(TaggedSchedule::new(*priority, &log)), *priority)
+                .expect("Start has been called so the executer can execute");
+        }
+
+        std::thread::sleep(Duration::from_millis(500));
+
+        let log = log.lock().expect("The log lock is poisoned");
+        for priority in &[SchedulePriority::Low, SchedulePriority::High] {
+            assert_eq!(
+                NUMBER_OF_TRANSACTIONS,
+                log.iter().filter(|logged| *logged == priority).count(),
+                "Every {:?} priority task completed: {:?}",
+                priority,
+                log
+            );
+        }
+
+        executer.stop();
+    }
+
+    /// Once their families register, the high priority schedule's tasks are dispatched
+    /// first, with the low priority schedule served after every third high priority task.
+    #[test]
+    fn test_executer_priority_lanes() {
+        let test_execution_adapter = TestExecutionAdapter::new();
+
+        let adapter = test_execution_adapter.clone();
+
+        let mut executer = Executer::new(vec![Box::new(test_execution_adapter)])
+            .with_max_in_flight(1)
+            .with_starvation_limit(3);
+
+        executer.start().expect("Executer did not correctly start");
+
+        let log = Arc::new(Mutex::new(vec![]));
+
+        executer
+            .execute_with_priority(
+                Box::new(TaggedSchedule::new(SchedulePriority::Low, &log)),
+                SchedulePriority::Low,
+            )
+            .expect("Start has been called so the executer can execute");
+        executer
+            .execute_with_priority(
+                Box::new(TaggedSchedule::new(SchedulePriority::High, &log)),
+                SchedulePriority::High,
+            )
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        adapter.register_family(TransactionFamilyRegistration::with_versions(
+            FAMILY_NAME1.to_string(),
+            vec![FAMILY_VERSION.to_string()],
+        ));
+        adapter.register_family(TransactionFamilyRegistration::with_versions(
+            FAMILY_NAME2.to_string(),
+            vec![FAMILY_VERSION.to_string()],
+        ));
+
+        std::thread::sleep(Duration::from_millis(500));
+
+        let log = log.lock().expect("The log lock is poisoned");
+        assert_eq!(2 * NUMBER_OF_TRANSACTIONS, log.len());
+
+        let last_high = log
+            .iter()
+            .rposition(|priority| *priority == SchedulePriority::High)
+            .expect("High priority results were logged");
+        let first_low = log
+            .iter()
+            .position(|priority| *priority == SchedulePriority::Low)
+            .expect("Low priority results were logged");
+
+        assert!(
+            last_high < NUMBER_OF_TRANSACTIONS + NUMBER_OF_TRANSACTIONS / 3 + 2,
+            "High priority tasks went first: {:?}",
+            log
+        );
+        assert!(
+            first_low <= 4,
+            "The low priority schedule was not starved: {:?}",
+            log
+        );
+
+        executer.stop();
+    }
+
+    #[test]
+    fn test_executer_shutdown_drain() {
+        let test_execution_adapter = TestExecutionAdapter::new();
+
+        let adapter = test_execution_adapter.clone();
+
+        let mut executer = Executer::new(vec![Box::new(test_execution_adapter)]);
+
+        executer.start().expect("Executer did not correctly start");
+
+        let schedule = MockSchedule::new();
+        let schedule_results = schedule.clone();
+
+        executer
+            .execute(Box::new(schedule))
+            .expect("Start has been called so the executer can execute");
+
+        adapter.register("test1", "1.0");
+        adapter.register("test2", "1.0");
+
+        let summary = executer.shutdown(true, Instant::now() + Duration::from_secs(5));
+
+        assert!(summary.is_clean(), "Every task was drained");
+        assert_eq!(NUMBER_OF_TRANSACTIONS, schedule_results.num_results());
+    }
+
+    #[test]
+    fn test_executer_shutdown_abandoned() {
+        let test_execution_adapter = TestExecutionAdapter::new();
+
+        let adapter = test_execution_adapter.clone();
+
+        let mut executer = Executer::new(vec![Box::new(test_execution_adapter)]);
+
+        executer.start().expect("Executer did not correctly start");
+
+        let schedule = MockSchedule::new();
+        let schedule_results = schedule.clone();
+
+        executer
+            .execute(Box::new(schedule))
+            .expect("Start has been called so the executer can execute");
+
+        adapter.register("test1", "1.0");
+
+        let summary = executer.shutdown(true, Instant::now() + Duration::from_millis(300));
+
+        assert_eq!(NUMBER_OF_TRANSACTIONS / 2, schedule_results.num_results());
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS / 2,
+            summary.abandoned().len(),
+            "The tasks parked for test2 never received a result"
+        );
+        assert!(summary
+            .abandoned()
+            .iter()
+            .all(|abandoned| abandoned.schedule_id == 0));
+    }
+
+    #[test]
+    fn test_executer_metrics_and_tracer() {
+        let test_execution_adapter = TestExecutionAdapter::new();
+
+        let adapter = test_execution_adapter.clone();
+
+        let metrics = Arc::new(PrometheusExecuterMetrics::new());
+        let tracer = Arc::new(CountingTracer::default());
+
+        let mut executer = Executer::new(vec![Box::new(test_execution_adapter)])
+            .with_metrics(metrics.clone())
+            .with_tracer(tracer.clone());
+
+        executer.start().expect("Executer did not correctly start");
+
+        executer
+            .execute(Box::new(MockSchedule::new()))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        adapter.register("test1", "1.0");
+        adapter.register("test2", "1.0");
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        let rendered = metrics.render();
+        for family_name in &[FAMILY_NAME1, FAMILY_NAME2] {
+            let labels = format!(
+                "{{family_name=\"{}\",family_version=\"{}\"",
+                family_name, FAMILY_VERSION
+            );
+            assert!(rendered.contains(&format!(
+                "transact_executer_tasks_parked_total{}}} {}",
+                labels,
+                NUMBER_OF_TRANSACTIONS / 2
+            )));
+            assert!(rendered.contains(&format!(
+                "transact_executer_tasks_completed_total{},adapter=\"0\"}} {}",
+                labels,
+                NUMBER_OF_TRANSACTIONS / 2
+            )));
+            assert!(rendered.contains(&format!("transact_executer_parked_tasks{}}} 0", labels)));
+        }
+
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            tracer.scheduled.load(Ordering::SeqCst)
+        );
+        assert_eq!(NUMBER_OF_TRANSACTIONS, tracer.parked.load(Ordering::SeqCst));
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            tracer.dispatched.load(Ordering::SeqCst)
+        );
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            tracer.finished.load(Ordering::SeqCst)
+        );
+        assert_eq!(
+            0,
+            tracer.out_of_order.load(Ordering::SeqCst),
+            "Each task is traced as dispatched before it is executed"
+        );
+    }
+
+    /// Outstanding tasks get a Cancelled result and are cancelled on the adapter, and the
+    /// results that arrive afterwards are dropped.
+    #[test]
+    fn test_executer_cancel() {
+        let holding_adapter = HoldingExecutionAdapter::default();
+        let held = Arc::clone(&holding_adapter.held);
+        let cancelled = Arc::clone(&holding_adapter.cancelled);
+
+        let mut executer = Executer::new(vec![Box::new(holding_adapter)]);
+
+        executer.start().expect("Executer did not correctly start");
+
+        let schedule = MockSchedule::new();
+        let schedule_results = schedule.clone();
+
+        let schedule_id = executer
+            .execute(Box::new(schedule))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            held.lock().expect("The held lock is poisoned").len()
+        );
+
+        let cancelled_ids = executer
+            .cancel(schedule_id)
+            .expect("The schedule is running");
+        assert_eq!(NUMBER_OF_TRANSACTIONS, cancelled_ids.len());
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        {
+            let results = schedule_results
+                .results
+                .lock()
+                .expect("The MockScheduler lock is poisoned");
+            assert_eq!(NUMBER_OF_TRANSACTIONS, results.len());
+            assert!(results
+                .iter()
+                .all(|result| result.status == TransactionStatus::Cancelled));
+        }
+        assert_eq!(
+            vec![[0; 16]],
+            *cancelled.lock().expect("The cancelled lock is poisoned"),
+            "Every task shares a context, which was cancelled once"
+        );
+
+        for (transaction_id, mut on_done) in
+            held.lock().expect("The held lock is poisoned").drain(..)
+        {
+            on_done(Ok(ExecutionResult {
+                transaction_id,
+                status: TransactionStatus::Valid,
+                resource_usage: None,
+            }));
+        }
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            schedule_results.num_results(),
+            "The late results were dropped"
+        );
+
+        assert!(matches!(
+            executer.cancel(schedule_id),
+            Err(ExecuterError::UnknownSchedule(0))
+        ));
+
+        executer.stop();
+    }
+
+    /// An adapter that drops `on_done` on cancel does not keep the cancelled tasks in
+    /// flight, so it is still given tasks under `with_max_in_flight`.
+    #[test]
+    fn test_executer_cancel_dropping_on_done() {
+        let holding_adapter = HoldingExecutionAdapter {
+            drop_on_cancel: true,
+            ..HoldingExecutionAdapter::default()
+        };
+        let held = Arc::clone(&holding_adapter.held);
+
+        let mut executer = Executer::new(vec![Box::new(holding_adapter)])
+            .with_max_in_flight(NUMBER_OF_TRANSACTIONS);
+
+        executer.start().expect("Executer did not correctly start");
+
+        let schedule_id = executer
+            .execute(Box::new(MockSchedule::new()))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            held.lock().expect("The held lock is poisoned").len()
+        );
+
+        executer
+            .cancel(schedule_id)
+            .expect("The schedule is running");
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(held.lock().expect("The held lock is poisoned").is_empty());
+
+        executer
+            .execute(Box::new(MockSchedule::new()))
+            .expect("Start has been called so the executer can execute");
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            NUMBER_OF_TRANSACTIONS,
+            held.lock().expect("The held lock is poisoned").len(),
+            "The dropped callbacks gave back their place in flight"
+        );
+
+        executer.stop();
+    }
+
+    type HeldExecution = (String, Box<OnDoneCallback>);
+
+    /// Registers both test families and holds on to every `on_done` callback, dropping
+    /// them on cancel if `drop_on_cancel` is set.
+    #[derive(Default)]
+    struct HoldingExecutionAdapter {
+        held: Arc<Mutex<Vec<HeldExecution>>>,
+        cancelled: Arc<Mutex<Vec<ContextId>>>,
+        drop_on_cancel: bool,
+    }
+
+    impl ExecutionAdapter for HoldingExecutionAdapter {
+        fn on_register(&self, mut callback: Box<OnRegisterCallback>) {
+            for family_name in &[FAMILY_NAME1, FAMILY_NAME2] {
+                callback(TransactionFamilyRegistration::with_versions(
+                    family_name.to_string(),
+                    vec![FAMILY_VERSION.to_string()],
+                ));
+            }
+        }
+
+        fn on_unregister(&self, _callback: Box<OnUnregisterCallback>) {}
+
+        fn execute(
+            &self,
+            transaction_pair: TransactionPair,
+            _context_id: ContextId,
+            on_done: Box<OnDoneCallback>,
+        ) {
+            self.held.lock().expect("The held lock is poisoned").push((
+                transaction_pair
+                    .transaction()
+                    .header_signature()
+                    .to_string(),
+                on_done,
+            ));
+        }
+
+        fn cancel(&self, context_id: ContextId) {
+            self.cancelled
+                .lock()
+                .expect("The cancelled lock is poisoned")
+                .push(context_id);
+            if self.drop_on_cancel {
+                self.held.lock().expect("The held lock is poisoned").clear();
+            }
+        }
+
+        fn stop(self: Box<Self>) -> bool {
+            true
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingTracer {
+        scheduled: AtomicUsize,
+        parked: AtomicUsize,
+        dispatched: AtomicUsize,
+        finished: AtomicUsize,
+        dispatched_ids: Mutex<Vec<String>>,
+        /// Tasks whose execution was traced before their dispatch.
+        out_of_order: AtomicUsize,
+    }
+
+    impl ExecutionTaskTracer for CountingTracer {
+        fn task_scheduled(&self, _schedule_id: usize, _task: &ExecutionTask) {
+            self.scheduled.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn task_parked(&self, _task: &ExecutionTask) {
+            self.parked.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn task_dispatched(&self, task: &ExecutionTask, _adapter_id: usize) {
+            self.dispatched.fetch_add(1, Ordering::SeqCst);
+            self.dispatched_ids
+                .lock()
+                .expect("The dispatched lock is poisoned")
+                .push(task.pair().transaction().header_signature().to_string());
+        }
+
+        fn task_executed(
+            &self,
+            transaction_id: &str,
+            _adapter_id: usize,
+            _result: &Result<ExecutionResult, ExecutionAdapterError>,
+        ) {
+            let dispatched_ids = self
+                .dispatched_ids
+                .lock()
+                .expect("The dispatched lock is poisoned");
+            if !dispatched_ids.iter().any(|id| id == transaction_id) {
+                self.out_of_order.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn task_finished(&self, _schedule_id: usize, _result: &ExecutionResult) {
+            self.finished.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
     fn create_txn(signer: &Signer, family_name: &str) -> TransactionPair {
         TransactionBuilder::new()
             .with_batcher_public_key(hex::decode(KEY1).unwrap())
@@ -321,6 +1385,34 @@ mod tests {
             .map(move |txn_pair| ExecutionTask::new(txn_pair, context_id.clone()))
     }
 
+    /// Logs the priority of its schedule for each result, in the order received.
+    struct TaggedSchedule {
+        priority: SchedulePriority,
+        log: Arc<Mutex<Vec<SchedulePriority>>>,
+    }
+
+    impl TaggedSchedule {
+        fn new(priority: SchedulePriority, log: &Arc<Mutex<Vec<SchedulePriority>>>) -> Self {
+            TaggedSchedule {
+                priority,
+                log: Arc::clone(log),
+            }
+        }
+    }
+
+    impl SchedulePair for TaggedSchedule {
+        fn get_schedule_iterator(&self) -> Box<dyn Iterator<Item = ExecutionTask> + Send> {
+            Box::new(create_iterator())
+        }
+
+        fn add_execution_result(&self, _execution_result: ExecutionResult) {
+            self.log
+                .lock()
+                .expect("The log lock is poisoned")
+                .push(self.priority);
+        }
+    }
+
     #[derive(Clone)]
     struct MockSchedule {
         results: Arc<Mutex<Vec<ExecutionResult>>>,
@@ -25,24 +25,54 @@
 //                                                                                                --------- ExecutionAdapter
 //
 
-use crate::execution::adapter::TransactionFamily;
-use crate::execution::adapter::{ExecutionAdapter, ExecutionAdapterError, ExecutionResult};
+use crate::context::ContextId;
+use crate::execution::adapter::{
+    ExecutionAdapter, ExecutionAdapterError, ExecutionResult, OnRegisterCallback,
+    OnUnregisterCallback, TransactionFamily, TransactionFamilyRegistration, TransactionStatus,
+};
+use crate::execution::dispatch::{DispatchPolicy, DispatchStrategy};
+use crate::execution::metrics::{ExecuterMetrics, ExecutionTaskTracer, Instrumentation};
+use crate::execution::priority::{PriorityLanes, SchedulePriority, DEFAULT_STARVATION_LIMIT};
 use crate::scheduler::ExecutionTask;
-use log::warn;
-use std::collections::{HashMap, HashSet};
+use log::{debug, warn};
+use std::collections::HashMap;
 use std::error::Error;
 use std::hash::{Hash, Hasher};
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     mpsc::{channel, Receiver, Sender},
-    Arc,
+    Arc, Mutex,
 };
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// The `TransactionPair` and `ContextId` along with where to send
-/// results.
-pub type ExecutionEvent = (Sender<ExecutionResult>, ExecutionTask);
+/// results, and the priority and cancellation of the schedule they came from.
+pub type ExecutionEvent = (
+    Sender<ExecutionResult>,
+    ExecutionTask,
+    SchedulePriority,
+    CancellationToken,
+);
+
+/// Shared by the `ExecutionEvent`s of a schedule, and set when the schedule is cancelled so
+/// that its `ExecutionEvent`s are dropped wherever they are and late results discarded.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
 
 /// The type that gets sent to the `ExecutionAdapter`.
 pub enum ExecutionCommand {
@@ -50,12 +80,20 @@ pub enum ExecutionCommand {
     Event(Box<ExecutionEvent>),
     /// Shut down the execution adapter.
     Sentinel,
+    /// The execution adapter is being removed: hand back any queued `ExecutionEvent`s,
+    /// then shut down.
+    Detach,
+    /// Abandon the transaction with the `ContextId`, as its schedule was cancelled.
+    Cancel(ContextId),
 }
 
 /// A registration or unregistration request from the `ExecutionAdapter`.
 pub enum RegistrationChange {
-    UnregisterRequest((TransactionFamily, NamedExecutionEventSender)),
-    RegisterRequest((TransactionFamily, NamedExecutionEventSender)),
+    UnregisterRequest((TransactionFamilyRegistration, NamedExecutionEventSender)),
+    RegisterRequest((TransactionFamilyRegistration, NamedExecutionEventSender)),
+    /// Unregister every family of the `ExecutionAdapter` and send it an
+    /// `ExecutionCommand::Detach`.
+    DetachRequest(NamedExecutionEventSender),
 }
 
 /// One of either a `RegistrationChange` or an `ExecutionEvent`.
@@ -63,6 +101,10 @@ pub enum RegistrationChange {
 pub enum RegistrationExecutionEvent {
     RegistrationChange(RegistrationChange),
     Execution(Box<ExecutionEvent>),
+    /// A schedule was cancelled: drop its waiting `ExecutionEvent`s, whose
+    /// `CancellationToken` is already set, and have every `ExecutionAdapter` abandon the
+    /// `ExecutionEvent`s with these `ContextId`s.
+    Cancellation(Vec<ContextId>),
 }
 
 ///`RegistrationChange` and `ExecutionEvent` multiplex sender
@@ -77,23 +119,178 @@ pub type ExecutionEventSender = Sender<ExecutionCommand>;
 /// Receiver part of a channel for the `ExecutionAdapter` to receive from the internal looping thread.
 pub type ExecutionEventReceiver = Receiver<ExecutionCommand>;
 
-/// ExecutionEvents that don't currently have a `ExecutionAdapter` to send to.
-pub type ParkedExecutionEvents = Vec<ExecutionEvent>;
+/// ExecutionEvents that don't currently have a `ExecutionAdapter` to send to, along with
+/// when they were parked.
+pub type ParkedExecutionEvents = Vec<(Instant, ExecutionEvent)>;
 
 /// A Map to do lookups of `ExecutionEvent`s by `TransactionFamily` for finding `ExecutionEvent`s that were
 /// waiting for a just registered `TransactionFamily`
 pub type ParkedExecutionEventsMap = HashMap<TransactionFamily, ParkedExecutionEvents>;
 
+/// The number of parked `ExecutionEvent`s for each `TransactionFamily` that has any.
+pub type ParkedCounts = HashMap<TransactionFamily, usize>;
+
+/// The `NamedExecutionEventSender`s registered under each `TransactionFamilyRegistration`.
+///
+/// Since a registration can cover many versions of a family, the senders able to process
+/// a particular `TransactionFamily` are resolved by matching and cached until the next
+/// registration change.
+#[derive(Default)]
+pub struct FanoutMap {
+    registrations: Vec<(
+        TransactionFamilyRegistration,
+        Vec<NamedExecutionEventSender>,
+    )>,
+    resolved: HashMap<TransactionFamily, Vec<NamedExecutionEventSender>>,
+}
+
+impl FanoutMap {
+    pub fn new() -> Self {
+        FanoutMap::default()
+    }
+
+    pub fn register(
+        &mut self,
+        registration: TransactionFamilyRegistration,
+        sender: NamedExecutionEventSender,
+    ) {
+        match self
+            .registrations
+            .iter_mut()
+            .find(|(registered, _)| registered == &registration)
+        {
+            Some((_, senders)) => {
+                if !senders.contains(&sender) {
+                    senders.push(sender);
+                }
+            }
+            None => self.registrations.push((registration, vec![sender])),
+        }
+        self.resolved.clear();
+    }
+
+    pub fn unregister(
+        &mut self,
+        registration: &TransactionFamilyRegistration,
+        sender: &NamedExecutionEventSender,
+    ) {
+        for (registered, senders) in self.registrations.iter_mut() {
+            if registered == registration {
+                senders.retain(|s| s != sender);
+            }
+        }
+        self.registrations
+            .retain(|(_, senders)| !senders.is_empty());
+        self.resolved.clear();
+    }
+
+    /// Remove the sender from every registration.
+    pub fn remove_sender(&mut self, sender: &NamedExecutionEventSender) {
+        for (_, senders) in self.registrations.iter_mut() {
+            senders.retain(|s| s != sender);
+        }
+        self.registrations
+            .retain(|(_, senders)| !senders.is_empty());
+        self.resolved.clear();
+    }
+
+    /// The senders able to process the `TransactionFamily`, in the order in which
+    /// they registered.
+    pub fn senders(
+        &mut self,
+        transaction_family: &TransactionFamily,
+    ) -> &[NamedExecutionEventSender] {
+        let registrations = &self.registrations;
+        self.resolved
+            .entry(transaction_family.clone())
+            .or_insert_with(|| {
+                let mut senders: Vec<NamedExecutionEventSender> = vec![];
+                for (_, registered) in registrations
+                    .iter()
+                    .filter(|(registration, _)| registration.matches(transaction_family))
+                {
+                    for sender in registered {
+                        if !senders.contains(sender) {
+                            senders.push(sender.clone());
+                        }
+                    }
+                }
+                senders
+            })
+    }
+
+    /// Every registered sender, once each.
+    pub fn all_senders(&self) -> Vec<&NamedExecutionEventSender> {
+        let mut all: Vec<&NamedExecutionEventSender> = vec![];
+        for sender in self
+            .registrations
+            .iter()
+            .flat_map(|(_, senders)| senders.iter())
+        {
+            if !all.contains(&sender) {
+                all.push(sender);
+            }
+        }
+        all
+    }
+}
+
 /// An ExecutionEventSender along with a hashable name or id.
+///
+/// Clones share the count of `ExecutionEvent`s that have been sent but have not yet
+/// returned a result.
 #[derive(Clone)]
 pub struct NamedExecutionEventSender {
     pub sender: ExecutionEventSender,
     name: usize,
+    in_flight: Arc<AtomicUsize>,
 }
 
 impl NamedExecutionEventSender {
     pub fn new(sender: ExecutionEventSender, name: usize) -> Self {
-        NamedExecutionEventSender { sender, name }
+        NamedExecutionEventSender {
+            sender,
+            name,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn name(&self) -> usize {
+        self.name
+    }
+
+    /// The number of `ExecutionEvent`s sent that have not yet returned a result.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn increment_in_flight(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn decrement_in_flight(&self) {
+        saturating_decrement(&self.in_flight);
+    }
+}
+
+/// An `ExecutionEvent`'s place in its `ExecutionAdapter`'s `in_flight` count, given back
+/// when dropped. The `on_done` callback owns it, so an adapter that drops the callback
+/// without firing it, as it may after `cancel`, still lowers the count.
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        saturating_decrement(&self.0);
+    }
+}
+
+fn saturating_decrement(in_flight: &AtomicUsize) {
+    let mut current = in_flight.load(Ordering::SeqCst);
+    while current > 0 {
+        match in_flight.compare_exchange(current, current - 1, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => break,
+            Err(actual) => current = actual,
+        }
     }
 }
 
@@ -115,6 +312,7 @@ impl Eq for NamedExecutionEventSender {}
 pub enum ExecuterThreadError {
     InvalidState,
     ResourcesUnavailable,
+    UnknownExecutionAdapter,
 }
 
 impl std::error::Error for ExecuterThreadError {
@@ -130,6 +328,9 @@ impl std::error::Error for ExecuterThreadError {
             ExecuterThreadError::ResourcesUnavailable => {
                 "ExecuterThread unable to access a resource needed for operation"
             }
+            ExecuterThreadError::UnknownExecutionAdapter => {
+                "ExecuterThread has no running execution adapter with that id"
+            }
         }
     }
 }
@@ -141,29 +342,163 @@ impl std::fmt::Display for ExecuterThreadError {
             ExecuterThreadError::ResourcesUnavailable => {
                 write!(f, "ResourcesUnavailable: {}", self.description())
             }
+            ExecuterThreadError::UnknownExecutionAdapter => {
+                write!(f, "UnknownExecutionAdapter: {}", self.description())
+            }
         }
     }
 }
 
+/// An `ExecutionAdapter` whose thread has been started.
+struct RunningExecutionAdapter {
+    sender: NamedExecutionEventSender,
+    join_handle: JoinHandle<()>,
+    /// Set once removal starts, so that later registrations from the adapter are ignored.
+    detached: Arc<AtomicBool>,
+}
+
 pub struct ExecuterThread {
     execution_adapters: Vec<Box<ExecutionAdapter>>,
-    join_handles: Vec<JoinHandle<()>>,
+    running_adapters: Vec<RunningExecutionAdapter>,
+    next_adapter_id: usize,
     internal_thread: Option<JoinHandle<()>>,
     sender: Option<RegistrationExecutionEventSender>,
     stop: Arc<AtomicBool>,
+    dispatch_strategy: Option<Box<dyn DispatchStrategy>>,
+    park_timeout: Option<Duration>,
+    max_in_flight: Option<usize>,
+    starvation_limit: usize,
+    parked_counts: Arc<Mutex<ParkedCounts>>,
+    instrumentation: Instrumentation,
 }
 
 impl ExecuterThread {
     pub fn new(execution_adapters: Vec<Box<ExecutionAdapter>>) -> Self {
         ExecuterThread {
             execution_adapters,
-            join_handles: vec![],
+            running_adapters: vec![],
+            next_adapter_id: 0,
             internal_thread: None,
             sender: None,
             stop: Arc::new(AtomicBool::new(false)),
+            dispatch_strategy: None,
+            park_timeout: None,
+            max_in_flight: None,
+            starvation_limit: DEFAULT_STARVATION_LIMIT,
+            parked_counts: Arc::new(Mutex::new(HashMap::new())),
+            instrumentation: Instrumentation::default(),
+        }
+    }
+
+    /// Add an `ExecutionAdapter` to be started by `start`, after those given to `new`.
+    pub fn push_execution_adapter(&mut self, execution_adapter: Box<dyn ExecutionAdapter>) {
+        self.execution_adapters.push(execution_adapter);
+    }
+
+    /// Add an `ExecutionAdapter`, returning the id it is known by. Before `start` it is
+    /// started along with the others; after `start` its thread is started right away.
+    pub fn add_execution_adapter(
+        &mut self,
+        execution_adapter: Box<dyn ExecutionAdapter>,
+    ) -> Result<usize, ExecuterThreadError> {
+        match self.sender() {
+            Some(registration_sender) => {
+                self.attach_execution_adapter(execution_adapter, &registration_sender)
+            }
+            None => {
+                self.push_execution_adapter(execution_adapter);
+                Ok(self.execution_adapters.len() - 1)
+            }
         }
     }
 
+    /// Remove a running `ExecutionAdapter`, unregistering all of its families.
+    ///
+    /// `ExecutionEvent`s queued for the adapter are sent back through the internal thread,
+    /// as with a `RoutingError`, before the adapter is stopped and its thread joined.
+    /// Adapters should fire `on_done` with a `RoutingError` for anything they still hold
+    /// when stopped, so that it is rerouted too.
+    pub fn remove_execution_adapter(
+        &mut self,
+        adapter_id: usize,
+    ) -> Result<(), ExecuterThreadError> {
+        let registration_sender = self.sender().ok_or(ExecuterThreadError::InvalidState)?;
+        let position = self
+            .running_adapters
+            .iter()
+            .position(|adapter| adapter.sender.name() == adapter_id)
+            .ok_or(ExecuterThreadError::UnknownExecutionAdapter)?;
+
+        // The adapter stays running, and can be removed again, if the internal thread is gone.
+        let detached = &self.running_adapters[position].detached;
+        detached.store(true, Ordering::SeqCst);
+        if registration_sender
+            .send(RegistrationExecutionEvent::RegistrationChange(
+                RegistrationChange::DetachRequest(self.running_adapters[position].sender.clone()),
+            ))
+            .is_err()
+        {
+            detached.store(false, Ordering::SeqCst);
+            return Err(ExecuterThreadError::ResourcesUnavailable);
+        }
+
+        let adapter = self.running_adapters.remove(position);
+        if let Err(err) = adapter.join_handle.join() {
+            warn!("During removal of execution adapter thread: {:?}", err);
+        }
+        Ok(())
+    }
+
+    /// Set how `ExecutionEvent`s are spread across the `ExecutionAdapter`s that
+    /// registered the same `TransactionFamily`. Has no effect after `start`.
+    pub fn set_dispatch_strategy(&mut self, dispatch_strategy: Box<dyn DispatchStrategy>) {
+        self.dispatch_strategy = Some(dispatch_strategy);
+    }
+
+    /// Set how long an `ExecutionEvent` may wait for an `ExecutionAdapter` to register its
+    /// `TransactionFamily` before it gets a `TransactionStatus::NoHandler` result. Without
+    /// a timeout, `ExecutionEvent`s wait indefinitely. Has no effect after `start`.
+    pub fn set_park_timeout(&mut self, park_timeout: Option<Duration>) {
+        self.park_timeout = park_timeout;
+    }
+
+    /// Set how many `ExecutionEvent`s each `ExecutionAdapter` may have awaiting results.
+    /// The rest wait in the priority lanes. Has no effect after `start`.
+    pub fn set_max_in_flight(&mut self, max_in_flight: Option<usize>) {
+        self.max_in_flight = max_in_flight;
+    }
+
+    /// Set how many times in a row a waiting priority lane may be passed over before it
+    /// is served. Has no effect after `start`.
+    pub fn set_starvation_limit(&mut self, starvation_limit: usize) {
+        self.starvation_limit = starvation_limit;
+    }
+
+    /// Set the `ExecuterMetrics` that receive counts, queue depths and latencies. Has no
+    /// effect after `start`.
+    pub fn set_metrics(&mut self, metrics: Arc<dyn ExecuterMetrics>) {
+        self.instrumentation.metrics = metrics;
+    }
+
+    /// Set the `ExecutionTaskTracer` that follows each `ExecutionTask`. Has no effect
+    /// after `start`.
+    pub fn set_tracer(&mut self, tracer: Arc<dyn ExecutionTaskTracer>) {
+        self.instrumentation.tracer = tracer;
+    }
+
+    pub fn instrumentation(&self) -> Instrumentation {
+        self.instrumentation.clone()
+    }
+
+    /// The number of `ExecutionEvent`s currently waiting for an `ExecutionAdapter` to
+    /// register each `TransactionFamily`.
+    pub fn parked_counts(&self) -> ParkedCounts {
+        self.parked_counts
+            .lock()
+            .expect("The parked counts lock is poisoned")
+            .clone()
+    }
+
     pub fn sender(&self) -> Option<RegistrationExecutionEventSender> {
         self.sender.as_ref().cloned()
     }
@@ -172,34 +507,10 @@ impl ExecuterThread {
         if self.sender.is_none() {
             let (registration_sender, receiver) = channel();
 
-            for (index, execution_adapter) in self.execution_adapters.drain(0..).enumerate() {
-                let (sender, adapter_receiver) = channel();
-                let ee_sender = NamedExecutionEventSender::new(sender, index);
-                Self::add_register_callback(
-                    execution_adapter.as_ref(),
-                    ee_sender.clone(),
-                    registration_sender.clone(),
-                );
-                Self::add_unregister_callback(
-                    execution_adapter.as_ref(),
-                    ee_sender,
-                    registration_sender.clone(),
-                );
-                match Self::start_execution_adapter_thread(
-                    Arc::clone(&self.stop),
-                    execution_adapter,
-                    adapter_receiver,
-                    &registration_sender,
-                    index,
-                ) {
-                    Ok(join_handle) => {
-                        self.join_handles.push(join_handle);
-                    }
-                    Err(err) => {
-                        warn!("Unable to start thread for execution adapter: {}", err);
-                        return Err(ExecuterThreadError::ResourcesUnavailable);
-                    }
-                }
+            let execution_adapters: Vec<Box<dyn ExecutionAdapter>> =
+                self.execution_adapters.drain(0..).collect();
+            for execution_adapter in execution_adapters {
+                self.attach_execution_adapter(execution_adapter, &registration_sender)?;
             }
 
             self.sender = Some(registration_sender);
@@ -219,6 +530,57 @@ impl ExecuterThread {
         }
     }
 
+    fn attach_execution_adapter(
+        &mut self,
+        execution_adapter: Box<dyn ExecutionAdapter>,
+        registration_sender: &RegistrationExecutionEventSender,
+    ) -> Result<usize, ExecuterThreadError> {
+        let index = self.next_adapter_id;
+        let (sender, adapter_receiver) = channel();
+        let ee_sender = NamedExecutionEventSender::new(sender, index);
+        let in_flight = Arc::clone(&ee_sender.in_flight);
+        let detached = Arc::new(AtomicBool::new(false));
+        Self::add_register_callback(
+            execution_adapter.as_ref(),
+            ee_sender.clone(),
+            registration_sender.clone(),
+            Arc::clone(&detached),
+        );
+        Self::add_unregister_callback(
+            execution_adapter.as_ref(),
+            ee_sender.clone(),
+            registration_sender.clone(),
+        );
+        match Self::start_execution_adapter_thread(
+            Arc::clone(&self.stop),
+            execution_adapter,
+            adapter_receiver,
+            registration_sender,
+            index,
+            in_flight,
+            self.instrumentation.clone(),
+        ) {
+            Ok(join_handle) => {
+                self.next_adapter_id += 1;
+                self.running_adapters.push(RunningExecutionAdapter {
+                    sender: ee_sender,
+                    join_handle,
+                    detached,
+                });
+                Ok(index)
+            }
+            Err(err) => {
+                warn!("Unable to start thread for execution adapter: {}", err);
+                Err(ExecuterThreadError::ResourcesUnavailable)
+            }
+        }
+    }
+
+    /// Stop the internal thread, then send `ExecutionCommand::Sentinel` to every
+    /// `ExecutionAdapter` and wait for all of the threads to exit.
+    ///
+    /// `ExecutionEvent`s already sent to an `ExecutionAdapter` are handed to it before
+    /// the `Sentinel`.
     pub fn stop(self) {
         self.stop.store(true, Ordering::Relaxed);
         if let Some(internal) = self.internal_thread {
@@ -226,6 +588,21 @@ impl ExecuterThread {
                 warn!("During stop of executer thread: {:?}", err);
             }
         }
+        for adapter in self.running_adapters.iter() {
+            if adapter
+                .sender
+                .sender
+                .send(ExecutionCommand::Sentinel)
+                .is_err()
+            {
+                debug!("The execution adapter thread had already exited");
+            }
+        }
+        for adapter in self.running_adapters {
+            if let Err(err) = adapter.join_handle.join() {
+                warn!("During stop of execution adapter thread: {:?}", err);
+            }
+        }
     }
 
     fn start_execution_adapter_thread(
@@ -234,6 +611,8 @@ impl ExecuterThread {
         receiver: ExecutionEventReceiver,
         sender: &RegistrationExecutionEventSender,
         index: usize,
+        in_flight: Arc<AtomicUsize>,
+        instrumentation: Instrumentation,
     ) -> Result<JoinHandle<()>, std::io::Error> {
         let sender = sender.clone();
 
@@ -244,10 +623,52 @@ impl ExecuterThread {
                 if let Ok(execution_command) = receiver.recv_timeout(Duration::from_millis(200)) {
                     match execution_command {
                         ExecutionCommand::Event(execution_event) => {
-                            let (results_sender, task) = *execution_event;
+                            let (results_sender, task, priority, cancellation) = *execution_event;
+                            if cancellation.is_cancelled() {
+                                saturating_decrement(&in_flight);
+                                debug!("Skipping a transaction whose schedule was cancelled");
+                                continue;
+                            }
+                            // Recorded here rather than once sent, so that they come before
+                            // the callback's, which may fire before `execute` returns.
+                            let metrics = &instrumentation.metrics;
+                            metrics
+                                .task_dispatched(&TransactionFamily::from_pair(task.pair()), index);
+                            metrics.adapter_queue_depth(index, in_flight.load(Ordering::SeqCst));
+                            instrumentation.tracer.task_dispatched(&task, index);
                             let (pair, context_id) = task.take();
+                            let in_flight = Arc::clone(&in_flight);
+                            let mut in_flight_guard = Some(InFlightGuard(Arc::clone(&in_flight)));
+                            let instrumentation = instrumentation.clone();
+                            let tf = TransactionFamily::from_pair(&pair);
+                            let transaction_id = pair.transaction().header_signature().to_string();
+                            let started_at = Instant::now();
 
                             let callback = Box::new(move |result| {
+                                drop(in_flight_guard.take());
+                                let metrics = &instrumentation.metrics;
+                                metrics
+                                    .adapter_queue_depth(index, in_flight.load(Ordering::SeqCst));
+                                metrics.execution_latency(&tf, index, started_at.elapsed());
+                                instrumentation.tracer.task_executed(
+                                    &transaction_id,
+                                    index,
+                                    &result,
+                                );
+                                if result.is_ok() {
+                                    metrics.task_completed(&tf, index);
+                                } else {
+                                    metrics.task_retried(&tf, index);
+                                }
+
+                                if cancellation.is_cancelled() {
+                                    debug!(
+                                        "Dropping the result of {}, as its schedule was cancelled",
+                                        transaction_id
+                                    );
+                                    return;
+                                }
+
                                 let res_sender = results_sender.clone();
                                 match result {
                                     Ok(tp_processing_result) => {
@@ -264,7 +685,12 @@ impl ExecuterThread {
 
                                         let execution_task =
                                             ExecutionTask::new(transaction_pair, context_id);
-                                        let execution_event = (res_sender, execution_task);
+                                        let execution_event = (
+                                            res_sender,
+                                            execution_task,
+                                            priority,
+                                            cancellation.clone(),
+                                        );
                                         if let Err(err) =
                                             sender.send(RegistrationExecutionEvent::Execution(
                                                 Box::new(execution_event),
@@ -278,7 +704,12 @@ impl ExecuterThread {
 
                                         let execution_task =
                                             ExecutionTask::new(transaction_pair, context_id);
-                                        let execution_event = (res_sender, execution_task);
+                                        let execution_event = (
+                                            res_sender,
+                                            execution_task,
+                                            priority,
+                                            cancellation.clone(),
+                                        );
                                         if let Err(err) =
                                             sender.send(RegistrationExecutionEvent::Execution(
                                                 Box::new(execution_event),
@@ -295,6 +726,30 @@ impl ExecuterThread {
                             execution_adapter.stop();
                             break;
                         }
+                        ExecutionCommand::Detach => {
+                            // Nothing is sent to this adapter after the Detach, so every
+                            // queued ExecutionEvent is already in the channel.
+                            while let Ok(execution_command) = receiver.try_recv() {
+                                if let ExecutionCommand::Event(execution_event) = execution_command
+                                {
+                                    saturating_decrement(&in_flight);
+                                    instrumentation.metrics.task_retried(
+                                        &TransactionFamily::from_pair(execution_event.1.pair()),
+                                        index,
+                                    );
+                                    if let Err(err) = sender.send(
+                                        RegistrationExecutionEvent::Execution(execution_event),
+                                    ) {
+                                        warn!("During reroute from detached adapter: {}", err);
+                                    }
+                                }
+                            }
+                            execution_adapter.stop();
+                            break;
+                        }
+                        ExecutionCommand::Cancel(context_id) => {
+                            execution_adapter.cancel(context_id);
+                        }
                     }
                 } else if stop.load(Ordering::Relaxed) {
                     execution_adapter.stop();
@@ -304,107 +759,207 @@ impl ExecuterThread {
     }
 
     fn start_thread(
-        &self,
+        &mut self,
         receiver: RegistrationExecutionEventReceiver,
     ) -> Result<JoinHandle<()>, std::io::Error> {
         let stop = Arc::clone(&self.stop);
+        let mut dispatch_strategy = self
+            .dispatch_strategy
+            .take()
+            .unwrap_or_else(|| DispatchPolicy::default().into_strategy());
+        let park_timeout = self.park_timeout;
+        let max_in_flight = self.max_in_flight;
+        let starvation_limit = self.starvation_limit;
+        let parked_counts = Arc::clone(&self.parked_counts);
+        let instrumentation = self.instrumentation.clone();
         std::thread::Builder::new()
             .name("internal_executer_thread".to_string())
             .spawn(move || {
-                let mut fanout_threads: HashMap<
-                    TransactionFamily,
-                    HashSet<NamedExecutionEventSender>,
-                > = HashMap::new();
+                let mut fanout_threads = FanoutMap::new();
                 let mut parked: ParkedExecutionEventsMap = HashMap::new();
                 let mut unparked = vec![];
+                let mut lanes = PriorityLanes::new(starvation_limit);
                 loop {
                     for execution_event in unparked.drain(0..) {
-                        Self::try_send_execution_event(
-                            Box::new(execution_event),
-                            &fanout_threads,
-                            &mut parked,
-                        );
+                        let execution_event: ExecutionEvent = execution_event;
+                        if !execution_event.3.is_cancelled() {
+                            lanes.push(execution_event.2, Box::new(execution_event));
+                        }
                     }
 
-                    if let Ok(reg_execution_event) =
-                        receiver.recv_timeout(Duration::from_millis(200))
-                    {
-                        match reg_execution_event {
-                            RegistrationExecutionEvent::Execution(execution_event) => {
-                                Self::try_send_execution_event(
-                                    execution_event,
-                                    &fanout_threads,
-                                    &mut parked,
-                                )
-                            }
-                            RegistrationExecutionEvent::RegistrationChange(
-                                RegistrationChange::RegisterRequest((transaction_family, sender)),
-                            ) => {
-                                if let Some(p) = parked.get_mut(&transaction_family) {
-                                    unparked.append(p);
-                                }
-                                let found = if let Some(ea_senders) =
-                                    fanout_threads.get_mut(&transaction_family)
-                                {
-                                    ea_senders.insert(sender);
-                                    None
-                                } else {
-                                    let mut s = HashSet::new();
-                                    s.insert(sender);
-                                    Some(s)
-                                };
+                    Self::dispatch_execution_events(
+                        &mut lanes,
+                        &mut fanout_threads,
+                        &mut parked,
+                        dispatch_strategy.as_mut(),
+                        max_in_flight,
+                        &instrumentation,
+                    );
 
-                                if let Some(f) = found {
-                                    fanout_threads.insert(transaction_family, f);
-                                }
-                            }
-                            RegistrationExecutionEvent::RegistrationChange(
-                                RegistrationChange::UnregisterRequest((transaction_family, sender)),
-                            ) => {
-                                fanout_threads
-                                    .entry(transaction_family)
-                                    .and_modify(|ea_senders| {
-                                        ea_senders.remove(&sender);
-                                    });
-                            }
-                        }
-                    } else if stop.load(Ordering::Relaxed) {
-                        for sender in
-                            fanout_threads
-                                .values()
-                                .fold(HashSet::new(), |mut set, item| {
-                                    for s in item {
-                                        set.insert(s);
-                                    }
-                                    set
-                                })
+                    if let Some(park_timeout) = park_timeout {
+                        Self::expire_parked_execution_events(
+                            &mut parked,
+                            park_timeout,
+                            &instrumentation,
+                        );
+                    }
+                    Self::update_parked_counts(&parked, &parked_counts, &instrumentation);
+
+                    // ExecutionEvents held back by max_in_flight are retried shortly, as
+                    // adapters free up.
+                    let timeout = if lanes.is_empty() {
+                        Duration::from_millis(200)
+                    } else {
+                        Duration::from_millis(1)
+                    };
+
+                    if let Ok(reg_execution_event) = receiver.recv_timeout(timeout) {
+                        // Take everything available, so that the lanes can order it.
+                        for reg_execution_event in
+                            std::iter::once(reg_execution_event).chain(receiver.try_iter())
                         {
-                            if let Err(err) = sender.sender.send(ExecutionCommand::Sentinel) {
-                                warn!("During stop of ExecuterThread internal thread: {}", err);
-                            }
+                            Self::receive_event(
+                                reg_execution_event,
+                                &mut lanes,
+                                &mut fanout_threads,
+                                &mut parked,
+                                &mut unparked,
+                            );
                         }
-
+                    } else if stop.load(Ordering::Relaxed) {
                         break;
                     }
                 }
             })
     }
 
+    fn receive_event(
+        reg_execution_event: RegistrationExecutionEvent,
+        lanes: &mut PriorityLanes<Box<ExecutionEvent>>,
+        fanout_threads: &mut FanoutMap,
+        parked: &mut ParkedExecutionEventsMap,
+        unparked: &mut Vec<ExecutionEvent>,
+    ) {
+        match reg_execution_event {
+            RegistrationExecutionEvent::Execution(execution_event) => {
+                lanes.push(execution_event.2, execution_event);
+            }
+            RegistrationExecutionEvent::RegistrationChange(
+                RegistrationChange::RegisterRequest((registration, sender)),
+            ) => {
+                for (transaction_family, p) in parked.iter_mut() {
+                    if registration.matches(transaction_family) {
+                        unparked.extend(p.drain(0..).map(|(_, event)| event));
+                    }
+                }
+                fanout_threads.register(registration, sender);
+            }
+            RegistrationExecutionEvent::RegistrationChange(
+                RegistrationChange::UnregisterRequest((registration, sender)),
+            ) => {
+                fanout_threads.unregister(&registration, &sender);
+            }
+            RegistrationExecutionEvent::RegistrationChange(RegistrationChange::DetachRequest(
+                sender,
+            )) => {
+                fanout_threads.remove_sender(&sender);
+                if let Err(err) = sender.sender.send(ExecutionCommand::Detach) {
+                    warn!("During detach of execution adapter: {}", err);
+                }
+            }
+            RegistrationExecutionEvent::Cancellation(context_ids) => {
+                lanes.retain(|execution_event| !execution_event.3.is_cancelled());
+                for parked_events in parked.values_mut() {
+                    parked_events.retain(|(_, execution_event)| !execution_event.3.is_cancelled());
+                }
+                unparked.retain(|execution_event| !execution_event.3.is_cancelled());
+
+                for sender in fanout_threads.all_senders() {
+                    for context_id in context_ids.iter() {
+                        if let Err(err) = sender.sender.send(ExecutionCommand::Cancel(*context_id))
+                        {
+                            warn!("During cancel on execution adapter: {}", err);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send, or park, the `ExecutionEvent`s in the lanes in priority order. Those whose
+    /// adapters are all at `max_in_flight` stay in the lanes.
+    fn dispatch_execution_events(
+        lanes: &mut PriorityLanes<Box<ExecutionEvent>>,
+        fanout_threads: &mut FanoutMap,
+        parked: &mut ParkedExecutionEventsMap,
+        dispatch_strategy: &mut dyn DispatchStrategy,
+        max_in_flight: Option<usize>,
+        instrumentation: &Instrumentation,
+    ) {
+        loop {
+            let next = match max_in_flight {
+                Some(max_in_flight) => lanes.pop_where(|execution_event| {
+                    let tf = TransactionFamily::from_pair(execution_event.1.pair());
+                    let senders = fanout_threads.senders(&tf);
+                    senders.is_empty()
+                        || senders
+                            .iter()
+                            .any(|sender| sender.in_flight() < max_in_flight)
+                }),
+                None => lanes.pop(),
+            };
+            match next {
+                Some((_, execution_event)) => Self::try_send_execution_event(
+                    execution_event,
+                    fanout_threads,
+                    parked,
+                    dispatch_strategy,
+                    max_in_flight,
+                    instrumentation,
+                ),
+                None => break,
+            }
+        }
+    }
+
+    /// Send the `ExecutionEvent` to an `ExecutionAdapter` below `max_in_flight`, or park
+    /// it if none has registered its family.
     fn try_send_execution_event(
         execution_event: Box<ExecutionEvent>,
-        fanout_threads: &HashMap<TransactionFamily, HashSet<NamedExecutionEventSender>>,
+        fanout_threads: &mut FanoutMap,
         parked: &mut ParkedExecutionEventsMap,
+        dispatch_strategy: &mut dyn DispatchStrategy,
+        max_in_flight: Option<usize>,
+        instrumentation: &Instrumentation,
     ) {
+        if execution_event.3.is_cancelled() {
+            debug!("Dropping a transaction whose schedule was cancelled");
+            return;
+        }
+
         let tf = TransactionFamily::from_pair(&execution_event.1.pair());
-        if let Some(ea_senders) = fanout_threads.get(&tf) {
-            if let Some(sender) = ea_senders.iter().nth(0) {
-                if let Err(err) = sender.sender.send(ExecutionCommand::Event(execution_event)) {
-                    warn!("During send of ExecutionCommand: {}", err);
-                }
-            } else {
-                Self::park_execution_event(parked, *execution_event, tf);
+        let senders = fanout_threads.senders(&tf);
+        let available: Vec<NamedExecutionEventSender>;
+        let candidates = match max_in_flight {
+            Some(max_in_flight) => {
+                available = senders
+                    .iter()
+                    .filter(|sender| sender.in_flight() < max_in_flight)
+                    .cloned()
+                    .collect();
+                &available[..]
+            }
+            _ => senders,
+        };
+        if let Some(sender) = dispatch_strategy.select(&tf, candidates) {
+            sender.increment_in_flight();
+            if let Err(err) = sender.sender.send(ExecutionCommand::Event(execution_event)) {
+                sender.decrement_in_flight();
+                warn!("During send of ExecutionCommand: {}", err);
             }
         } else {
+            instrumentation.metrics.task_parked(&tf);
+            instrumentation.tracer.task_parked(&execution_event.1);
             Self::park_execution_event(parked, *execution_event, tf);
         }
     }
@@ -414,14 +969,15 @@ impl ExecuterThread {
         execution_event: ExecutionEvent,
         transaction_family: TransactionFamily,
     ) {
+        let parked_at = Instant::now();
         let p: Option<ParkedExecutionEvents> = match parked.get_mut(&transaction_family) {
             Some(p) => {
-                p.push(execution_event);
+                p.push((parked_at, execution_event));
                 None
             }
             None => {
                 let mut p = vec![];
-                p.push(execution_event);
+                p.push((parked_at, execution_event));
                 Some(p)
             }
         };
@@ -430,16 +986,97 @@ impl ExecuterThread {
         }
     }
 
+    /// Return a `TransactionStatus::NoHandler` result for every `ExecutionEvent` that has
+    /// been parked for at least `park_timeout`.
+    fn expire_parked_execution_events(
+        parked: &mut ParkedExecutionEventsMap,
+        park_timeout: Duration,
+        instrumentation: &Instrumentation,
+    ) {
+        let now = Instant::now();
+        for (transaction_family, parked_events) in parked.iter_mut() {
+            let (expired, waiting): (ParkedExecutionEvents, ParkedExecutionEvents) = parked_events
+                .drain(0..)
+                .partition(|(parked_at, _)| now.duration_since(*parked_at) >= park_timeout);
+            *parked_events = waiting;
+
+            if !expired.is_empty() {
+                warn!(
+                    "No execution adapter registered for {:?} within {:?}, {} transactions have no handler",
+                    transaction_family,
+                    park_timeout,
+                    expired.len()
+                );
+            }
+
+            for (_, (results_sender, task, _, cancellation)) in expired {
+                if cancellation.is_cancelled() {
+                    continue;
+                }
+                instrumentation.metrics.task_timed_out(transaction_family);
+                let execution_result = ExecutionResult {
+                    transaction_id: task.pair().transaction().header_signature().to_string(),
+                    status: TransactionStatus::NoHandler(transaction_family.clone()),
+                    resource_usage: None,
+                };
+                if let Err(err) = results_sender.send(execution_result) {
+                    warn!("Sending NoHandler result on channel: {}", err);
+                }
+            }
+        }
+        parked.retain(|_, parked_events| !parked_events.is_empty());
+    }
+
+    fn update_parked_counts(
+        parked: &ParkedExecutionEventsMap,
+        parked_counts: &Mutex<ParkedCounts>,
+        instrumentation: &Instrumentation,
+    ) {
+        let mut counts = parked_counts
+            .lock()
+            .expect("The parked counts lock is poisoned");
+        if counts.is_empty() && parked.values().all(|p| p.is_empty()) {
+            return;
+        }
+        let updated: ParkedCounts = parked
+            .iter()
+            .filter(|(_, p)| !p.is_empty())
+            .map(|(transaction_family, p)| (transaction_family.clone(), p.len()))
+            .collect();
+
+        // Families that drained are reported once with a depth of zero.
+        for transaction_family in counts.keys() {
+            if !updated.contains_key(transaction_family) {
+                instrumentation
+                    .metrics
+                    .parked_queue_depth(transaction_family, 0);
+            }
+        }
+        for (transaction_family, depth) in updated.iter() {
+            if counts.get(transaction_family) != Some(depth) {
+                instrumentation
+                    .metrics
+                    .parked_queue_depth(transaction_family, *depth);
+            }
+        }
+        *counts = updated;
+    }
+
     fn add_register_callback(
         execution_adapter: &ExecutionAdapter,
         sender: NamedExecutionEventSender,
         register_sender: RegistrationExecutionEventSender,
+        detached: Arc<AtomicBool>,
     ) {
-        let callback: Box<FnMut(TransactionFamily) + Send> =
-            Box::new(move |transaction_family: TransactionFamily| {
+        let callback: Box<OnRegisterCallback> =
+            Box::new(move |registration: TransactionFamilyRegistration| {
+                if detached.load(Ordering::SeqCst) {
+                    debug!("Ignoring registration from a removed execution adapter");
+                    return;
+                }
                 if let Err(err) =
                     register_sender.send(RegistrationExecutionEvent::RegistrationChange(
-                        RegistrationChange::RegisterRequest((transaction_family, sender.clone())),
+                        RegistrationChange::RegisterRequest((registration, sender.clone())),
                     ))
                 {
                     warn!(
@@ -456,11 +1093,11 @@ impl ExecuterThread {
         sender: NamedExecutionEventSender,
         unregister_sender: RegistrationExecutionEventSender,
     ) {
-        let callback: Box<FnMut(TransactionFamily) + Send> =
-            Box::new(move |transaction_family: TransactionFamily| {
+        let callback: Box<OnUnregisterCallback> =
+            Box::new(move |registration: TransactionFamilyRegistration| {
                 if let Err(err) =
                     unregister_sender.send(RegistrationExecutionEvent::RegistrationChange(
-                        RegistrationChange::UnregisterRequest((transaction_family, sender.clone())),
+                        RegistrationChange::UnregisterRequest((registration, sender.clone())),
                     ))
                 {
                     warn!(
@@ -481,7 +1118,7 @@ mod tests {
     };
     use crate::signing::{hash::HashSigner, Signer};
     use crate::transaction::{HashMethod, TransactionBuilder, TransactionPair};
-    use std::{self, collections::HashSet, sync::mpsc::channel};
+    use std::{self, sync::mpsc::channel};
 
     static FAMILY_NAME: &str = "test";
     static FAMILY_VERSION: &str = "1.0";
@@ -517,7 +1154,7 @@ mod tests {
         let tf = TransactionFamily::new(FAMILY_NAME.to_string(), FAMILY_VERSION.to_string());
         let named_sender = NamedExecutionEventSender::new(execution_adapter_sender, 0);
         let registration_event = RegistrationExecutionEvent::RegistrationChange(
-            RegistrationChange::RegisterRequest((tf, named_sender)),
+            RegistrationChange::RegisterRequest((tf.into(), named_sender)),
         );
 
         registration_execution_event_sender
@@ -529,7 +1166,14 @@ mod tests {
         // Send the ExecutionEvents on the multiplexing channel.
 
         for reg_ex_event in execution_tasks
-            .map(|execution_task| (sender.clone(), execution_task))
+            .map(|execution_task| {
+                (
+                    sender.clone(),
+                    execution_task,
+                    SchedulePriority::default(),
+                    CancellationToken::new(),
+                )
+            })
             .map(|execution_event| RegistrationExecutionEvent::Execution(Box::new(execution_event)))
         {
             registration_execution_event_sender
@@ -539,45 +1183,30 @@ mod tests {
 
         let mut parked_transaction_map: ParkedExecutionEventsMap = HashMap::new();
         let mut unparked_transactions: Vec<ExecutionEvent> = vec![];
-        let mut named_senders: HashMap<TransactionFamily, HashSet<NamedExecutionEventSender>> =
-            HashMap::new();
+        let mut named_senders = FanoutMap::new();
 
         // Main Executer loop
 
         while let Ok(event) = internal_receiver.try_recv() {
             match event {
                 RegistrationExecutionEvent::Execution(execution_event) => {
-                    let (_, execution_state) = execution_event.as_ref();
+                    let (_, execution_state, _, _) = execution_event.as_ref();
 
                     let tf = TransactionFamily::from_pair(execution_state.pair());
-                    match named_senders.get(&tf) {
-                        Some(senders) => match senders.iter().nth(0) {
-                            Some(sender) => {
-                                sender
-                                    .sender
-                                    .send(ExecutionCommand::Event(execution_event))
-                                    .expect("The receiver has been dropped");
-                            }
-                            None => {
-                                let parked = match parked_transaction_map.get_mut(&tf) {
-                                    Some(parked) => {
-                                        parked.push(*execution_event);
-                                        None
-                                    }
-                                    None => Some(vec![*execution_event]),
-                                };
-                                if let Some(p) = parked {
-                                    parked_transaction_map.insert(tf, p);
-                                }
-                            }
-                        },
+                    match named_senders.senders(&tf).first() {
+                        Some(sender) => {
+                            sender
+                                .sender
+                                .send(ExecutionCommand::Event(execution_event))
+                                .expect("The receiver has been dropped");
+                        }
                         None => {
                             let parked = match parked_transaction_map.get_mut(&tf) {
                                 Some(parked) => {
-                                    parked.push(*execution_event);
+                                    parked.push((Instant::now(), *execution_event));
                                     None
                                 }
-                                None => Some(vec![*execution_event]),
+                                None => Some(vec![(Instant::now(), *execution_event)]),
                             };
                             if let Some(p) = parked {
                                 parked_transaction_map.insert(tf, p);
@@ -587,36 +1216,25 @@ mod tests {
                 }
                 RegistrationExecutionEvent::RegistrationChange(registration_event) => {
                     match registration_event {
-                        RegistrationChange::RegisterRequest((tf, sender)) => {
-                            parked_transaction_map
-                                .entry(tf.clone())
-                                .and_modify(|parked| {
-                                    for p in parked.drain(0..) {
+                        RegistrationChange::RegisterRequest((registration, sender)) => {
+                            for (tf, parked) in parked_transaction_map.iter_mut() {
+                                if registration.matches(tf) {
+                                    for (_, p) in parked.drain(0..) {
                                         unparked_transactions.push(p);
                                     }
-                                });
-                            let senders_option = match named_senders.get_mut(&tf) {
-                                Some(senders) => {
-                                    senders.insert(sender);
-                                    None
-                                }
-                                None => {
-                                    let mut s = HashSet::new();
-                                    s.insert(sender);
-                                    Some(s)
                                 }
-                            };
-                            if let Some(senders) = senders_option {
-                                named_senders.insert(tf, senders);
                             }
+                            named_senders.register(registration, sender);
                         }
-                        RegistrationChange::UnregisterRequest((tf, sender)) => {
-                            named_senders.entry(tf).and_modify(|senders| {
-                                senders.remove(&sender);
-                            });
+                        RegistrationChange::UnregisterRequest((registration, sender)) => {
+                            named_senders.unregister(&registration, &sender);
+                        }
+                        RegistrationChange::DetachRequest(sender) => {
+                            named_senders.remove_sender(&sender);
                         }
                     }
                 }
+                RegistrationExecutionEvent::Cancellation(_) => (),
             }
         }
 
@@ -624,11 +1242,12 @@ mod tests {
 
         while let Ok(event) = receiver.try_recv() {
             if let ExecutionCommand::Event(execution_event) = event {
-                let (result_sender, task) = *execution_event;
+                let (result_sender, task, _, _) = *execution_event;
                 let transaction_status = TransactionStatus::Valid;
                 let execution_result = ExecutionResult {
                     transaction_id: task.pair().transaction().header_signature().to_string(),
                     status: transaction_status,
+                    resource_usage: None,
                 };
                 result_sender
                     .send(execution_result)
@@ -672,7 +1291,14 @@ mod tests {
         let (s, receiver) = channel();
 
         for reg_ex_event in execution_tasks
-            .map(|execution_task| (s.clone(), execution_task))
+            .map(|execution_task| {
+                (
+                    s.clone(),
+                    execution_task,
+                    SchedulePriority::default(),
+                    CancellationToken::new(),
+                )
+            })
             .map(|execution_event| RegistrationExecutionEvent::Execution(Box::new(execution_event)))
         {
             sender
@@ -702,6 +1328,143 @@ mod tests {
         executer_thread.stop();
     }
 
+    /// An adapter whose removal could not be requested of the internal thread is kept.
+    #[test]
+    fn test_executer_thread_remove_without_internal_thread() {
+        let mut executer_thread = ExecuterThread::new(vec![Box::new(TestExecutionAdapter::new())]);
+        executer_thread
+            .start()
+            .expect("Start can only be called once");
+
+        executer_thread.stop.store(true, Ordering::Relaxed);
+        executer_thread
+            .internal_thread
+            .take()
+            .expect("The internal thread was started")
+            .join()
+            .expect("The internal thread panicked");
+
+        assert!(matches!(
+            executer_thread.remove_execution_adapter(0),
+            Err(ExecuterThreadError::ResourcesUnavailable)
+        ));
+        assert_eq!(1, executer_thread.running_adapters.len());
+        assert!(!executer_thread.running_adapters[0]
+            .detached
+            .load(Ordering::SeqCst));
+
+        executer_thread.stop();
+    }
+
+    /// Parked ExecutionEvents are sent once an ExecutionAdapter registers a compatible, rather
+    /// than identical, version of their TransactionFamily.
+    #[test]
+    fn test_executer_thread_version_requirement() {
+        let noop_adapter = TestExecutionAdapter::new();
+
+        let adapter = noop_adapter.clone();
+
+        let mut executer_thread: ExecuterThread = ExecuterThread::new(vec![Box::new(noop_adapter)]);
+
+        executer_thread
+            .start()
+            .expect("Start can only be called once");
+
+        let sender = executer_thread
+            .sender()
+            .expect("Sender is some after start is called");
+
+        let (s, receiver) = channel();
+
+        for reg_ex_event in create_iterator()
+            .map(|execution_task| {
+                (
+                    s.clone(),
+                    execution_task,
+                    SchedulePriority::default(),
+                    CancellationToken::new(),
+                )
+            })
+            .map(|execution_event| RegistrationExecutionEvent::Execution(Box::new(execution_event)))
+        {
+            sender
+                .send(reg_ex_event)
+                .expect("Receiver has been dropped");
+        }
+
+        adapter.register_family(
+            TransactionFamilyRegistration::with_requirement(FAMILY_NAME.to_string(), "1.x")
+                .expect("The requirement is valid semver"),
+        );
+
+        let mut results = vec![];
+
+        while let Ok(result) = receiver.recv_timeout(Duration::from_millis(200)) {
+            results.push(result);
+        }
+
+        assert_eq!(
+            results.len(),
+            NUMBER_OF_TRANSACTIONS,
+            "Incorrect number of results received",
+        );
+
+        executer_thread.stop();
+    }
+
+    #[test]
+    fn test_fanout_map() {
+        let (exact_sender, _exact_receiver) = channel();
+        let exact = NamedExecutionEventSender::new(exact_sender, 0);
+        let (requirement_sender, _requirement_receiver) = channel();
+        let requirement = NamedExecutionEventSender::new(requirement_sender, 1);
+
+        let exact_registration = TransactionFamilyRegistration::with_versions(
+            FAMILY_NAME.to_string(),
+            vec![FAMILY_VERSION.to_string()],
+        );
+        let requirement_registration =
+            TransactionFamilyRegistration::with_requirement(FAMILY_NAME.to_string(), ">=1, <2")
+                .expect("The requirement is valid semver");
+
+        let mut fanout_map = FanoutMap::new();
+        fanout_map.register(exact_registration.clone(), exact.clone());
+        fanout_map.register(requirement_registration, requirement.clone());
+
+        let version_1_0 =
+            TransactionFamily::new(FAMILY_NAME.to_string(), FAMILY_VERSION.to_string());
+        let version_1_5 = TransactionFamily::new(FAMILY_NAME.to_string(), "1.5".to_string());
+
+        assert_eq!(
+            vec![0, 1],
+            fanout_map
+                .senders(&version_1_0)
+                .iter()
+                .map(|s| s.name())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1],
+            fanout_map
+                .senders(&version_1_5)
+                .iter()
+                .map(|s| s.name())
+                .collect::<Vec<_>>()
+        );
+
+        fanout_map.unregister(&exact_registration, &exact);
+
+        assert_eq!(
+            vec![1],
+            fanout_map
+                .senders(&version_1_0)
+                .iter()
+                .map(|s| s.name())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, fanout_map.all_senders().len());
+    }
+
     fn create_txn(signer: &Signer) -> TransactionPair {
         TransactionBuilder::new()
             .with_batcher_public_key(hex::decode(KEY1).unwrap())
@@ -0,0 +1,120 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! Hooks for observing the `Executer`: metrics about the work it does and span-style
+//! tracing of each `ExecutionTask` from the schedule's iterator to its result.
+//!
+//! Every method has a no-op default, so implementers only override what they record.
+//! The hooks are called from the `Executer`'s internal threads and should return quickly.
+
+pub mod prometheus;
+
+use crate::execution::adapter::{ExecutionAdapterError, ExecutionResult, TransactionFamily};
+use crate::scheduler::ExecutionTask;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Receives counts, queue depths and latencies from the `Executer`.
+///
+/// Adapters are identified by their index in the list given to the `Executer`.
+pub trait ExecuterMetrics: Send + Sync {
+    /// An `ExecutionTask` was sent to an `ExecutionAdapter`.
+    fn task_dispatched(&self, _transaction_family: &TransactionFamily, _adapter_id: usize) {}
+
+    /// An `ExecutionAdapter` returned an `ExecutionResult`.
+    fn task_completed(&self, _transaction_family: &TransactionFamily, _adapter_id: usize) {}
+
+    /// An `ExecutionAdapter` returned an `ExecutionAdapterError`, so the `ExecutionTask` is
+    /// being retried.
+    fn task_retried(&self, _transaction_family: &TransactionFamily, _adapter_id: usize) {}
+
+    /// An `ExecutionTask` is waiting for an `ExecutionAdapter` to register its family.
+    fn task_parked(&self, _transaction_family: &TransactionFamily) {}
+
+    /// A parked `ExecutionTask` reached the park timeout and was given a
+    /// `TransactionStatus::NoHandler` result.
+    fn task_timed_out(&self, _transaction_family: &TransactionFamily) {}
+
+    /// The number of `ExecutionTask`s parked for the family has changed.
+    fn parked_queue_depth(&self, _transaction_family: &TransactionFamily, _depth: usize) {}
+
+    /// The number of `ExecutionTask`s sent to the adapter and awaiting a result has changed.
+    fn adapter_queue_depth(&self, _adapter_id: usize, _depth: usize) {}
+
+    /// The time between handing an `ExecutionTask` to an `ExecutionAdapter` and its
+    /// `on_done` callback firing.
+    fn execution_latency(
+        &self,
+        _transaction_family: &TransactionFamily,
+        _adapter_id: usize,
+        _latency: Duration,
+    ) {
+    }
+}
+
+/// Span-style hooks that follow a single `ExecutionTask` through the `Executer`.
+///
+/// A span starts at `task_scheduled` and ends at `task_finished`; the `ExecutionTask`s
+/// are identified by their transaction's header signature.
+pub trait ExecutionTaskTracer: Send + Sync {
+    /// The schedule's iterator produced the `ExecutionTask`.
+    fn task_scheduled(&self, _schedule_id: usize, _task: &ExecutionTask) {}
+
+    /// The `ExecutionTask` is waiting for an `ExecutionAdapter` to register its family.
+    fn task_parked(&self, _task: &ExecutionTask) {}
+
+    /// The `ExecutionTask` is being handed to an `ExecutionAdapter`.
+    fn task_dispatched(&self, _task: &ExecutionTask, _adapter_id: usize) {}
+
+    /// The `ExecutionAdapter` fired `on_done` for the transaction.
+    fn task_executed(
+        &self,
+        _transaction_id: &str,
+        _adapter_id: usize,
+        _result: &Result<ExecutionResult, ExecutionAdapterError>,
+    ) {
+    }
+
+    /// The schedule received the `ExecutionResult` for the transaction.
+    fn task_finished(&self, _schedule_id: usize, _result: &ExecutionResult) {}
+}
+
+/// `ExecuterMetrics` that records nothing.
+pub struct NoopExecuterMetrics;
+
+impl ExecuterMetrics for NoopExecuterMetrics {}
+
+/// An `ExecutionTaskTracer` that traces nothing.
+pub struct NoopExecutionTaskTracer;
+
+impl ExecutionTaskTracer for NoopExecutionTaskTracer {}
+
+/// The `ExecuterMetrics` and `ExecutionTaskTracer` shared by the `Executer`'s threads.
+#[derive(Clone)]
+pub struct Instrumentation {
+    pub metrics: Arc<dyn ExecuterMetrics>,
+    pub tracer: Arc<dyn ExecutionTaskTracer>,
+}
+
+impl Default for Instrumentation {
+    fn default() -> Self {
+        Instrumentation {
+            metrics: Arc::new(NoopExecuterMetrics),
+            tracer: Arc::new(NoopExecutionTaskTracer),
+        }
+    }
+}
@@ -0,0 +1,494 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! `ExecuterMetrics` that are rendered in the Prometheus text exposition format.
+
+use crate::execution::adapter::TransactionFamily;
+use crate::execution::metrics::ExecuterMetrics;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The upper bounds, in seconds, of the execution latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+/// The default number of families labelled in the parked task metrics.
+const DEFAULT_MAX_PARKED_FAMILIES: usize = 64;
+
+/// The label of the parked tasks of families over the limit.
+const OTHER_FAMILY: &str = "other";
+
+type FamilyKey = (String, String);
+type FamilyAdapterKey = (String, String, usize);
+
+#[derive(Default)]
+struct Histogram {
+    /// Non-cumulative counts, one per entry of `LATENCY_BUCKETS`.
+    buckets: [u64; 12],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+struct PrometheusState {
+    max_parked_families: usize,
+    /// The families labelled in `parked`, `timed_out` and `parked_depth`.
+    parked_families: BTreeSet<FamilyKey>,
+    dispatched: BTreeMap<FamilyAdapterKey, u64>,
+    completed: BTreeMap<FamilyAdapterKey, u64>,
+    retried: BTreeMap<FamilyAdapterKey, u64>,
+    parked: BTreeMap<FamilyKey, u64>,
+    timed_out: BTreeMap<FamilyKey, u64>,
+    parked_depth: BTreeMap<FamilyKey, usize>,
+    adapter_depth: BTreeMap<usize, usize>,
+    latency: BTreeMap<FamilyAdapterKey, Histogram>,
+}
+
+impl Default for PrometheusState {
+    fn default() -> Self {
+        PrometheusState {
+            max_parked_families: DEFAULT_MAX_PARKED_FAMILIES,
+            parked_families: BTreeSet::new(),
+            dispatched: BTreeMap::new(),
+            completed: BTreeMap::new(),
+            retried: BTreeMap::new(),
+            parked: BTreeMap::new(),
+            timed_out: BTreeMap::new(),
+            parked_depth: BTreeMap::new(),
+            adapter_depth: BTreeMap::new(),
+            latency: BTreeMap::new(),
+        }
+    }
+}
+
+impl PrometheusState {
+    /// The key of a parked task's family, or `None` if the family is one too many to be
+    /// labelled. Parked tasks come from any client, registered family or not, so each new
+    /// family would otherwise be a new series.
+    fn parked_family_key(&mut self, transaction_family: &TransactionFamily) -> Option<FamilyKey> {
+        let key = family_key(transaction_family);
+        if self.parked_families.contains(&key) {
+            Some(key)
+        } else if self.parked_families.len() < self.max_parked_families {
+            self.parked_families.insert(key.clone());
+            Some(key)
+        } else {
+            None
+        }
+    }
+}
+
+/// Keeps the `Executer`'s metrics in memory and renders them for a Prometheus scrape.
+///
+/// Share it with the `Executer` through an `Arc` and serve the output of `render`
+/// from the process's metrics endpoint.
+///
+/// Parked tasks are labelled with the first families seen, up to a limit, and those of any
+/// other family are counted as `family_name="other"`.
+#[derive(Default)]
+pub struct PrometheusExecuterMetrics {
+    state: Mutex<PrometheusState>,
+}
+
+impl PrometheusExecuterMetrics {
+    pub fn new() -> Self {
+        PrometheusExecuterMetrics::default()
+    }
+
+    /// Label the parked tasks of at most `max_parked_families` families. Defaults to 64.
+    pub fn with_max_parked_families(self, max_parked_families: usize) -> Self {
+        self.with_state(|state| state.max_parked_families = max_parked_families);
+        self
+    }
+
+    /// Render every metric recorded so far in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self
+            .state
+            .lock()
+            .expect("The prometheus metrics lock is poisoned");
+
+        let mut out = String::new();
+
+        write_family_adapter_metric(
+            &mut out,
+            "transact_executer_tasks_dispatched_total",
+            "counter",
+            "Execution tasks sent to an execution adapter.",
+            &state.dispatched,
+        );
+        write_family_adapter_metric(
+            &mut out,
+            "transact_executer_tasks_completed_total",
+            "counter",
+            "Execution tasks an execution adapter returned a result for.",
+            &state.completed,
+        );
+        write_family_adapter_metric(
+            &mut out,
+            "transact_executer_tasks_retried_total",
+            "counter",
+            "Execution tasks an execution adapter returned an error for.",
+            &state.retried,
+        );
+        write_family_metric(
+            &mut out,
+            "transact_executer_tasks_parked_total",
+            "counter",
+            "Execution tasks parked until an adapter registers their family.",
+            &state.parked,
+        );
+        write_family_metric(
+            &mut out,
+            "transact_executer_tasks_timed_out_total",
+            "counter",
+            "Parked execution tasks that reached the park timeout.",
+            &state.timed_out,
+        );
+        write_family_metric(
+            &mut out,
+            "transact_executer_parked_tasks",
+            "gauge",
+            "Execution tasks currently parked.",
+            &state.parked_depth,
+        );
+
+        write_header(
+            &mut out,
+            "transact_executer_adapter_in_flight_tasks",
+            "gauge",
+            "Execution tasks sent to an execution adapter and awaiting a result.",
+        );
+        for (adapter, depth) in state.adapter_depth.iter() {
+            let _ = writeln!(
+                out,
+                "transact_executer_adapter_in_flight_tasks{{adapter=\"{}\"}} {}",
+                adapter, depth
+            );
+        }
+
+        write_header(
+            &mut out,
+            "transact_executer_execution_latency_seconds",
+            "histogram",
+            "Time from handing an execution task to an adapter until its result.",
+        );
+        for ((name, version, adapter), histogram) in state.latency.iter() {
+            let labels = family_adapter_labels(name, version, *adapter);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "transact_executer_execution_latency_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "transact_executer_execution_latency_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "transact_executer_execution_latency_seconds_sum{{{}}} {}",
+                labels, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "transact_executer_execution_latency_seconds_count{{{}}} {}",
+                labels, histogram.count
+            );
+        }
+
+        out
+    }
+
+    fn with_state<F: FnOnce(&mut PrometheusState)>(&self, f: F) {
+        f(&mut self
+            .state
+            .lock()
+            .expect("The prometheus metrics lock is poisoned"))
+    }
+}
+
+impl ExecuterMetrics for PrometheusExecuterMetrics {
+    fn task_dispatched(&self, transaction_family: &TransactionFamily, adapter_id: usize) {
+        self.with_state(|state| {
+            *state
+                .dispatched
+                .entry(family_adapter_key(transaction_family, adapter_id))
+                .or_insert(0) += 1
+        });
+    }
+
+    fn task_completed(&self, transaction_family: &TransactionFamily, adapter_id: usize) {
+        self.with_state(|state| {
+            *state
+                .completed
+                .entry(family_adapter_key(transaction_family, adapter_id))
+                .or_insert(0) += 1
+        });
+    }
+
+    fn task_retried(&self, transaction_family: &TransactionFamily, adapter_id: usize) {
+        self.with_state(|state| {
+            *state
+                .retried
+                .entry(family_adapter_key(transaction_family, adapter_id))
+                .or_insert(0) += 1
+        });
+    }
+
+    fn task_parked(&self, transaction_family: &TransactionFamily) {
+        self.with_state(|state| {
+            let key = state
+                .parked_family_key(transaction_family)
+                .unwrap_or_else(other_family_key);
+            *state.parked.entry(key).or_insert(0) += 1
+        });
+    }
+
+    fn task_timed_out(&self, transaction_family: &TransactionFamily) {
+        self.with_state(|state| {
+            let key = state
+                .parked_family_key(transaction_family)
+                .unwrap_or_else(other_family_key);
+            *state.timed_out.entry(key).or_insert(0) += 1
+        });
+    }
+
+    /// The depths of families that are not labelled are not kept, as they are not summed.
+    fn parked_queue_depth(&self, transaction_family: &TransactionFamily, depth: usize) {
+        self.with_state(|state| {
+            if let Some(key) = state.parked_family_key(transaction_family) {
+                state.parked_depth.insert(key, depth);
+            }
+        });
+    }
+
+    fn adapter_queue_depth(&self, adapter_id: usize, depth: usize) {
+        self.with_state(|state| {
+            state.adapter_depth.insert(adapter_id, depth);
+        });
+    }
+
+    fn execution_latency(
+        &self,
+        transaction_family: &TransactionFamily,
+        adapter_id: usize,
+        latency: Duration,
+    ) {
+        let seconds = latency.as_secs() as f64 + f64::from(latency.subsec_nanos()) / 1e9;
+        self.with_state(|state| {
+            state
+                .latency
+                .entry(family_adapter_key(transaction_family, adapter_id))
+                .or_default()
+                .observe(seconds)
+        });
+    }
+}
+
+fn family_key(transaction_family: &TransactionFamily) -> FamilyKey {
+    (
+        transaction_family.family_name().to_string(),
+        transaction_family.family_version().to_string(),
+    )
+}
+
+fn other_family_key() -> FamilyKey {
+    (OTHER_FAMILY.to_string(), OTHER_FAMILY.to_string())
+}
+
+fn family_adapter_key(
+    transaction_family: &TransactionFamily,
+    adapter_id: usize,
+) -> FamilyAdapterKey {
+    let (name, version) = family_key(transaction_family);
+    (name, version, adapter_id)
+}
+
+/// Escape a label value as required by the text exposition format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn family_labels(name: &str, version: &str) -> String {
+    format!(
+        "family_name=\"{}\",family_version=\"{}\"",
+        escape(name),
+        escape(version)
+    )
+}
+
+fn family_adapter_labels(name: &str, version: &str, adapter: usize) -> String {
+    format!("{},adapter=\"{}\"", family_labels(name, version), adapter)
+}
+
+fn write_header(out: &mut String, metric: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", metric, help);
+    let _ = writeln!(out, "# TYPE {} {}", metric, metric_type);
+}
+
+fn write_family_metric<V: std::fmt::Display>(
+    out: &mut String,
+    metric: &str,
+    metric_type: &str,
+    help: &str,
+    values: &BTreeMap<FamilyKey, V>,
+) {
+    write_header(out, metric, metric_type, help);
+    for ((name, version), value) in values.iter() {
+        let _ = writeln!(
+            out,
+            "{}{{{}}} {}",
+            metric,
+            family_labels(name, version),
+            value
+        );
+    }
+}
+
+fn write_family_adapter_metric(
+    out: &mut String,
+    metric: &str,
+    metric_type: &str,
+    help: &str,
+    values: &BTreeMap<FamilyAdapterKey, u64>,
+) {
+    write_header(out, metric, metric_type, help);
+    for ((name, version, adapter), value) in values.iter() {
+        let _ = writeln!(
+            out,
+            "{}{{{}}} {}",
+            metric,
+            family_adapter_labels(name, version, *adapter),
+            value
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_render() {
+        let metrics = PrometheusExecuterMetrics::new();
+        let family = TransactionFamily::new("intkey".to_string(), "1.0".to_string());
+        let quoted = TransactionFamily::new("say \"hi\"".to_string(), "1.0".to_string());
+
+        metrics.task_dispatched(&family, 0);
+        metrics.task_dispatched(&family, 0);
+        metrics.task_completed(&family, 0);
+        metrics.task_retried(&family, 0);
+        metrics.task_parked(&quoted);
+        metrics.parked_queue_depth(&quoted, 1);
+        metrics.adapter_queue_depth(0, 1);
+        metrics.execution_latency(&family, 0, Duration::from_millis(3));
+        metrics.execution_latency(&family, 0, Duration::from_secs(3));
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains("# TYPE transact_executer_tasks_dispatched_total counter\n"));
+        assert!(rendered.contains(
+            "transact_executer_tasks_dispatched_total\
+             {family_name=\"intkey\",family_version=\"1.0\",adapter=\"0\"} 2\n"
+        ));
+        assert!(rendered.contains(
+            "transact_executer_tasks_completed_total\
+             {family_name=\"intkey\",family_version=\"1.0\",adapter=\"0\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "transact_executer_tasks_parked_total\
+             {family_name=\"say \\\"hi\\\"\",family_version=\"1.0\"} 1\n"
+        ));
+        assert!(rendered.contains("transact_executer_adapter_in_flight_tasks{adapter=\"0\"} 1\n"));
+        assert!(rendered.contains(
+            "transact_executer_execution_latency_seconds_bucket\
+             {family_name=\"intkey\",family_version=\"1.0\",adapter=\"0\",le=\"0.005\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "transact_executer_execution_latency_seconds_bucket\
+             {family_name=\"intkey\",family_version=\"1.0\",adapter=\"0\",le=\"2.5\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "transact_executer_execution_latency_seconds_bucket\
+             {family_name=\"intkey\",family_version=\"1.0\",adapter=\"0\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(rendered.contains(
+            "transact_executer_execution_latency_seconds_count\
+             {family_name=\"intkey\",family_version=\"1.0\",adapter=\"0\"} 2\n"
+        ));
+    }
+
+    /// Parked tasks of families over the limit are counted together.
+    #[test]
+    fn test_prometheus_max_parked_families() {
+        let metrics = PrometheusExecuterMetrics::new().with_max_parked_families(2);
+
+        for i in 0..5 {
+            let family = TransactionFamily::new(format!("family{}", i), "1.0".to_string());
+            metrics.task_parked(&family);
+            metrics.parked_queue_depth(&family, 1);
+            metrics.task_timed_out(&family);
+        }
+        metrics.task_parked(&TransactionFamily::new(
+            "family0".to_string(),
+            "1.0".to_string(),
+        ));
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains(
+            "transact_executer_tasks_parked_total\
+             {family_name=\"family0\",family_version=\"1.0\"} 2\n"
+        ));
+        assert!(rendered.contains(
+            "transact_executer_tasks_parked_total\
+             {family_name=\"other\",family_version=\"other\"} 3\n"
+        ));
+        assert!(rendered.contains(
+            "transact_executer_tasks_timed_out_total\
+             {family_name=\"other\",family_version=\"other\"} 3\n"
+        ));
+        assert!(!rendered.contains("family2"));
+        assert_eq!(
+            2,
+            rendered
+                .lines()
+                .filter(|line| line.starts_with("transact_executer_parked_tasks{"))
+                .count()
+        );
+    }
+}
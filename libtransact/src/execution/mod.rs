@@ -19,5 +19,8 @@
 //! and return a `execution::adapter::ExecutionResult`.
 
 pub mod adapter;
+pub mod dispatch;
 pub mod executer;
 pub mod executer_internal;
+pub mod metrics;
+pub mod priority;
@@ -0,0 +1,238 @@
+/*
+ * Copyright 2019 Bitwise IO, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ * -----------------------------------------------------------------------------
+ */
+
+//! Priority lanes for the `ExecutionTask`s of concurrently running schedules.
+
+use std::collections::{BTreeMap, VecDeque};
+
+/// The number of times in a row a waiting lane may be passed over before it is served.
+pub const DEFAULT_STARVATION_LIMIT: usize = 8;
+
+/// How urgently the `ExecutionTask`s of a schedule are dispatched, relative to those of
+/// the other schedules running on the same `Executer`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchedulePriority {
+    /// Work that can wait, such as speculative block publishing.
+    Low,
+    #[default]
+    Normal,
+    /// Work that others are waiting on, such as block validation.
+    High,
+}
+
+/// A FIFO queue per `SchedulePriority`.
+///
+/// `pop` serves the highest priority lane, except that a lane passed over
+/// `starvation_limit` times in a row is served first, so that lower priorities still make
+/// progress under sustained high priority load. When several lanes are starved, the one
+/// passed over the most goes first.
+pub struct PriorityLanes<T> {
+    lanes: BTreeMap<SchedulePriority, VecDeque<T>>,
+    passed_over: BTreeMap<SchedulePriority, usize>,
+    starvation_limit: usize,
+}
+
+impl<T> PriorityLanes<T> {
+    pub fn new(starvation_limit: usize) -> Self {
+        PriorityLanes {
+            lanes: BTreeMap::new(),
+            passed_over: BTreeMap::new(),
+            starvation_limit,
+        }
+    }
+
+    pub fn push(&mut self, priority: SchedulePriority, item: T) {
+        self.lanes.entry(priority).or_default().push_back(item);
+    }
+
+    /// Return an item to the front of its lane, such as one that could not be dispatched.
+    pub fn push_front(&mut self, priority: SchedulePriority, item: T) {
+        self.lanes.entry(priority).or_default().push_front(item);
+    }
+
+    pub fn pop(&mut self) -> Option<(SchedulePriority, T)> {
+        self.pop_where(|_| true)
+    }
+
+    /// Pop the first item that `accept`s, such as one that can be dispatched right now,
+    /// choosing between lanes as `pop` does. A lane with nothing accepted is not counted
+    /// as passed over.
+    pub fn pop_where<F: FnMut(&T) -> bool>(
+        &mut self,
+        mut accept: F,
+    ) -> Option<(SchedulePriority, T)> {
+        // Highest priority first, with the position of each lane's first accepted item.
+        let ready: Vec<(SchedulePriority, usize)> = self
+            .lanes
+            .iter()
+            .rev()
+            .filter_map(|(priority, lane)| {
+                lane.iter()
+                    .position(&mut accept)
+                    .map(|position| (*priority, position))
+            })
+            .collect();
+
+        let (highest, _) = *ready.first()?;
+
+        // Lanes that emptied out start over.
+        let lanes = &self.lanes;
+        self.passed_over.retain(|priority, _| {
+            lanes
+                .get(priority)
+                .map(|lane| !lane.is_empty())
+                .unwrap_or(false)
+        });
+
+        let passed_over = &self.passed_over;
+        let times_passed_over =
+            |priority: &SchedulePriority| passed_over.get(priority).cloned().unwrap_or(0);
+
+        // max_by_key returns the last maximum, so go lowest first to favour the higher
+        // priority on a tie.
+        let chosen = ready
+            .iter()
+            .rev()
+            .map(|(priority, _)| priority)
+            .filter(|priority| times_passed_over(priority) >= self.starvation_limit)
+            .max_by_key(|priority| times_passed_over(priority))
+            .cloned()
+            .unwrap_or(highest);
+
+        let mut chosen_position = 0;
+        for (priority, position) in ready {
+            if priority == chosen {
+                chosen_position = position;
+                self.passed_over.remove(&priority);
+            } else {
+                *self.passed_over.entry(priority).or_insert(0) += 1;
+            }
+        }
+
+        self.lanes
+            .get_mut(&chosen)
+            .and_then(|lane| lane.remove(chosen_position))
+            .map(|item| (chosen, item))
+    }
+
+    /// Keep only the items for which `keep` returns true.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        for lane in self.lanes.values_mut() {
+            lane.retain(|item| keep(item));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.values().all(VecDeque::is_empty)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.values().map(VecDeque::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(lanes: &mut PriorityLanes<usize>) -> Vec<(SchedulePriority, usize)> {
+        let mut popped = vec![];
+        while let Some(item) = lanes.pop() {
+            popped.push(item);
+        }
+        popped
+    }
+
+    #[test]
+    fn test_priority_lanes_order() {
+        let mut lanes = PriorityLanes::new(DEFAULT_STARVATION_LIMIT);
+        lanes.push(SchedulePriority::Low, 0);
+        lanes.push(SchedulePriority::Normal, 1);
+        lanes.push(SchedulePriority::High, 2);
+        lanes.push(SchedulePriority::High, 3);
+        lanes.push_front(SchedulePriority::Normal, 4);
+
+        assert_eq!(5, lanes.len());
+        assert_eq!(
+            vec![
+                (SchedulePriority::High, 2),
+                (SchedulePriority::High, 3),
+                (SchedulePriority::Normal, 4),
+                (SchedulePriority::Normal, 1),
+                (SchedulePriority::Low, 0),
+            ],
+            drain(&mut lanes)
+        );
+        assert!(lanes.is_empty());
+    }
+
+    /// A low priority lane is served after being passed over the starvation limit.
+    #[test]
+    fn test_priority_lanes_fairness() {
+        let mut lanes = PriorityLanes::new(2);
+        for i in 0..6 {
+            lanes.push(SchedulePriority::High, i);
+        }
+        for i in 0..3 {
+            lanes.push(SchedulePriority::Low, i);
+        }
+
+        let priorities: Vec<SchedulePriority> =
+            drain(&mut lanes).into_iter().map(|(p, _)| p).collect();
+
+        use self::SchedulePriority::{High, Low};
+        assert_eq!(
+            vec![High, High, Low, High, High, Low, High, High, Low],
+            priorities
+        );
+    }
+
+    /// Items that are not accepted are skipped, and their lanes are not passed over.
+    #[test]
+    fn test_priority_lanes_pop_where() {
+        let mut lanes = PriorityLanes::new(1);
+        lanes.push(SchedulePriority::High, 1);
+        lanes.push(SchedulePriority::High, 2);
+        lanes.push(SchedulePriority::Low, 3);
+        lanes.push(SchedulePriority::Low, 4);
+
+        let even = |item: &usize| item.is_multiple_of(2);
+
+        assert_eq!(Some((SchedulePriority::High, 2)), lanes.pop_where(even));
+        assert_eq!(Some((SchedulePriority::Low, 4)), lanes.pop_where(even));
+        assert_eq!(None, lanes.pop_where(even));
+        assert_eq!(2, lanes.len());
+        assert_eq!(Some((SchedulePriority::High, 1)), lanes.pop());
+    }
+
+    /// With a starvation limit of 0 the lanes take turns.
+    #[test]
+    fn test_priority_lanes_no_starvation_limit() {
+        let mut lanes = PriorityLanes::new(0);
+        for i in 0..2 {
+            lanes.push(SchedulePriority::High, i);
+            lanes.push(SchedulePriority::Normal, i);
+            lanes.push(SchedulePriority::Low, i);
+        }
+
+        let priorities: Vec<SchedulePriority> =
+            drain(&mut lanes).into_iter().map(|(p, _)| p).collect();
+
+        use self::SchedulePriority::{High, Low, Normal};
+        assert_eq!(vec![High, Normal, Low, High, Normal, Low], priorities);
+    }
+}
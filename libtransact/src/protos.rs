@@ -50,6 +50,12 @@ impl std::fmt::Display for ProtoConversionError {
     }
 }
 
+impl From<protobuf::ProtobufError> for ProtoConversionError {
+    fn from(e: protobuf::ProtobufError) -> Self {
+        ProtoConversionError::SerializationError(format!("{}", e))
+    }
+}
+
 pub trait FromProto<P>: Sized {
     fn from_proto(other: P) -> Result<Self, ProtoConversionError>;
 }
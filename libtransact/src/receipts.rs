@@ -20,6 +20,7 @@
 use super::protos;
 use crate::protos::{FromNative, FromProto, IntoNative, IntoProto, ProtoConversionError};
 use std::error::Error as StdError;
+use std::time::Duration;
 
 /// A change to be applied to state, in terms of keys and values.
 ///
@@ -109,6 +110,8 @@ pub struct TransactionReceipt<K, V> {
     pub data: Vec<Vec<u8>>,
 
     pub transaction_id: String,
+    /// The resources used by the transaction's execution, if they were counted.
+    pub resource_usage: Option<ResourceUsage>,
 }
 
 impl FromProto<protos::transaction_receipt::TransactionReceipt>
@@ -132,6 +135,13 @@ impl FromProto<protos::transaction_receipt::TransactionReceipt>
                 .collect::<Result<Vec<Event>, ProtoConversionError>>()?,
             data: transaction_receipt.get_data().to_vec(),
             transaction_id: transaction_receipt.get_transaction_id().to_string(),
+            resource_usage: if transaction_receipt.has_resource_usage() {
+                Some(ResourceUsage::from_proto(
+                    transaction_receipt.get_resource_usage().clone(),
+                )?)
+            } else {
+                None
+            },
         })
     }
 }
@@ -160,6 +170,9 @@ impl FromNative<TransactionReceipt<String, Vec<u8>>>
         proto_transaction_receipt
             .set_data(protobuf::RepeatedField::from_vec(transaction_receipt.data));
         proto_transaction_receipt.set_transaction_id(transaction_receipt.transaction_id);
+        if let Some(resource_usage) = transaction_receipt.resource_usage {
+            proto_transaction_receipt.set_resource_usage(resource_usage.into_proto()?);
+        }
         Ok(proto_transaction_receipt)
     }
 }
@@ -173,6 +186,48 @@ impl IntoNative<TransactionReceipt<String, Vec<u8>>>
 {
 }
 
+/// The resources a `Transaction` used through its context while it executed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// Addresses read from state.
+    pub reads: u64,
+    /// Addresses set in state.
+    pub writes: u64,
+    /// Bytes of the values set in state.
+    pub bytes_written: u64,
+    pub events: u64,
+    pub wall_time: Duration,
+}
+
+impl FromProto<protos::transaction_receipt::ResourceUsage> for ResourceUsage {
+    fn from_proto(
+        resource_usage: protos::transaction_receipt::ResourceUsage,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(ResourceUsage {
+            reads: resource_usage.get_reads(),
+            writes: resource_usage.get_writes(),
+            bytes_written: resource_usage.get_bytes_written(),
+            events: resource_usage.get_events(),
+            wall_time: Duration::from_micros(resource_usage.get_wall_time_micros()),
+        })
+    }
+}
+
+impl FromNative<ResourceUsage> for protos::transaction_receipt::ResourceUsage {
+    fn from_native(resource_usage: ResourceUsage) -> Result<Self, ProtoConversionError> {
+        let mut proto_resource_usage = protos::transaction_receipt::ResourceUsage::new();
+        proto_resource_usage.set_reads(resource_usage.reads);
+        proto_resource_usage.set_writes(resource_usage.writes);
+        proto_resource_usage.set_bytes_written(resource_usage.bytes_written);
+        proto_resource_usage.set_events(resource_usage.events);
+        proto_resource_usage.set_wall_time_micros(resource_usage.wall_time.as_micros() as u64);
+        Ok(proto_resource_usage)
+    }
+}
+
+impl IntoProto<protos::transaction_receipt::ResourceUsage> for ResourceUsage {}
+impl IntoNative<ResourceUsage> for protos::transaction_receipt::ResourceUsage {}
+
 /// An `Event` is metadata about a `Transaction`'s processing. Events are
 /// transmitted by `transact` but are not verified or saved to state. `Event`s are
 /// generated by any of the ways that `Transaction`s are processed in `transact`.
@@ -330,6 +385,7 @@ where
     pub events: Vec<Event>,
     pub data: Vec<Vec<u8>>,
     pub transaction_id: Option<String>,
+    pub resource_usage: Option<ResourceUsage>,
 }
 
 impl<K, V> TransactionReceiptBuilder<K, V>
@@ -367,6 +423,14 @@ where
         self
     }
 
+    pub fn with_resource_usage(
+        mut self,
+        resource_usage: ResourceUsage,
+    ) -> TransactionReceiptBuilder<K, V> {
+        self.resource_usage = Some(resource_usage);
+        self
+    }
+
     pub fn build(self) -> Result<TransactionReceipt<K, V>, TransactionReceiptBuilderError> {
         let transaction_id = self.transaction_id.ok_or_else(|| {
             TransactionReceiptBuilderError::MissingField(
@@ -379,6 +443,7 @@ where
             events: self.events,
             data: self.data,
             transaction_id,
+            resource_usage: self.resource_usage,
         })
     }
 }
@@ -485,6 +550,7 @@ mod tests {
             events: vec![make_event_1(), make_event_2()],
             data: vec![BYTES1.to_vec(), BYTES2.to_vec(), BYTES3.to_vec()],
             transaction_id: TRANSACTION_ID.to_string(),
+            resource_usage: None,
         };
 
         check_transaction_receipt(transaction_receipt)
@@ -549,6 +615,38 @@ mod tests {
         assert_eq!(vec!(BYTES2.to_vec(),), transaction_receipt.data);
     }
 
+    #[test]
+    fn transaction_receipt_resource_usage_round_trip() {
+        let resource_usage = ResourceUsage {
+            reads: 3,
+            writes: 2,
+            bytes_written: 8,
+            events: 1,
+            wall_time: Duration::from_micros(1500),
+        };
+        let transaction_receipt = TransactionReceiptBuilder::new()
+            .with_transaction_id(TRANSACTION_ID.to_string())
+            .with_resource_usage(resource_usage.clone())
+            .build()
+            .unwrap();
+
+        let proto: protos::transaction_receipt::TransactionReceipt =
+            transaction_receipt.into_proto().unwrap();
+        let transaction_receipt: TransactionReceipt<String, Vec<u8>> = proto.into_native().unwrap();
+        assert_eq!(Some(resource_usage), transaction_receipt.resource_usage);
+
+        let proto: protos::transaction_receipt::TransactionReceipt =
+            TransactionReceiptBuilder::<String, Vec<u8>>::new()
+                .with_transaction_id(TRANSACTION_ID.to_string())
+                .build()
+                .unwrap()
+                .into_proto()
+                .unwrap();
+        assert!(!proto.has_resource_usage());
+        let transaction_receipt: TransactionReceipt<String, Vec<u8>> = proto.into_native().unwrap();
+        assert_eq!(None, transaction_receipt.resource_usage);
+    }
+
     #[test]
     fn transaction_receipt_builder_chain() {
         let transaction_receipt = TransactionReceiptBuilder::new()
@@ -668,6 +766,7 @@ mod benchmarks {
             events: vec![make_event_1(), make_event_2()],
             data: vec![BYTES1.to_vec(), BYTES2.to_vec(), BYTES3.to_vec()],
             transaction_id: TRANSACTION_ID.to_string(),
+            resource_usage: None,
         });
     }
 
@@ -734,6 +833,7 @@ mod benchmarks {
             events: vec![make_event_1(), make_event_2()],
             data: vec![BYTES1.to_vec(), BYTES2.to_vec(), BYTES3.to_vec()],
             transaction_id: TRANSACTION_ID.to_string(),
+            resource_usage: None,
         };
 
         b.iter(|| transaction_receipt.clone().into_proto());
@@ -0,0 +1,408 @@
+//! A `Signer` whose key is held by a separate signing daemon, so that it is never in this
+//! process's memory.
+//!
+//! The daemon listens on a Unix domain socket. Each `SigningDaemonRequest` and
+//! `SigningDaemonResponse` is written as a 4 byte big-endian length followed by the encoded
+//! message, and the daemon answers each request before the next is sent.
+
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use protobuf::Message;
+
+use crate::protos::signing_daemon::{
+    SigningDaemonRequest, SigningDaemonRequest_RequestType, SigningDaemonResponse,
+    SigningDaemonResponse_Status,
+};
+use crate::signing::{Error, Signer};
+
+/// How long to wait for the daemon to answer a request, by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest response, in bytes, that will be read.
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Write a length-prefixed message and flush the writer.
+fn write_message<W: Write, M: Message>(writer: &mut W, message: &M) -> io::Result<()> {
+    let bytes = message
+        .write_to_bytes()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+    writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+/// Read a length-prefixed message.
+fn read_message<R: Read, M: Message>(reader: &mut R) -> io::Result<M> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message of {} bytes is too large", length),
+        ));
+    }
+
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    M::parse_from_bytes(&bytes)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+}
+
+/// The connection to the daemon, and the id of the next request on it.
+struct Connection {
+    stream: UnixStream,
+    next_correlation_id: u64,
+}
+
+impl Connection {
+    fn open(socket_path: &Path, timeout: Duration) -> io::Result<Self> {
+        let stream = UnixStream::connect(socket_path)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(Connection {
+            stream,
+            next_correlation_id: 1,
+        })
+    }
+
+    /// Send the request and wait for its response. An `Err` means the connection can no
+    /// longer be used; a response with a status of `ERROR` is returned as `Ok`.
+    fn request(
+        &mut self,
+        request_type: SigningDaemonRequest_RequestType,
+        message: &[u8],
+    ) -> io::Result<SigningDaemonResponse> {
+        let correlation_id = self.next_correlation_id;
+        self.next_correlation_id += 1;
+
+        let mut request = SigningDaemonRequest::new();
+        request.set_request_type(request_type);
+        request.set_correlation_id(correlation_id);
+        request.set_message(message.to_vec());
+        write_message(&mut self.stream, &request)?;
+
+        let response: SigningDaemonResponse = read_message(&mut self.stream)?;
+        if response.get_correlation_id() != correlation_id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Response to request {} received for request {}",
+                    response.get_correlation_id(),
+                    correlation_id
+                ),
+            ));
+        }
+        Ok(response)
+    }
+
+    /// The algorithm name and public key of the daemon's key.
+    fn key_info(&mut self) -> io::Result<Result<(String, Vec<u8>), String>> {
+        let mut response = self.request(SigningDaemonRequest_RequestType::KEY_INFO, &[])?;
+        match response.get_status() {
+            SigningDaemonResponse_Status::OK => Ok(Ok((
+                response.take_algorithm_name(),
+                response.take_public_key(),
+            ))),
+            _ => Ok(Err(response.take_error_message())),
+        }
+    }
+}
+
+/// Signs by asking a signing daemon, listening on a Unix domain socket, for signatures.
+///
+/// The connection is kept between signatures. If it fails or a request times out, it is
+/// reopened and the request sent again, once, before `sign` returns an error.
+pub struct DaemonSigner {
+    socket_path: PathBuf,
+    timeout: Duration,
+    algorithm_name: String,
+    public_key: Vec<u8>,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl DaemonSigner {
+    /// Connect to the daemon listening on the socket, and ask for its public key.
+    pub fn connect<P: AsRef<Path>>(socket_path: P) -> Result<Self, Error> {
+        DaemonSigner::connect_with_timeout(socket_path, DEFAULT_TIMEOUT)
+    }
+
+    /// Connect to the daemon listening on the socket, waiting up to `timeout` for each
+    /// request to it to be answered.
+    pub fn connect_with_timeout<P: AsRef<Path>>(
+        socket_path: P,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let socket_path = socket_path.as_ref().to_path_buf();
+        let error = |msg: String| {
+            Error::SigningError(format!(
+                "Unable to get the key of the signing daemon at {}: {}",
+                socket_path.display(),
+                msg
+            ))
+        };
+
+        let mut connection =
+            Connection::open(&socket_path, timeout).map_err(|err| error(err.to_string()))?;
+        let (algorithm_name, public_key) = connection
+            .key_info()
+            .map_err(|err| error(err.to_string()))?
+            .map_err(error)?;
+
+        Ok(DaemonSigner {
+            socket_path,
+            timeout,
+            algorithm_name,
+            public_key,
+            connection: Mutex::new(Some(connection)),
+        })
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Open a new connection, checking that the daemon still has the same key.
+    fn reconnect(&self) -> io::Result<Connection> {
+        let mut connection = Connection::open(&self.socket_path, self.timeout)?;
+        match connection.key_info()? {
+            Ok((ref algorithm_name, ref public_key))
+                if *algorithm_name == self.algorithm_name && *public_key == self.public_key =>
+            {
+                Ok(connection)
+            }
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The daemon's key has changed",
+            )),
+            Err(msg) => Err(io::Error::other(msg)),
+        }
+    }
+
+    /// Send the request on the open connection, or a new one. If that fails, send it once
+    /// more on a new connection.
+    fn request(&self, message: &[u8]) -> io::Result<SigningDaemonResponse> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| io::Error::other("Connection lock poisoned"))?;
+
+        let mut last_error = None;
+        for _ in 0..2 {
+            let mut current = match connection.take() {
+                Some(current) => current,
+                None => match self.reconnect() {
+                    Ok(current) => current,
+                    Err(err) => {
+                        last_error = Some(err);
+                        continue;
+                    }
+                },
+            };
+
+            match current.request(SigningDaemonRequest_RequestType::SIGN, message) {
+                Ok(response) => {
+                    *connection = Some(current);
+                    return Ok(response);
+                }
+                Err(err) => {
+                    debug!(
+                        "Request to signing daemon at {} failed: {}",
+                        self.socket_path.display(),
+                        err
+                    );
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.expect("An error is set by each failed attempt"))
+    }
+}
+
+impl Signer for DaemonSigner {
+    fn algorithm_name(&self) -> &str {
+        &self.algorithm_name
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut response = self.request(message).map_err(|err| {
+            Error::SigningError(format!(
+                "Unable to sign with the signing daemon at {}: {}",
+                self.socket_path.display(),
+                err
+            ))
+        })?;
+
+        match response.get_status() {
+            SigningDaemonResponse_Status::OK => Ok(response.take_signature()),
+            _ => Err(Error::SigningError(format!(
+                "The signing daemon at {} did not sign: {}",
+                self.socket_path.display(),
+                response.get_error_message()
+            ))),
+        }
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::secp256k1::{self, Secp256k1Signer, Secp256k1Verifier};
+    use crate::signing::SignatureVerifier;
+    use std::env;
+    use std::fs;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+    use std::time::Instant;
+
+    static KEY1_PRIV_HEX: &str = "2f1e7b7a130d7ba9da0068b3bb0ba1d79e7e77110302c9f746c3c2a63fe40088";
+
+    /// How the stand-in daemon answers.
+    #[derive(Clone, Copy)]
+    enum Behavior {
+        Sign,
+        /// Closes each connection after one signature.
+        SignOnce,
+        /// Answers key requests but refuses to sign.
+        Refuse,
+        /// Answers key requests but never sign requests.
+        Hang,
+    }
+
+    /// Start a stand-in signing daemon with the secp256k1 key KEY1, returning its socket
+    /// path.
+    fn start_daemon(name: &str, behavior: Behavior) -> PathBuf {
+        let socket_path = env::temp_dir().join(format!(
+            "transact-daemon-{}-{}.sock",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        thread::spawn(move || {
+            let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                while let Ok(request) = read_message::<_, SigningDaemonRequest>(&mut stream) {
+                    let mut response = SigningDaemonResponse::new();
+                    response.set_correlation_id(request.get_correlation_id());
+                    match (request.get_request_type(), behavior) {
+                        (SigningDaemonRequest_RequestType::KEY_INFO, _) => {
+                            response.set_status(SigningDaemonResponse_Status::OK);
+                            response.set_algorithm_name(signer.algorithm_name().into());
+                            response.set_public_key(signer.public_key().to_vec());
+                        }
+                        (_, Behavior::Refuse) => {
+                            response.set_status(SigningDaemonResponse_Status::ERROR);
+                            response.set_error_message("Not allowed".into());
+                        }
+                        (_, Behavior::Hang) => {
+                            thread::sleep(Duration::from_secs(60));
+                        }
+                        _ => {
+                            response.set_status(SigningDaemonResponse_Status::OK);
+                            response.set_signature(signer.sign(request.get_message()).unwrap());
+                        }
+                    }
+                    if write_message(&mut stream, &response).is_err() {
+                        break;
+                    }
+                    if let (SigningDaemonRequest_RequestType::SIGN, Behavior::SignOnce) =
+                        (request.get_request_type(), behavior)
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+
+        socket_path
+    }
+
+    #[test]
+    fn test_daemon_signer() {
+        let socket_path = start_daemon("sign", Behavior::Sign);
+        let signer = DaemonSigner::connect(&socket_path).unwrap();
+        let local_signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+
+        assert_eq!(secp256k1::ALGORITHM_NAME, signer.algorithm_name());
+        assert_eq!(local_signer.public_key(), signer.public_key());
+
+        let verifier = Secp256k1Verifier::new();
+        for message in &[&b"first"[..], &b"second"[..]] {
+            let signature = signer.sign(message).unwrap();
+            assert_eq!(local_signer.sign(message).unwrap(), signature);
+            assert!(verifier
+                .verify(message, &signature, signer.public_key())
+                .unwrap());
+        }
+
+        fs::remove_file(&socket_path).unwrap();
+    }
+
+    /// A closed connection is reopened.
+    #[test]
+    fn test_daemon_signer_reconnect() {
+        let socket_path = start_daemon("reconnect", Behavior::SignOnce);
+        let signer = DaemonSigner::connect(&socket_path).unwrap();
+
+        assert!(signer.sign(b"first").is_ok());
+        assert!(signer.sign(b"second").is_ok());
+
+        fs::remove_file(&socket_path).unwrap();
+        match signer.sign(b"third") {
+            Err(Error::SigningError(msg)) => {
+                assert!(msg.contains(&socket_path.display().to_string()), "{}", msg)
+            }
+            other => panic!("Expected SigningError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_daemon_signer_refused() {
+        let socket_path = start_daemon("refuse", Behavior::Refuse);
+        let signer = DaemonSigner::connect(&socket_path).unwrap();
+
+        match signer.sign(b"message") {
+            Err(Error::SigningError(msg)) => assert!(msg.contains("Not allowed"), "{}", msg),
+            other => panic!("Expected SigningError, got {:?}", other),
+        }
+
+        fs::remove_file(&socket_path).unwrap();
+    }
+
+    /// A request that is not answered in time is an error, after it is sent once more.
+    #[test]
+    fn test_daemon_signer_timeout() {
+        let socket_path = start_daemon("hang", Behavior::Hang);
+        let signer =
+            DaemonSigner::connect_with_timeout(&socket_path, Duration::from_millis(100)).unwrap();
+
+        let start = Instant::now();
+        match signer.sign(b"message") {
+            Err(Error::SigningError(_)) => (),
+            other => panic!("Expected SigningError, got {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        fs::remove_file(&socket_path).unwrap();
+    }
+
+    #[test]
+    fn test_daemon_signer_no_daemon() {
+        assert!(matches!(
+            DaemonSigner::connect(env::temp_dir().join("transact-no-such-daemon.sock")),
+            Err(Error::SigningError(_))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+use ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey, Signature};
+use std::convert::TryFrom;
+
+use crate::signing::Error;
+use crate::signing::{SignatureVerifier, Signer};
+
+pub const ALGORITHM_NAME: &str = "ed25519";
+
+/// Signs with Ed25519, as specified by RFC 8032, over the message itself.
+///
+/// The private key is the 32 byte seed, the public key the 32 byte encoded point and the
+/// signature 64 bytes.
+pub struct Ed25519Signer {
+    private_key: ExpandedSecretKey,
+    public_key: PublicKey,
+    public_key_bytes: Vec<u8>,
+}
+
+impl Ed25519Signer {
+    /// Create a signer from the 32 byte seed of a private key.
+    pub fn new(private_key: &[u8]) -> Result<Self, Error> {
+        let private_key = SecretKey::from_bytes(private_key)
+            .map_err(|err| Error::KeyError(format!("Invalid private key: {}", err)))?;
+        let public_key = PublicKey::from(&private_key);
+
+        Ok(Ed25519Signer {
+            private_key: ExpandedSecretKey::from(&private_key),
+            public_key_bytes: public_key.to_bytes().to_vec(),
+            public_key,
+        })
+    }
+
+    /// Create a signer from a hex encoded private key seed.
+    pub fn from_hex(private_key: &str) -> Result<Self, Error> {
+        let private_key = hex::decode(private_key.trim())
+            .map_err(|err| Error::KeyError(format!("Invalid private key hex: {}", err)))?;
+        Self::new(&private_key)
+    }
+}
+
+impl Signer for Ed25519Signer {
+    fn algorithm_name(&self) -> &str {
+        ALGORITHM_NAME
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self
+            .private_key
+            .sign(message, &self.public_key)
+            .to_bytes()
+            .to_vec())
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key_bytes
+    }
+}
+
+/// Checks the signatures of an `Ed25519Signer`.
+///
+/// Signatures are checked strictly, rejecting those with a non-canonical S and public
+/// keys of small order, so that each signature has one valid form.
+#[derive(Default)]
+pub struct Ed25519Verifier;
+
+impl Ed25519Verifier {
+    pub fn new() -> Self {
+        Ed25519Verifier
+    }
+}
+
+impl SignatureVerifier for Ed25519Verifier {
+    fn algorithm_name(&self) -> &str {
+        ALGORITHM_NAME
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool, Error> {
+        let signature = Signature::try_from(signature)
+            .map_err(|err| Error::SigningError(format!("Invalid signature: {}", err)))?;
+        let public_key = PublicKey::from_bytes(public_key)
+            .map_err(|err| Error::KeyError(format!("Invalid public key: {}", err)))?;
+
+        Ok(public_key.verify_strict(message, &signature).is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::VerifierFactory;
+    use crate::transaction::{HashMethod, TransactionBuilder};
+
+    /// TEST 1, TEST 2 and TEST 3 of RFC 8032, section 7.1: the private key, public key,
+    /// message and signature.
+    static RFC8032_VECTORS: [(&str, &str, &str, &str); 3] = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555\
+             fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+             085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+        (
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac\
+             18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        ),
+    ];
+
+    #[test]
+    fn test_ed25519_rfc8032_vectors() {
+        let verifier = Ed25519Verifier::new();
+
+        for (private_key, public_key, message, signature) in RFC8032_VECTORS.iter() {
+            let signer = Ed25519Signer::from_hex(private_key).unwrap();
+            let message = hex::decode(message).unwrap();
+
+            assert_eq!(*public_key, hex::encode(signer.public_key()));
+            assert_eq!(*signature, hex::encode(signer.sign(&message).unwrap()));
+            assert!(verifier
+                .verify(
+                    &message,
+                    &hex::decode(signature).unwrap(),
+                    &hex::decode(public_key).unwrap()
+                )
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_ed25519_verifier() {
+        let verifier = Ed25519Verifier::new();
+        let (private_key, _, _, _) = RFC8032_VECTORS[0];
+        let signer = Ed25519Signer::from_hex(private_key).unwrap();
+        let signature = signer.sign(b"message").unwrap();
+
+        assert!(!verifier
+            .verify(b"other message", &signature, signer.public_key())
+            .unwrap());
+        assert!(verifier
+            .verify(b"message", &signature[..63], signer.public_key())
+            .is_err());
+        assert!(verifier
+            .verify(b"message", &signature, &signer.public_key()[..31])
+            .is_err());
+        assert!(Ed25519Signer::new(&[1; 31]).is_err());
+    }
+
+    /// A transaction header is verified with the scheme its signer names.
+    #[test]
+    fn test_ed25519_transaction_signature() {
+        let (private_key, _, _, _) = RFC8032_VECTORS[1];
+        let signer = Ed25519Signer::from_hex(private_key).unwrap();
+
+        let pair = TransactionBuilder::new()
+            .with_dependencies(vec![])
+            .with_family_name("intkey".to_string())
+            .with_family_version("1.0".to_string())
+            .with_inputs(vec![])
+            .with_outputs(vec![])
+            .with_nonce(b"nonce".to_vec())
+            .with_payload_hash_method(HashMethod::SHA512)
+            .with_payload(b"payload".to_vec())
+            .build_pair(&signer)
+            .unwrap();
+
+        let verifier = VerifierFactory::create_verifier(signer.algorithm_name()).unwrap();
+        assert!(pair.verify_signature(verifier.as_ref()).unwrap());
+
+        let verifier = VerifierFactory::create_verifier("secp256k1").unwrap();
+        assert!(
+            pair.verify_signature(verifier.as_ref()).is_err(),
+            "An Ed25519 public key is not a secp256k1 public key"
+        );
+    }
+}
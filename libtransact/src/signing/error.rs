@@ -1,20 +1,33 @@
 use std::error::Error as StdError;
+use std::io;
 
 #[derive(Debug)]
 pub enum Error {
     SigningError(String),
+    /// The key could not be used, such as one of the wrong length or not on the curve.
+    KeyError(String),
+    /// There is no implementation of the algorithm with the name.
+    NoSuchAlgorithm(String),
+    /// Reading or writing a key file failed.
+    IoError(io::Error),
 }
 
 impl StdError for Error {
     fn description(&self) -> &str {
         match *self {
             Error::SigningError(ref msg) => msg,
+            Error::KeyError(ref msg) => msg,
+            Error::NoSuchAlgorithm(ref msg) => msg,
+            Error::IoError(_) => "Reading or writing a key file failed.",
         }
     }
 
     fn cause(&self) -> Option<&StdError> {
         match *self {
             Error::SigningError(_) => None,
+            Error::KeyError(_) => None,
+            Error::NoSuchAlgorithm(_) => None,
+            Error::IoError(ref err) => Some(err),
         }
     }
 }
@@ -23,6 +36,15 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match *self {
             Error::SigningError(ref s) => write!(f, "SigningError: {}", s),
+            Error::KeyError(ref s) => write!(f, "KeyError: {}", s),
+            Error::NoSuchAlgorithm(ref s) => write!(f, "NoSuchAlgorithm: {}", s),
+            Error::IoError(ref err) => write!(f, "IoError: {}", err),
         }
     }
 }
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::IoError(err)
+    }
+}
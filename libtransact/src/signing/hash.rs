@@ -1,7 +1,11 @@
 use sha2::{Digest, Sha512};
 
 use crate::signing::Error;
-use crate::signing::Signer;
+use crate::signing::{SignatureVerifier, Signer};
+
+pub const ALGORITHM_NAME: &str = "hash";
+
+const DUMMY_PUBLIC_KEY: &[u8] = b"hash_signer";
 
 pub struct HashSigner {
     dummy_public_key: Vec<u8>,
@@ -16,12 +20,16 @@ impl HashSigner {
 impl Default for HashSigner {
     fn default() -> Self {
         HashSigner {
-            dummy_public_key: String::from("hash_signer").into_bytes(),
+            dummy_public_key: DUMMY_PUBLIC_KEY.to_vec(),
         }
     }
 }
 
 impl Signer for HashSigner {
+    fn algorithm_name(&self) -> &str {
+        ALGORITHM_NAME
+    }
+
     fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
         let mut hasher = Sha512::new();
         hasher.input(message);
@@ -32,3 +40,50 @@ impl Signer for HashSigner {
         &self.dummy_public_key
     }
 }
+
+/// Checks the signatures of a `HashSigner`, which anyone can make, so is only fit for tests.
+#[derive(Default)]
+pub struct HashVerifier;
+
+impl HashVerifier {
+    pub fn new() -> Self {
+        HashVerifier
+    }
+}
+
+impl SignatureVerifier for HashVerifier {
+    fn algorithm_name(&self) -> &str {
+        ALGORITHM_NAME
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool, Error> {
+        if public_key != DUMMY_PUBLIC_KEY {
+            return Err(Error::KeyError(
+                "The public key of a HashSigner is \"hash_signer\"".to_string(),
+            ));
+        }
+        Ok(HashSigner::new().sign(message)? == signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_verifier() {
+        let signer = HashSigner::new();
+        let verifier = HashVerifier::new();
+
+        let signature = signer.sign(b"message").unwrap();
+        assert!(verifier
+            .verify(b"message", &signature, signer.public_key())
+            .unwrap());
+        assert!(!verifier
+            .verify(b"other message", &signature, signer.public_key())
+            .unwrap());
+        assert!(verifier
+            .verify(b"message", &signature, b"other key")
+            .is_err());
+    }
+}
@@ -1,11 +1,115 @@
+pub mod daemon;
+#[cfg(feature = "ed25519")]
+pub mod ed25519;
 pub mod error;
 pub mod hash;
+pub mod key;
+pub mod multi;
+pub mod parallel;
+pub mod secp256k1;
 
 use std;
 
 pub use crate::signing::error::Error;
+pub use crate::signing::key::{PrivateKey, PublicKey};
 
 pub trait Signer {
+    /// The name of the algorithm, for choosing the `SignatureVerifier` of its signatures
+    /// with `VerifierFactory::create_verifier`. A public key alone does not say which
+    /// algorithm it is for.
+    fn algorithm_name(&self) -> &str;
     fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error>;
     fn public_key(&self) -> &[u8];
 }
+
+/// Checks the signatures made by a `Signer` of the same algorithm.
+pub trait SignatureVerifier {
+    /// The name of the algorithm, as given to `VerifierFactory::create_verifier`.
+    fn algorithm_name(&self) -> &str;
+
+    /// Whether `signature` is the signature of `message` by the private key of
+    /// `public_key`. Returns an error if the signature or public key is malformed.
+    fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool, Error>;
+
+    /// Verify each of the `(message, signature, public_key)` items, returning the result of
+    /// `verify` for each, in order.
+    ///
+    /// An algorithm with batch verification that accepts exactly the signatures `verify`
+    /// does may override this to check the items together. Ed25519 does not: the batch
+    /// equation of `ed25519-dalek` may accept signatures that `verify_strict` rejects, and
+    /// validators must agree on which signatures are valid.
+    fn verify_batch(&self, items: &[VerificationItem]) -> Vec<Result<bool, Error>> {
+        items
+            .iter()
+            .map(|(message, signature, public_key)| self.verify(message, signature, public_key))
+            .collect()
+    }
+}
+
+/// A message, its signature and the public key of its signer, as given to
+/// `SignatureVerifier::verify_batch`.
+pub type VerificationItem<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+/// Creates the `Signer` for a private key.
+pub struct SignerFactory;
+
+impl SignerFactory {
+    /// Create a signer of the key's algorithm that signs with the key.
+    pub fn create_signer(private_key: &PrivateKey) -> Result<Box<dyn Signer>, Error> {
+        match private_key.algorithm_name() {
+            secp256k1::ALGORITHM_NAME => Ok(Box::new(secp256k1::Secp256k1Signer::new(
+                private_key.as_slice(),
+            )?)),
+            #[cfg(feature = "ed25519")]
+            ed25519::ALGORITHM_NAME => Ok(Box::new(ed25519::Ed25519Signer::new(
+                private_key.as_slice(),
+            )?)),
+            _ => Err(Error::NoSuchAlgorithm(format!(
+                "No signer for algorithm {}",
+                private_key.algorithm_name()
+            ))),
+        }
+    }
+}
+
+/// Creates the `SignatureVerifier` for a key's algorithm.
+pub struct VerifierFactory;
+
+impl VerifierFactory {
+    /// Create the verifier for the algorithm with the name, such as `"secp256k1"`.
+    pub fn create_verifier(algorithm_name: &str) -> Result<Box<dyn SignatureVerifier>, Error> {
+        match algorithm_name {
+            secp256k1::ALGORITHM_NAME => Ok(Box::new(secp256k1::Secp256k1Verifier::new())),
+            hash::ALGORITHM_NAME => Ok(Box::new(hash::HashVerifier::new())),
+            #[cfg(feature = "ed25519")]
+            ed25519::ALGORITHM_NAME => Ok(Box::new(ed25519::Ed25519Verifier::new())),
+            _ => Err(Error::NoSuchAlgorithm(format!(
+                "No verifier for algorithm {}",
+                algorithm_name
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verifier_factory() {
+        let mut algorithm_names = vec![secp256k1::ALGORITHM_NAME, hash::ALGORITHM_NAME];
+        if cfg!(feature = "ed25519") {
+            algorithm_names.push("ed25519");
+        }
+
+        for algorithm_name in &algorithm_names {
+            let verifier = VerifierFactory::create_verifier(algorithm_name).unwrap();
+            assert_eq!(*algorithm_name, verifier.algorithm_name());
+        }
+
+        assert!(matches!(
+            VerifierFactory::create_verifier("rsa"),
+            Err(Error::NoSuchAlgorithm(_))
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+//! Batches signed by several keys, and policies of how many of a set of keys must sign.
+//!
+//! A `MultiSigner` builds a batch whose header is signed by its first signer, as for any
+//! batch, and by each of its other signers. The other signatures travel with the batch in a
+//! `MultiSignatureBatch`, which is only sent to peers that support the
+//! `MULTI_SIGNATURE_EXTENSION`; other peers are sent the batch alone, which is a plain
+//! Sawtooth batch.
+
+use protobuf::Message;
+
+use crate::batch::{Batch, BatchBuildError, BatchBuilder, BatchHeader};
+use crate::protos;
+use crate::protos::{FromNative, FromProto, IntoNative, IntoProto, ProtoConversionError};
+use crate::signing::{Error, SignatureVerifier, Signer};
+
+/// The name of the extension, for peers to say they accept `MultiSignatureBatch`es.
+pub const MULTI_SIGNATURE_EXTENSION: &str = "batch-multi-signature/1";
+
+/// Whether a peer supports multi-signature batches, given the extensions it supports.
+pub fn supports_multi_signature(peer_extensions: &[String]) -> bool {
+    peer_extensions
+        .iter()
+        .any(|extension| extension == MULTI_SIGNATURE_EXTENSION)
+}
+
+/// A signature of a batch header by a key other than the header's signer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchSignature {
+    signer_public_key: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl BatchSignature {
+    pub fn new(signer_public_key: Vec<u8>, signature: Vec<u8>) -> Self {
+        BatchSignature {
+            signer_public_key,
+            signature,
+        }
+    }
+
+    pub fn signer_public_key(&self) -> &[u8] {
+        &self.signer_public_key
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+impl FromProto<protos::multi_signature::BatchSignature> for BatchSignature {
+    fn from_proto(
+        signature: protos::multi_signature::BatchSignature,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(BatchSignature {
+            signer_public_key: hex::decode(signature.get_signer_public_key())?,
+            signature: hex::decode(signature.get_signature())?,
+        })
+    }
+}
+
+impl FromNative<BatchSignature> for protos::multi_signature::BatchSignature {
+    fn from_native(signature: BatchSignature) -> Result<Self, ProtoConversionError> {
+        let mut proto_signature = protos::multi_signature::BatchSignature::new();
+        proto_signature.set_signer_public_key(hex::encode(signature.signer_public_key));
+        proto_signature.set_signature(hex::encode(signature.signature));
+        Ok(proto_signature)
+    }
+}
+
+impl IntoProto<protos::multi_signature::BatchSignature> for BatchSignature {}
+impl IntoNative<BatchSignature> for protos::multi_signature::BatchSignature {}
+
+/// A batch with the signatures of its header by keys other than its signer.
+pub struct MultiSignatureBatch {
+    batch: Batch,
+    signatures: Vec<BatchSignature>,
+}
+
+impl MultiSignatureBatch {
+    pub fn new(batch: Batch, signatures: Vec<BatchSignature>) -> Self {
+        MultiSignatureBatch { batch, signatures }
+    }
+
+    pub fn batch(&self) -> &Batch {
+        &self.batch
+    }
+
+    /// The signatures by keys other than the batch header's signer.
+    pub fn signatures(&self) -> &[BatchSignature] {
+        &self.signatures
+    }
+
+    pub fn take(self) -> (Batch, Vec<BatchSignature>) {
+        (self.batch, self.signatures)
+    }
+
+    /// Serialize the batch for a peer. If the peer supports the multi-signature extension
+    /// this is a `MultiSignatureBatch` message; otherwise it is the batch alone, as a
+    /// Sawtooth `Batch` message, without the other signatures.
+    pub fn to_bytes(
+        &self,
+        multi_signature_negotiated: bool,
+    ) -> Result<Vec<u8>, ProtoConversionError> {
+        if multi_signature_negotiated {
+            let mut proto = protos::multi_signature::MultiSignatureBatch::new();
+            proto.set_batch(self.batch.clone().into_proto()?);
+            proto.set_signatures(
+                self.signatures
+                    .iter()
+                    .cloned()
+                    .map(IntoProto::into_proto)
+                    .collect::<Result<_, _>>()?,
+            );
+            Ok(proto.write_to_bytes()?)
+        } else {
+            self.batch.to_bytes()
+        }
+    }
+
+    /// Deserialize a batch from a peer, which is a `MultiSignatureBatch` message if the
+    /// multi-signature extension was negotiated with the peer, or a Sawtooth `Batch` message
+    /// without other signatures if not.
+    pub fn from_bytes(
+        bytes: &[u8],
+        multi_signature_negotiated: bool,
+    ) -> Result<Self, ProtoConversionError> {
+        if multi_signature_negotiated {
+            protos::multi_signature::MultiSignatureBatch::parse_from_bytes(bytes)?.into_native()
+        } else {
+            Ok(MultiSignatureBatch::new(Batch::from_bytes(bytes)?, vec![]))
+        }
+    }
+}
+
+impl FromProto<protos::multi_signature::MultiSignatureBatch> for MultiSignatureBatch {
+    fn from_proto(
+        mut batch: protos::multi_signature::MultiSignatureBatch,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(MultiSignatureBatch {
+            signatures: batch
+                .take_signatures()
+                .into_iter()
+                .map(IntoNative::into_native)
+                .collect::<Result<_, _>>()?,
+            batch: batch.take_batch().into_native()?,
+        })
+    }
+}
+
+impl IntoNative<MultiSignatureBatch> for protos::multi_signature::MultiSignatureBatch {}
+
+/// Signs batches with several keys.
+pub struct MultiSigner {
+    signers: Vec<Box<dyn Signer>>,
+}
+
+impl MultiSigner {
+    /// Create a multi-signer of the signers. The first is the signer of the batch headers.
+    pub fn new(signers: Vec<Box<dyn Signer>>) -> Result<Self, Error> {
+        if signers.is_empty() {
+            return Err(Error::KeyError(
+                "A MultiSigner needs at least one signer".into(),
+            ));
+        }
+        Ok(MultiSigner { signers })
+    }
+
+    /// The public keys of the signers, the batch headers' signer first.
+    pub fn public_keys(&self) -> Vec<&[u8]> {
+        self.signers
+            .iter()
+            .map(|signer| signer.public_key())
+            .collect()
+    }
+
+    /// Build the batch, signed by the first signer, with the signatures of its header by
+    /// each of the others.
+    pub fn build_batch(
+        &self,
+        builder: BatchBuilder,
+    ) -> Result<MultiSignatureBatch, BatchBuildError> {
+        let (batch, _) = builder.build_pair(self.signers[0].as_ref())?.take();
+        let signatures = self.signers[1..]
+            .iter()
+            .map(|signer| {
+                signer
+                    .sign(batch.header())
+                    .map(|signature| BatchSignature::new(signer.public_key().to_vec(), signature))
+                    .map_err(|err| BatchBuildError::SigningError(err.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(MultiSignatureBatch::new(batch, signatures))
+    }
+}
+
+/// Requires that a batch be signed by at least `threshold` of a set of keys.
+///
+/// The batch header's signer counts as one of the keys, if it is one.
+#[derive(Debug, Clone)]
+pub struct ThresholdPolicy {
+    threshold: usize,
+    public_keys: Vec<Vec<u8>>,
+}
+
+impl ThresholdPolicy {
+    /// Create a policy of `threshold` of the public keys. The threshold must be at least one
+    /// and at most the number of distinct keys.
+    pub fn new(threshold: usize, mut public_keys: Vec<Vec<u8>>) -> Result<Self, Error> {
+        public_keys.sort();
+        public_keys.dedup();
+        if threshold == 0 || threshold > public_keys.len() {
+            return Err(Error::KeyError(format!(
+                "A threshold of {} of {} keys can not be met",
+                threshold,
+                public_keys.len()
+            )));
+        }
+
+        Ok(ThresholdPolicy {
+            threshold,
+            public_keys,
+        })
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn public_keys(&self) -> &[Vec<u8>] {
+        &self.public_keys
+    }
+
+    /// The keys of the policy that signed the batch's header. A signature that does not
+    /// verify, or is malformed, is not counted.
+    pub fn signers(
+        &self,
+        batch: &MultiSignatureBatch,
+        verifier: &dyn SignatureVerifier,
+    ) -> Result<Vec<&[u8]>, Error> {
+        let header: BatchHeader =
+            protos::batch::BatchHeader::parse_from_bytes(batch.batch.header())
+                .map_err(|err| Error::SigningError(format!("Invalid batch header: {}", err)))?
+                .into_native()
+                .map_err(|err| Error::SigningError(format!("Invalid batch header: {}", err)))?;
+        let header_signature = hex::decode(batch.batch.header_signature())
+            .map_err(|err| Error::SigningError(format!("Invalid header signature: {}", err)))?;
+
+        let signatures = std::iter::once((header.signer_public_key(), &header_signature[..]))
+            .chain(
+                batch
+                    .signatures
+                    .iter()
+                    .map(|signature| (signature.signer_public_key(), signature.signature())),
+            );
+
+        let mut signers = vec![];
+        for (public_key, signature) in signatures {
+            let public_key = match self.public_keys.iter().find(|key| *key == public_key) {
+                Some(public_key) => public_key,
+                None => continue,
+            };
+            if signers.contains(&&public_key[..]) {
+                continue;
+            }
+            match verifier.verify(batch.batch.header(), signature, public_key) {
+                Ok(true) => signers.push(&public_key[..]),
+                Ok(false) => (),
+                Err(err) => debug!(
+                    "Not counting malformed signature by {}: {}",
+                    hex::encode(public_key),
+                    err
+                ),
+            }
+        }
+
+        Ok(signers)
+    }
+
+    /// Whether at least `threshold` of the policy's keys signed the batch's header.
+    pub fn is_satisfied_by(
+        &self,
+        batch: &MultiSignatureBatch,
+        verifier: &dyn SignatureVerifier,
+    ) -> Result<bool, Error> {
+        Ok(self.signers(batch, verifier)?.len() >= self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::secp256k1::{Secp256k1Signer, Secp256k1Verifier};
+
+    static KEY1_PRIV_HEX: &str = "2f1e7b7a130d7ba9da0068b3bb0ba1d79e7e77110302c9f746c3c2a63fe40088";
+    static KEY2_PRIV_HEX: &str = "51b845c2cdde22fe646148f0b51eaf5feec8c82ee921d5e0cbe7619f3bb9c62d";
+    static KEY3_PRIV_HEX: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+    static KEY4_PRIV_HEX: &str = "0202020202020202020202020202020202020202020202020202020202020202";
+
+    fn signer(private_key: &str) -> Box<dyn Signer> {
+        Box::new(Secp256k1Signer::from_hex(private_key).unwrap())
+    }
+
+    fn public_key(private_key: &str) -> Vec<u8> {
+        signer(private_key).public_key().to_vec()
+    }
+
+    fn multi_signed_batch(private_keys: &[&str]) -> MultiSignatureBatch {
+        MultiSigner::new(private_keys.iter().map(|key| signer(key)).collect())
+            .unwrap()
+            .build_batch(BatchBuilder::new().with_transactions(vec![]))
+            .unwrap()
+    }
+
+    fn policy(threshold: usize) -> ThresholdPolicy {
+        ThresholdPolicy::new(
+            threshold,
+            vec![
+                public_key(KEY1_PRIV_HEX),
+                public_key(KEY2_PRIV_HEX),
+                public_key(KEY3_PRIV_HEX),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_multi_signer() {
+        assert!(MultiSigner::new(vec![]).is_err());
+
+        let batch = multi_signed_batch(&[KEY1_PRIV_HEX, KEY2_PRIV_HEX, KEY3_PRIV_HEX]);
+        assert_eq!(
+            vec![public_key(KEY2_PRIV_HEX), public_key(KEY3_PRIV_HEX)],
+            batch
+                .signatures()
+                .iter()
+                .map(|signature| signature.signer_public_key().to_vec())
+                .collect::<Vec<_>>()
+        );
+
+        let verifier = Secp256k1Verifier::new();
+        for signature in batch.signatures() {
+            assert!(verifier
+                .verify(
+                    batch.batch().header(),
+                    signature.signature(),
+                    signature.signer_public_key()
+                )
+                .unwrap());
+        }
+    }
+
+    /// The header's signer and the other signers count towards the threshold, but only
+    /// once each, and only if they are keys of the policy.
+    #[test]
+    fn test_threshold_policy() {
+        let verifier = Secp256k1Verifier::new();
+
+        assert!(ThresholdPolicy::new(0, vec![public_key(KEY1_PRIV_HEX)]).is_err());
+        assert!(ThresholdPolicy::new(
+            2,
+            vec![public_key(KEY1_PRIV_HEX), public_key(KEY1_PRIV_HEX)]
+        )
+        .is_err());
+
+        let batch = multi_signed_batch(&[KEY1_PRIV_HEX, KEY2_PRIV_HEX]);
+        assert!(policy(2).is_satisfied_by(&batch, &verifier).unwrap());
+        assert!(!policy(3).is_satisfied_by(&batch, &verifier).unwrap());
+
+        let batch = multi_signed_batch(&[KEY4_PRIV_HEX, KEY3_PRIV_HEX, KEY3_PRIV_HEX]);
+        assert_eq!(
+            vec![&public_key(KEY3_PRIV_HEX)[..]],
+            policy(1).signers(&batch, &verifier).unwrap()
+        );
+        assert!(!policy(2).is_satisfied_by(&batch, &verifier).unwrap());
+
+        // A signature of another header does not count.
+        let (batch, mut signatures) = multi_signed_batch(&[KEY1_PRIV_HEX, KEY2_PRIV_HEX]).take();
+        let (_, other_signatures) = multi_signed_batch(&[KEY4_PRIV_HEX, KEY3_PRIV_HEX]).take();
+        signatures.extend(other_signatures);
+        signatures.push(BatchSignature::new(
+            public_key(KEY3_PRIV_HEX),
+            vec![1, 2, 3],
+        ));
+        let batch = MultiSignatureBatch::new(batch, signatures);
+        assert!(policy(2).is_satisfied_by(&batch, &verifier).unwrap());
+        assert!(!policy(3).is_satisfied_by(&batch, &verifier).unwrap());
+    }
+
+    /// Peers that have not negotiated the extension are sent, and send, plain Sawtooth
+    /// batches.
+    #[test]
+    fn test_multi_signature_batch_bytes() {
+        let batch = multi_signed_batch(&[KEY1_PRIV_HEX, KEY2_PRIV_HEX]);
+
+        assert!(supports_multi_signature(&[
+            "other".to_string(),
+            MULTI_SIGNATURE_EXTENSION.to_string()
+        ]));
+        assert!(!supports_multi_signature(&["other".to_string()]));
+
+        let bytes = batch.to_bytes(true).unwrap();
+        let read = MultiSignatureBatch::from_bytes(&bytes, true).unwrap();
+        assert_eq!(batch.signatures(), read.signatures());
+        assert_eq!(batch.batch().header(), read.batch().header());
+        assert_eq!(
+            batch.batch().header_signature(),
+            read.batch().header_signature()
+        );
+
+        let bytes = batch.to_bytes(false).unwrap();
+        let sawtooth_batch =
+            sawtooth_sdk::messages::batch::Batch::parse_from_bytes(&bytes).unwrap();
+        assert_eq!(batch.batch().header(), sawtooth_batch.get_header());
+        assert_eq!(
+            batch.batch().header_signature(),
+            sawtooth_batch.get_header_signature()
+        );
+
+        let read = MultiSignatureBatch::from_bytes(&bytes, false).unwrap();
+        assert!(read.signatures().is_empty());
+        assert_eq!(batch.batch().header(), read.batch().header());
+    }
+}
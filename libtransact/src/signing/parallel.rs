@@ -0,0 +1,514 @@
+//! Verification of the batch and transaction header signatures of many batches at once, such
+//! as those of a block, spread over several threads.
+
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use protobuf::Message;
+
+use crate::batch::BatchPair;
+use crate::protos;
+use crate::protos::IntoNative;
+use crate::signing::{Error, SignatureVerifier, VerificationItem, VerifierFactory};
+use crate::transaction::TransactionHeader;
+
+/// The number of signatures a thread is given at a time, and gives to
+/// `SignatureVerifier::verify_batch` together.
+const CHUNK_SIZE: usize = 64;
+
+/// A header signature that was checked.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureId {
+    /// The header signature of a batch.
+    Batch(String),
+    /// The header signature of a transaction, with that of its batch.
+    Transaction {
+        batch_id: String,
+        transaction_id: String,
+    },
+}
+
+#[derive(Debug)]
+pub enum FailureReason {
+    /// The signature is not that of the header by the header's signer.
+    InvalidSignature,
+    /// The header could not be read, so it has no signer to check the signature against.
+    InvalidHeader(String),
+    /// The signature or the signer's public key is malformed.
+    SigningError(Error),
+}
+
+/// A header signature that did not verify, and why.
+#[derive(Debug)]
+pub struct SignatureFailure {
+    signature: SignatureId,
+    reason: FailureReason,
+}
+
+impl SignatureFailure {
+    pub fn signature(&self) -> &SignatureId {
+        &self.signature
+    }
+
+    pub fn reason(&self) -> &FailureReason {
+        &self.reason
+    }
+}
+
+/// A signature to check, owned so that it can be given to a worker.
+struct Job {
+    header: Vec<u8>,
+    header_signature: String,
+    /// The signer of a batch header. That of a transaction header is read from the header.
+    public_key: Option<Vec<u8>>,
+}
+
+impl Job {
+    /// The decoded signature and the signer's public key.
+    fn signature_and_public_key(&self) -> Result<(Vec<u8>, Vec<u8>), FailureReason> {
+        let public_key = match self.public_key {
+            Some(ref public_key) => public_key.clone(),
+            None => {
+                let header: TransactionHeader =
+                    protos::transaction::TransactionHeader::parse_from_bytes(&self.header)
+                        .map_err(|err| FailureReason::InvalidHeader(err.to_string()))?
+                        .into_native()
+                        .map_err(|err| FailureReason::InvalidHeader(err.to_string()))?;
+                header.signer_public_key().to_vec()
+            }
+        };
+
+        let signature = hex::decode(&self.header_signature).map_err(|err| {
+            FailureReason::SigningError(Error::SigningError(format!(
+                "Invalid header signature: {}",
+                err
+            )))
+        })?;
+
+        Ok((signature, public_key))
+    }
+}
+
+/// A chunk of the jobs of a call to `ParallelVerifier::verify`, by its index, with where to
+/// send its results.
+struct Task {
+    index: usize,
+    jobs: Vec<Job>,
+    results: Sender<(usize, Vec<Option<FailureReason>>)>,
+}
+
+/// Checks the header signatures of every batch and transaction of a list of batches, on a
+/// pool of threads.
+///
+/// Each thread keeps its own `SignatureVerifier`, as creating one may be costly. The threads
+/// are stopped when the `ParallelVerifier` is dropped.
+pub struct ParallelVerifier {
+    sender: Option<Sender<Task>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ParallelVerifier {
+    /// Start `num_threads` threads that verify signatures of the algorithm, such as
+    /// `"secp256k1"`.
+    pub fn new(algorithm_name: &str, num_threads: usize) -> Result<Self, Error> {
+        VerifierFactory::create_verifier(algorithm_name)?;
+
+        let (sender, receiver) = channel::<Task>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..num_threads.max(1))
+            .map(|i| {
+                let receiver = receiver.clone();
+                let algorithm_name = algorithm_name.to_string();
+                thread::Builder::new()
+                    .name(format!("signature_verifier_{}", i))
+                    .spawn(move || {
+                        let verifier = VerifierFactory::create_verifier(&algorithm_name)
+                            .expect("The algorithm was checked by ParallelVerifier::new");
+                        loop {
+                            let task = match receiver.lock() {
+                                Ok(receiver) => receiver.recv(),
+                                Err(_) => return,
+                            };
+                            let task = match task {
+                                Ok(task) => task,
+                                Err(_) => return,
+                            };
+                            let results = verify_chunk(verifier.as_ref(), &task.jobs);
+                            // The caller is gone only if it panicked, so the results are not
+                            // wanted.
+                            let _ = task.results.send((task.index, results));
+                        }
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ParallelVerifier {
+            sender: Some(sender),
+            workers,
+        })
+    }
+
+    /// Verify every header signature of the batches. The result of each batch is in the
+    /// order of `batches`, and is an error naming each of its signatures that did not verify.
+    pub fn verify(&self, batches: &[BatchPair]) -> Vec<Result<(), Vec<SignatureFailure>>> {
+        let mut jobs = vec![];
+        let mut signatures = vec![];
+        for (index, pair) in batches.iter().enumerate() {
+            let batch_id = pair.batch().header_signature();
+            jobs.push(Job {
+                header: pair.batch().header().to_vec(),
+                header_signature: batch_id.to_string(),
+                public_key: Some(pair.header().signer_public_key().to_vec()),
+            });
+            signatures.push((index, SignatureId::Batch(batch_id.to_string())));
+
+            for transaction in pair.batch().transactions() {
+                jobs.push(Job {
+                    header: transaction.header().to_vec(),
+                    header_signature: transaction.header_signature().to_string(),
+                    public_key: None,
+                });
+                signatures.push((
+                    index,
+                    SignatureId::Transaction {
+                        batch_id: batch_id.to_string(),
+                        transaction_id: transaction.header_signature().to_string(),
+                    },
+                ));
+            }
+        }
+
+        let sender = self
+            .sender
+            .as_ref()
+            .expect("The sender is only taken when dropped");
+        let (results_sender, results_receiver) = channel();
+        let mut chunk_sizes = vec![];
+        let mut jobs = jobs.into_iter().peekable();
+        while jobs.peek().is_some() {
+            let chunk: Vec<Job> = jobs.by_ref().take(CHUNK_SIZE).collect();
+            chunk_sizes.push(chunk.len());
+            let task = Task {
+                index: chunk_sizes.len() - 1,
+                jobs: chunk,
+                results: results_sender.clone(),
+            };
+            if sender.send(task).is_err() {
+                error!("The signature verification threads have stopped");
+            }
+        }
+        drop(results_sender);
+
+        let reasons = chunk_reasons(&chunk_sizes, results_receiver.iter());
+
+        let mut batch_failures: Vec<Vec<SignatureFailure>> =
+            batches.iter().map(|_| vec![]).collect();
+        for ((batch_index, signature), reason) in signatures.into_iter().zip(reasons) {
+            if let Some(reason) = reason {
+                batch_failures[batch_index].push(SignatureFailure { signature, reason });
+            }
+        }
+
+        batch_failures
+            .into_iter()
+            .map(|failures| {
+                if failures.is_empty() {
+                    Ok(())
+                } else {
+                    Err(failures)
+                }
+            })
+            .collect()
+    }
+}
+
+impl Drop for ParallelVerifier {
+    fn drop(&mut self) {
+        // Closing the channel stops the threads once they have finished their tasks.
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                error!("A signature verification thread panicked");
+            }
+        }
+    }
+}
+
+/// The failure reasons of the chunks' jobs, in order, from the results received for the
+/// chunks of the sizes. A job of a chunk that has no results, because its thread stopped,
+/// or results for the wrong number of jobs, has failed: it was not verified.
+fn chunk_reasons<I>(chunk_sizes: &[usize], received: I) -> Vec<Option<FailureReason>>
+where
+    I: Iterator<Item = (usize, Vec<Option<FailureReason>>)>,
+{
+    let mut chunk_results: Vec<Option<Vec<Option<FailureReason>>>> =
+        chunk_sizes.iter().map(|_| None).collect();
+    for (index, results) in received {
+        if let Some(chunk) = chunk_results.get_mut(index) {
+            *chunk = Some(results);
+        }
+    }
+
+    chunk_results
+        .into_iter()
+        .zip(chunk_sizes)
+        .flat_map(|(results, &size)| match results {
+            Some(results) if results.len() == size => results,
+            _ => {
+                error!("The signatures of a chunk of {} were not verified", size);
+                (0..size).map(|_| Some(not_verified())).collect()
+            }
+        })
+        .collect()
+}
+
+/// The reason of a signature that no verifier gave a result for.
+fn not_verified() -> FailureReason {
+    FailureReason::SigningError(Error::SigningError("The signature was not verified".into()))
+}
+
+/// Verify the signatures of the jobs together, returning why each that failed did.
+fn verify_chunk(verifier: &dyn SignatureVerifier, jobs: &[Job]) -> Vec<Option<FailureReason>> {
+    let prepared: Vec<_> = jobs.iter().map(Job::signature_and_public_key).collect();
+    let items: Vec<VerificationItem> =
+        jobs.iter()
+            .zip(&prepared)
+            .filter_map(|(job, prepared)| {
+                prepared.as_ref().ok().map(|(signature, public_key)| {
+                    (&job.header[..], &signature[..], &public_key[..])
+                })
+            })
+            .collect();
+    let verified = verifier.verify_batch(&items);
+    // Results that do not match the items one to one cannot be told apart, so none count.
+    let mut verified = if verified.len() == items.len() {
+        Some(verified.into_iter())
+    } else {
+        error!(
+            "The verifier returned {} results for {} signatures",
+            verified.len(),
+            items.len()
+        );
+        None
+    };
+
+    prepared
+        .into_iter()
+        .map(|prepared| match prepared {
+            Err(reason) => Some(reason),
+            Ok(_) => match verified.as_mut().and_then(Iterator::next) {
+                Some(Ok(true)) => None,
+                Some(Ok(false)) => Some(FailureReason::InvalidSignature),
+                Some(Err(err)) => Some(FailureReason::SigningError(err)),
+                None => Some(not_verified()),
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::BatchBuilder;
+    use crate::signing::hash::HashSigner;
+    use crate::signing::secp256k1::{self, Secp256k1Signer};
+    use crate::signing::Signer;
+    use crate::transaction::Transaction;
+    use sha2::{Digest, Sha512};
+
+    static KEY1_PRIV_HEX: &str = "2f1e7b7a130d7ba9da0068b3bb0ba1d79e7e77110302c9f746c3c2a63fe40088";
+
+    /// A transaction signed by the signer, with a header as a Sawtooth client would make.
+    pub(super) fn transaction(signer: &dyn Signer, nonce: &str) -> Transaction {
+        let mut header = protos::transaction::TransactionHeader::new();
+        header.set_batcher_public_key(hex::encode(signer.public_key()));
+        header.set_family_name("intkey".to_string());
+        header.set_family_version("1.0".to_string());
+        header.set_nonce(nonce.to_string());
+        header.set_payload_sha512(hex::encode(Sha512::digest(b"payload")));
+        header.set_signer_public_key(hex::encode(signer.public_key()));
+        let header = header.write_to_bytes().unwrap();
+
+        let signature = hex::encode(signer.sign(&header).unwrap());
+        Transaction::new(header, signature, b"payload".to_vec())
+    }
+
+    pub(super) fn batch(signer: &dyn Signer, transactions: Vec<Transaction>) -> BatchPair {
+        BatchBuilder::new()
+            .with_transactions(transactions)
+            .build_pair(signer)
+            .unwrap()
+    }
+
+    pub(super) fn batches(
+        signer: &dyn Signer,
+        count: usize,
+        transactions: usize,
+    ) -> Vec<BatchPair> {
+        (0..count)
+            .map(|i| {
+                batch(
+                    signer,
+                    (0..transactions)
+                        .map(|j| transaction(signer, &format!("{}-{}", i, j)))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_verifier_valid() {
+        let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+        let batches = batches(&signer, 20, 10);
+
+        for num_threads in &[1, 4] {
+            let verifier = ParallelVerifier::new(secp256k1::ALGORITHM_NAME, *num_threads).unwrap();
+            let results = verifier.verify(&batches);
+            assert_eq!(20, results.len());
+            assert!(results.iter().all(Result::is_ok));
+        }
+
+        let verifier = ParallelVerifier::new(secp256k1::ALGORITHM_NAME, 4).unwrap();
+        assert!(verifier.verify(&[]).is_empty());
+        assert!(ParallelVerifier::new("rsa", 4).is_err());
+    }
+
+    /// Each signature that does not verify is named, in the result of its batch.
+    #[test]
+    fn test_chunk_reasons_missing_results() {
+        // The second chunk's thread stopped, and the third returned too few results.
+        let received = vec![
+            (2, vec![None]),
+            (0, vec![None, Some(FailureReason::InvalidSignature)]),
+        ];
+        let reasons = chunk_reasons(&[2, 3, 2], received.into_iter());
+
+        assert_eq!(7, reasons.len());
+        assert!(reasons[0].is_none());
+        assert!(matches!(reasons[1], Some(FailureReason::InvalidSignature)));
+        for reason in &reasons[2..] {
+            assert!(matches!(reason, Some(FailureReason::SigningError(_))));
+        }
+    }
+
+    /// Verifies everything, but returns a result for the first item only.
+    struct ShortVerifier;
+
+    impl SignatureVerifier for ShortVerifier {
+        fn algorithm_name(&self) -> &str {
+            "short"
+        }
+
+        fn verify(&self, _: &[u8], _: &[u8], _: &[u8]) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        fn verify_batch(&self, items: &[VerificationItem]) -> Vec<Result<bool, Error>> {
+            items.iter().take(1).map(|_| Ok(true)).collect()
+        }
+    }
+
+    /// A verifier that returns too few results fails the chunk instead of its thread.
+    #[test]
+    fn test_verify_chunk_short_results() {
+        let jobs: Vec<Job> = (0..3)
+            .map(|i| Job {
+                header: vec![i],
+                header_signature: "00".into(),
+                public_key: Some(vec![1]),
+            })
+            .collect();
+
+        let reasons = verify_chunk(&ShortVerifier, &jobs);
+        assert_eq!(3, reasons.len());
+        for reason in &reasons {
+            assert!(matches!(reason, Some(FailureReason::SigningError(_))));
+        }
+    }
+
+    #[test]
+    fn test_parallel_verifier_failures() {
+        let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+
+        // A transaction whose header signature is of another header.
+        let valid = transaction(&signer, "valid");
+        let forged = Transaction::new(
+            transaction(&signer, "forged").header().to_vec(),
+            valid.header_signature().to_string(),
+            b"payload".to_vec(),
+        );
+        let forged_id = forged.header_signature().to_string();
+        let malformed = Transaction::new(b"not a header".to_vec(), "00".into(), vec![]);
+
+        let mut batches = batches(&signer, 3, 2);
+        batches.insert(1, batch(&signer, vec![forged, malformed]));
+        // A batch signed with another scheme than the verifier's.
+        batches.push(batch(&HashSigner::new(), vec![]));
+
+        let verifier = ParallelVerifier::new(secp256k1::ALGORITHM_NAME, 4).unwrap();
+        let results = verifier.verify(&batches);
+        assert_eq!(5, results.len());
+        assert!(results[0].is_ok());
+        assert!(results[2].is_ok());
+        assert!(results[3].is_ok());
+
+        let batch_id = batches[1].batch().header_signature().to_string();
+        let failures = results[1].as_ref().unwrap_err();
+        assert_eq!(2, failures.len());
+        assert_eq!(
+            &SignatureId::Transaction {
+                batch_id,
+                transaction_id: forged_id,
+            },
+            failures[0].signature()
+        );
+        assert!(matches!(
+            failures[0].reason(),
+            FailureReason::InvalidSignature
+        ));
+        assert!(matches!(
+            failures[1].reason(),
+            FailureReason::InvalidHeader(_)
+        ));
+
+        let failures = results[4].as_ref().unwrap_err();
+        assert_eq!(1, failures.len());
+        assert_eq!(
+            &SignatureId::Batch(batches[4].batch().header_signature().to_string()),
+            failures[0].signature()
+        );
+        assert!(matches!(
+            failures[0].reason(),
+            FailureReason::SigningError(_)
+        ));
+    }
+}
+
+#[cfg(all(feature = "nightly", test))]
+mod benchmarks {
+    extern crate test;
+    use super::tests::batches;
+    use super::*;
+    use crate::signing::secp256k1::{self, Secp256k1Signer};
+    use test::Bencher;
+
+    static KEY1_PRIV_HEX: &str = "2f1e7b7a130d7ba9da0068b3bb0ba1d79e7e77110302c9f746c3c2a63fe40088";
+
+    #[bench]
+    fn bench_verify_1_thread(b: &mut Bencher) {
+        let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+        let batches = batches(&signer, 10, 50);
+        let verifier = ParallelVerifier::new(secp256k1::ALGORITHM_NAME, 1).unwrap();
+        b.iter(|| verifier.verify(&batches));
+    }
+
+    #[bench]
+    fn bench_verify_4_threads(b: &mut Bencher) {
+        let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+        let batches = batches(&signer, 10, 50);
+        let verifier = ParallelVerifier::new(secp256k1::ALGORITHM_NAME, 4).unwrap();
+        b.iter(|| verifier.verify(&batches));
+    }
+}
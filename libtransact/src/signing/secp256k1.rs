@@ -0,0 +1,275 @@
+use ::secp256k1::{
+    constants, All, Message, PublicKey, Secp256k1, SecretKey, Signature, VerifyOnly,
+};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::signing::Error;
+use crate::signing::{SignatureVerifier, Signer};
+
+pub const ALGORITHM_NAME: &str = "secp256k1";
+
+/// The message signed for `message`: its SHA-256 digest.
+fn digest(message: &[u8]) -> Result<Message, Error> {
+    let mut hasher = Sha256::new();
+    hasher.input(message);
+    Message::from_slice(&hasher.result()).map_err(|err| Error::SigningError(err.to_string()))
+}
+
+/// Signs with secp256k1 ECDSA over the SHA-256 digest of the message, as Sawtooth does.
+///
+/// Signatures are the 64 byte compact serialization of r and s, and the public key is the
+/// 33 byte compressed serialization, so that `hex::encode` of each is what a Sawtooth
+/// validator expects in a header.
+pub struct Secp256k1Signer {
+    context: Secp256k1<All>,
+    private_key: SecretKey,
+    public_key: Vec<u8>,
+}
+
+impl Secp256k1Signer {
+    /// Create a signer from the 32 bytes of a private key.
+    pub fn new(private_key: &[u8]) -> Result<Self, Error> {
+        let context = Secp256k1::new();
+        let private_key = SecretKey::from_slice(private_key)
+            .map_err(|err| Error::KeyError(format!("Invalid private key: {}", err)))?;
+        let public_key = PublicKey::from_secret_key(&context, &private_key)
+            .serialize()
+            .to_vec();
+
+        Ok(Secp256k1Signer {
+            context,
+            private_key,
+            public_key,
+        })
+    }
+
+    /// Create a signer from a hex encoded private key, such as the contents of a Sawtooth
+    /// `.priv` file.
+    pub fn from_hex(private_key: &str) -> Result<Self, Error> {
+        let private_key = Zeroizing::new(
+            hex::decode(private_key.trim())
+                .map_err(|err| Error::KeyError(format!("Invalid private key hex: {}", err)))?,
+        );
+        Self::new(&private_key)
+    }
+}
+
+impl Drop for Secp256k1Signer {
+    fn drop(&mut self) {
+        // SecretKey does not clear itself, so its bytes are overwritten in place.
+        let bytes = unsafe {
+            std::slice::from_raw_parts_mut(
+                self.private_key.as_mut_ptr(),
+                constants::SECRET_KEY_SIZE,
+            )
+        };
+        bytes.zeroize();
+    }
+}
+
+impl Signer for Secp256k1Signer {
+    fn algorithm_name(&self) -> &str {
+        ALGORITHM_NAME
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self
+            .context
+            .sign(&digest(message)?, &self.private_key)
+            .serialize_compact()
+            .to_vec())
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+/// Checks the signatures of a `Secp256k1Signer`, or of Sawtooth.
+///
+/// As with Sawtooth, a signature with a high S value is rejected, though it would be valid
+/// ECDSA, so that each signature has one valid form.
+pub struct Secp256k1Verifier {
+    context: Secp256k1<VerifyOnly>,
+}
+
+impl Secp256k1Verifier {
+    pub fn new() -> Self {
+        Secp256k1Verifier {
+            context: Secp256k1::verification_only(),
+        }
+    }
+}
+
+impl Default for Secp256k1Verifier {
+    fn default() -> Self {
+        Secp256k1Verifier::new()
+    }
+}
+
+impl SignatureVerifier for Secp256k1Verifier {
+    fn algorithm_name(&self) -> &str {
+        ALGORITHM_NAME
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool, Error> {
+        let signature = Signature::from_compact(signature)
+            .map_err(|err| Error::SigningError(format!("Invalid signature: {}", err)))?;
+        let public_key = PublicKey::from_slice(public_key)
+            .map_err(|err| Error::KeyError(format!("Invalid public key: {}", err)))?;
+
+        Ok(self
+            .context
+            .verify(&digest(message)?, &signature, &public_key)
+            .is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::BatchBuilder;
+    use crate::transaction::{HashMethod, TransactionBuilder};
+    use sawtooth_sdk::signing::{self as sawtooth_signing, PublicKey as _};
+
+    // The keys and signatures of the Sawtooth SDK's own secp256k1 tests.
+    static KEY1_PRIV_HEX: &str = "2f1e7b7a130d7ba9da0068b3bb0ba1d79e7e77110302c9f746c3c2a63fe40088";
+    static KEY1_PUB_HEX: &str =
+        "026a2c795a9776f75464aa3bda3534c3154a6e91b357b1181d3f515110f84b67c5";
+    static KEY2_PRIV_HEX: &str = "51b845c2cdde22fe646148f0b51eaf5feec8c82ee921d5e0cbe7619f3bb9c62d";
+    static KEY2_PUB_HEX: &str =
+        "039c20a66b4ec7995391dbec1d8bb0e2c6e6fd63cd259ed5b877cb4ea98858cf6d";
+
+    static MSG1: &str = "test";
+    static MSG1_KEY1_SIG: &str = "5195115d9be2547b720ee74c23dd841842875db6eae1f5da8605b050a49e702b\
+                                  4aa83be72ab7e3cb20f17c657011b49f4c8632be2745ba4de79e6aa05da57b35";
+    static MSG2: &str = "test2";
+    static MSG2_KEY2_SIG: &str = "d589c7b1fa5f8a4c5a389de80ae9582c2f7f2a5e21bab5450b670214e5b1c123\
+                                  5e9eb8102fd0ca690a8b42e2c406a682bd57f6daf6e142e5fa4b2c26ef40a490";
+
+    /// Whether the Sawtooth SDK accepts `signature` of `message` by `public_key`.
+    fn sawtooth_verify(signature: &str, message: &[u8], public_key: &[u8]) -> bool {
+        let context = sawtooth_signing::create_context("secp256k1").unwrap();
+        let public_key =
+            sawtooth_signing::secp256k1::Secp256k1PublicKey::from_hex(&hex::encode(public_key))
+                .unwrap();
+        assert_eq!("secp256k1", public_key.get_algorithm_name());
+        context.verify(signature, message, &public_key).unwrap()
+    }
+
+    #[test]
+    fn test_secp256k1_public_key() {
+        let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+        assert_eq!(KEY1_PUB_HEX, hex::encode(signer.public_key()));
+
+        let signer = Secp256k1Signer::new(&hex::decode(KEY2_PRIV_HEX).unwrap()).unwrap();
+        assert_eq!(KEY2_PUB_HEX, hex::encode(signer.public_key()));
+    }
+
+    /// Signatures are the same as those made by the Sawtooth SDK.
+    #[test]
+    fn test_secp256k1_sawtooth_signatures() {
+        let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+        assert_eq!(
+            MSG1_KEY1_SIG,
+            hex::encode(signer.sign(MSG1.as_bytes()).unwrap())
+        );
+
+        let signer = Secp256k1Signer::from_hex(KEY2_PRIV_HEX).unwrap();
+        assert_eq!(
+            MSG2_KEY2_SIG,
+            hex::encode(signer.sign(MSG2.as_bytes()).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_secp256k1_verifier() {
+        let verifier = Secp256k1Verifier::new();
+        let public_key = hex::decode(KEY1_PUB_HEX).unwrap();
+        let signature = hex::decode(MSG1_KEY1_SIG).unwrap();
+
+        assert!(verifier
+            .verify(MSG1.as_bytes(), &signature, &public_key)
+            .unwrap());
+        assert!(!verifier
+            .verify(MSG2.as_bytes(), &signature, &public_key)
+            .unwrap());
+        assert!(!verifier
+            .verify(
+                MSG1.as_bytes(),
+                &signature,
+                &hex::decode(KEY2_PUB_HEX).unwrap()
+            )
+            .unwrap());
+
+        assert!(verifier
+            .verify(MSG1.as_bytes(), &signature[..63], &public_key)
+            .is_err());
+        assert!(verifier
+            .verify(MSG1.as_bytes(), &signature, &public_key[..32])
+            .is_err());
+    }
+
+    /// Signatures made by the Sawtooth SDK, with its random keys, are verified.
+    #[test]
+    fn test_secp256k1_verify_sawtooth() {
+        let context = sawtooth_signing::create_context("secp256k1").unwrap();
+        let private_key = context.new_random_private_key().unwrap();
+        let signature = context.sign(b"message", &*private_key).unwrap();
+        let public_key = Secp256k1Signer::new(private_key.as_slice())
+            .unwrap()
+            .public_key()
+            .to_vec();
+
+        let verifier = Secp256k1Verifier::new();
+        assert!(verifier
+            .verify(b"message", &hex::decode(signature).unwrap(), &public_key)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_secp256k1_invalid_keys() {
+        assert!(Secp256k1Signer::new(&[1; 31]).is_err());
+        assert!(Secp256k1Signer::new(&[0; 32]).is_err(), "Zero is not a key");
+        assert!(Secp256k1Signer::from_hex("not hex").is_err());
+    }
+
+    /// The transactions and batches built are accepted by the Sawtooth SDK's verifier.
+    #[test]
+    fn test_secp256k1_builders() {
+        let signer = Secp256k1Signer::from_hex(KEY1_PRIV_HEX).unwrap();
+
+        let transaction = TransactionBuilder::new()
+            .with_dependencies(vec![])
+            .with_family_name("intkey".to_string())
+            .with_family_version("1.0".to_string())
+            .with_inputs(vec![])
+            .with_outputs(vec![])
+            .with_nonce(b"nonce".to_vec())
+            .with_payload_hash_method(HashMethod::SHA512)
+            .with_payload(b"payload".to_vec())
+            .build(&signer)
+            .unwrap();
+        assert!(sawtooth_verify(
+            transaction.header_signature(),
+            transaction.header(),
+            signer.public_key()
+        ));
+
+        let batch = BatchBuilder::new()
+            .with_transactions(vec![transaction])
+            .build(&signer)
+            .unwrap();
+        assert!(sawtooth_verify(
+            batch.header_signature(),
+            batch.header(),
+            signer.public_key()
+        ));
+        assert!(!sawtooth_verify(
+            batch.header_signature(),
+            b"another header",
+            signer.public_key()
+        ));
+    }
+}
@@ -1,6 +1,7 @@
+use blake2::Blake2b;
 use hex;
 use protobuf::Message;
-use sha2::{Digest, Sha512};
+use sha2::{Digest, Sha256, Sha512};
 use std;
 use std::error::Error as StdError;
 
@@ -8,9 +9,39 @@ use crate::protos;
 use crate::protos::{FromNative, FromProto, IntoNative, IntoProto, ProtoConversionError};
 use crate::signing;
 
-#[derive(Debug, PartialEq, Clone)]
+/// The hash of a transaction's payload in its header. Sawtooth only has `SHA512`.
+#[derive(Debug, Default, PartialEq, Clone)]
 pub enum HashMethod {
+    #[default]
     SHA512,
+    SHA256,
+    BLAKE2B,
+}
+
+impl HashMethod {
+    /// The method with the name in a proto header, where no name is `SHA512`. `SHA512` has
+    /// no other name, so that each header has one encoding.
+    fn from_proto_name(name: &str) -> Result<Self, ProtoConversionError> {
+        match name {
+            "" => Ok(HashMethod::SHA512),
+            "sha256" => Ok(HashMethod::SHA256),
+            "blake2b" => Ok(HashMethod::BLAKE2B),
+            _ => Err(ProtoConversionError::InvalidTypeError(format!(
+                "Unknown payload hash method: {}",
+                name
+            ))),
+        }
+    }
+
+    /// The name of the method in a proto header. `SHA512` has none, so that its headers are
+    /// those of Sawtooth.
+    fn proto_name(&self) -> &'static str {
+        match self {
+            HashMethod::SHA512 => "",
+            HashMethod::SHA256 => "sha256",
+            HashMethod::BLAKE2B => "blake2b",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -106,7 +137,7 @@ impl FromProto<protos::transaction::TransactionHeader> for TransactionHeader {
                 .map(|d| hex::decode(d).map_err(ProtoConversionError::from))
                 .collect::<Result<_, _>>()?,
             payload_hash: hex::decode(header.get_payload_sha512())?,
-            payload_hash_method: HashMethod::SHA512,
+            payload_hash_method: HashMethod::from_proto_name(header.get_payload_hash_method())?,
             signer_public_key: hex::decode(header.get_signer_public_key())?,
         })
     }
@@ -115,12 +146,16 @@ impl FromProto<protos::transaction::TransactionHeader> for TransactionHeader {
 impl FromNative<TransactionHeader> for protos::transaction::TransactionHeader {
     fn from_native(header: TransactionHeader) -> Result<Self, ProtoConversionError> {
         let mut proto_header = protos::transaction::TransactionHeader::new();
-        proto_header.set_family_version(header.family_version().to_string());
         proto_header.set_batcher_public_key(hex::encode(header.batcher_public_key()));
         proto_header.set_dependencies(header.dependencies().iter().map(hex::encode).collect());
+        proto_header.set_family_name(header.family_name().to_string());
+        proto_header.set_family_version(header.family_version().to_string());
         proto_header.set_inputs(header.inputs().iter().map(hex::encode).collect());
         proto_header.set_nonce(String::from_utf8(header.nonce().to_vec())?);
         proto_header.set_outputs(header.outputs().iter().map(hex::encode).collect());
+        proto_header.set_payload_sha512(hex::encode(header.payload_hash()));
+        proto_header.set_signer_public_key(hex::encode(header.signer_public_key()));
+        proto_header.set_payload_hash_method(header.payload_hash_method().proto_name().to_string());
         Ok(proto_header)
     }
 }
@@ -155,6 +190,66 @@ impl Transaction {
     pub fn payload(&self) -> &[u8] {
         &self.payload
     }
+
+    /// Decode the header, making the pair of this transaction and its header.
+    pub fn into_pair(self) -> Result<TransactionPair, TransactionParseError> {
+        let header = protos::transaction::TransactionHeader::parse_from_bytes(&self.header)
+            .map_err(|err| TransactionParseError::MalformedHeader(err.to_string()))?
+            .into_native()
+            .map_err(|err| TransactionParseError::MalformedHeader(err.to_string()))?;
+
+        Ok(TransactionPair {
+            transaction: self,
+            header,
+        })
+    }
+
+    /// Decode the header as `into_pair` does, and check that the header signature is the
+    /// header's signer's, using the verifier of the signer's algorithm, and that the payload
+    /// has the header's payload hash.
+    pub fn into_pair_verified(
+        self,
+        verifier: &dyn signing::SignatureVerifier,
+    ) -> Result<TransactionPair, TransactionParseError> {
+        let pair = self.into_pair()?;
+
+        match pair.verify_signature(verifier) {
+            Ok(true) => (),
+            Ok(false) => {
+                return Err(TransactionParseError::InvalidSignature(format!(
+                    "Header signature {} is not by the header's signer",
+                    pair.transaction.header_signature
+                )))
+            }
+            Err(err) => {
+                return Err(TransactionParseError::InvalidSignature(format!(
+                    "Header signature {} can not be verified: {}",
+                    pair.transaction.header_signature, err
+                )))
+            }
+        }
+
+        let payload_hash =
+            hash_payload(&pair.header.payload_hash_method, &pair.transaction.payload);
+        if payload_hash != pair.header.payload_hash {
+            return Err(TransactionParseError::PayloadHashMismatch(format!(
+                "Payload hash is {}, but the header's is {}",
+                hex::encode(payload_hash),
+                hex::encode(&pair.header.payload_hash)
+            )));
+        }
+
+        Ok(pair)
+    }
+}
+
+/// The hash of the payload by the method.
+fn hash_payload(method: &HashMethod, payload: &[u8]) -> Vec<u8> {
+    match method {
+        HashMethod::SHA512 => Sha512::digest(payload).to_vec(),
+        HashMethod::SHA256 => Sha256::digest(payload).to_vec(),
+        HashMethod::BLAKE2B => Blake2b::digest(payload).to_vec(),
+    }
 }
 
 impl From<protos::transaction::Transaction> for Transaction {
@@ -167,7 +262,39 @@ impl From<protos::transaction::Transaction> for Transaction {
     }
 }
 
-#[derive(Debug)]
+impl FromProto<protos::transaction::Transaction> for Transaction {
+    fn from_proto(
+        transaction: protos::transaction::Transaction,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(Transaction::from(transaction))
+    }
+}
+
+impl FromNative<Transaction> for protos::transaction::Transaction {
+    fn from_native(transaction: Transaction) -> Result<Self, ProtoConversionError> {
+        let mut proto_transaction = protos::transaction::Transaction::new();
+        proto_transaction.set_header(transaction.header);
+        proto_transaction.set_header_signature(transaction.header_signature);
+        proto_transaction.set_payload(transaction.payload);
+        Ok(proto_transaction)
+    }
+}
+
+impl IntoProto<protos::transaction::Transaction> for Transaction {}
+impl IntoNative<Transaction> for protos::transaction::Transaction {}
+
+impl Transaction {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto: protos::transaction::Transaction = self.clone().into_proto()?;
+        Ok(proto.write_to_bytes()?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Transaction, ProtoConversionError> {
+        protos::transaction::Transaction::parse_from_bytes(bytes)?.into_native()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TransactionPair {
     transaction: Transaction,
     header: TransactionHeader,
@@ -185,6 +312,23 @@ impl TransactionPair {
     pub fn take(self) -> (Transaction, TransactionHeader) {
         (self.transaction, self.header)
     }
+
+    /// Check the header signature against the header's signer public key, using the
+    /// verifier for the scheme the transaction was signed with. The verifier, from
+    /// `VerifierFactory::create_verifier`, can be reused across transactions.
+    pub fn verify_signature(
+        &self,
+        verifier: &dyn signing::SignatureVerifier,
+    ) -> Result<bool, signing::Error> {
+        let signature = hex::decode(self.transaction.header_signature()).map_err(|err| {
+            signing::Error::SigningError(format!("Invalid header signature: {}", err))
+        })?;
+        verifier.verify(
+            self.transaction.header(),
+            &signature,
+            self.header.signer_public_key(),
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -224,6 +368,49 @@ impl std::fmt::Display for TransactionBuildError {
     }
 }
 
+/// Why a received transaction could not be made into a `TransactionPair`.
+#[derive(Debug)]
+pub enum TransactionParseError {
+    /// The header bytes are not a valid `TransactionHeader`.
+    MalformedHeader(String),
+    /// The header signature is not that of the header by the header's signer.
+    InvalidSignature(String),
+    /// The hash of the payload is not the header's payload hash.
+    PayloadHashMismatch(String),
+}
+
+impl StdError for TransactionParseError {
+    fn description(&self) -> &str {
+        match *self {
+            TransactionParseError::MalformedHeader(ref msg) => msg,
+            TransactionParseError::InvalidSignature(ref msg) => msg,
+            TransactionParseError::PayloadHashMismatch(ref msg) => msg,
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn StdError> {
+        match *self {
+            TransactionParseError::MalformedHeader(_) => None,
+            TransactionParseError::InvalidSignature(_) => None,
+            TransactionParseError::PayloadHashMismatch(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TransactionParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TransactionParseError::MalformedHeader(ref s) => write!(f, "MalformedHeader: {}", s),
+            TransactionParseError::InvalidSignature(ref s) => {
+                write!(f, "InvalidSignature: {}", s)
+            }
+            TransactionParseError::PayloadHashMismatch(ref s) => {
+                write!(f, "PayloadHashMismatch: {}", s)
+            }
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct TransactionBuilder {
     batcher_public_key: Option<Vec<u8>>,
@@ -325,13 +512,7 @@ impl TransactionBuilder {
         })?;
         let signer_public_key = signer.public_key().to_vec();
 
-        let payload_hash = match payload_hash_method {
-            HashMethod::SHA512 => {
-                let mut hasher = Sha512::new();
-                hasher.input(&payload);
-                hasher.result().to_vec()
-            }
-        };
+        let payload_hash = hash_payload(&payload_hash_method, &payload);
 
         let header = TransactionHeader {
             batcher_public_key,
@@ -384,6 +565,7 @@ mod tests {
     use crate::signing::hash::HashSigner;
     use crate::signing::Signer;
     use protobuf::Message;
+    use rand::{thread_rng, Rng};
     use sawtooth_sdk;
 
     static FAMILY_NAME: &str = "test_family";
@@ -404,13 +586,10 @@ mod tests {
         "sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1sig1";
 
     fn check_builder_transaction(signer: &Signer, pair: &TransactionPair) {
-        let payload_hash = match pair.header().payload_hash_method() {
-            HashMethod::SHA512 => {
-                let mut hasher = Sha512::new();
-                hasher.input(&pair.transaction().payload());
-                hasher.result().to_vec()
-            }
-        };
+        let payload_hash = hash_payload(
+            pair.header().payload_hash_method(),
+            pair.transaction().payload(),
+        );
 
         assert_eq!(KEY1, hex::encode(pair.header().batcher_public_key()));
         assert_eq!(
@@ -436,6 +615,13 @@ mod tests {
         assert_eq!(payload_hash, pair.header().payload_hash());
         assert_eq!(HashMethod::SHA512, *pair.header().payload_hash_method());
         assert_eq!(signer.public_key(), pair.header().signer_public_key());
+
+        let header: TransactionHeader =
+            protos::transaction::TransactionHeader::parse_from_bytes(pair.transaction().header())
+                .unwrap()
+                .into_native()
+                .unwrap();
+        assert_eq!(pair.header(), &header);
     }
 
     #[test]
@@ -490,6 +676,132 @@ mod tests {
         check_builder_transaction(&signer, &pair);
     }
 
+    #[test]
+    fn transaction_verify_signature() {
+        let signer = HashSigner::new();
+
+        let pair = TransactionBuilder::new()
+            .with_dependencies(vec![])
+            .with_family_name(FAMILY_NAME.to_string())
+            .with_family_version(FAMILY_VERSION.to_string())
+            .with_inputs(vec![])
+            .with_nonce(NONCE.to_string().into_bytes())
+            .with_outputs(vec![])
+            .with_payload_hash_method(HashMethod::SHA512)
+            .with_payload(BYTES2.to_vec())
+            .build_pair(&signer)
+            .unwrap();
+
+        let verifier = signing::VerifierFactory::create_verifier(signer.algorithm_name()).unwrap();
+        assert!(pair.verify_signature(verifier.as_ref()).unwrap());
+
+        let verifier = signing::VerifierFactory::create_verifier("secp256k1").unwrap();
+        assert!(pair.verify_signature(verifier.as_ref()).is_err());
+    }
+
+    /// A transaction with the header built directly as a proto, signed by the `HashSigner`.
+    fn signed_transaction(payload: &[u8]) -> Transaction {
+        let signer = HashSigner::new();
+
+        let mut proto_header = protos::transaction::TransactionHeader::new();
+        proto_header.set_batcher_public_key(KEY1.to_string());
+        proto_header.set_family_name(FAMILY_NAME.to_string());
+        proto_header.set_family_version(FAMILY_VERSION.to_string());
+        proto_header.set_nonce(NONCE.to_string());
+        proto_header.set_payload_sha512(hex::encode(hash_payload(&HashMethod::SHA512, payload)));
+        proto_header.set_signer_public_key(hex::encode(signer.public_key()));
+        let header = proto_header.write_to_bytes().unwrap();
+
+        let header_signature = hex::encode(signer.sign(&header).unwrap());
+        Transaction::new(header, header_signature, payload.to_vec())
+    }
+
+    #[test]
+    fn transaction_into_pair() {
+        let transaction = signed_transaction(&BYTES2);
+
+        let pair = transaction.clone().into_pair().unwrap();
+        assert_eq!(&transaction, pair.transaction());
+        assert_eq!(KEY1, hex::encode(pair.header().batcher_public_key()));
+        assert_eq!(FAMILY_NAME, pair.header().family_name());
+        assert_eq!(b"hash_signer", pair.header().signer_public_key());
+
+        let malformed = Transaction::new(vec![0xff, 0xff], SIGNATURE1.to_string(), vec![]);
+        assert!(matches!(
+            malformed.into_pair(),
+            Err(TransactionParseError::MalformedHeader(_))
+        ));
+
+        let mut proto_header = protos::transaction::TransactionHeader::new();
+        proto_header.set_batcher_public_key("not hex".to_string());
+        let bad_hex = Transaction::new(
+            proto_header.write_to_bytes().unwrap(),
+            SIGNATURE1.to_string(),
+            vec![],
+        );
+        assert!(matches!(
+            bad_hex.into_pair(),
+            Err(TransactionParseError::MalformedHeader(_))
+        ));
+    }
+
+    #[test]
+    fn transaction_into_pair_verified() {
+        let verifier =
+            signing::VerifierFactory::create_verifier(signing::hash::ALGORITHM_NAME).unwrap();
+
+        let transaction = signed_transaction(&BYTES2);
+        let pair = transaction
+            .clone()
+            .into_pair_verified(verifier.as_ref())
+            .unwrap();
+        assert_eq!(&transaction, pair.transaction());
+
+        let forged = Transaction::new(
+            transaction.header().to_vec(),
+            hex::encode(signed_transaction(&BYTES1).header()),
+            transaction.payload().to_vec(),
+        );
+        assert!(matches!(
+            forged.into_pair_verified(verifier.as_ref()),
+            Err(TransactionParseError::InvalidSignature(_))
+        ));
+
+        let unverifiable = Transaction::new(
+            transaction.header().to_vec(),
+            "not hex".to_string(),
+            transaction.payload().to_vec(),
+        );
+        assert!(matches!(
+            unverifiable.into_pair_verified(verifier.as_ref()),
+            Err(TransactionParseError::InvalidSignature(_))
+        ));
+
+        let swapped = Transaction::new(
+            transaction.header().to_vec(),
+            transaction.header_signature().to_string(),
+            BYTES1.to_vec(),
+        );
+        assert!(matches!(
+            swapped.into_pair_verified(verifier.as_ref()),
+            Err(TransactionParseError::PayloadHashMismatch(_))
+        ));
+
+        let mut proto_header =
+            protos::transaction::TransactionHeader::parse_from_bytes(transaction.header()).unwrap();
+        proto_header.set_payload_hash_method("sha256".to_string());
+        let header = proto_header.write_to_bytes().unwrap();
+        let other_method = Transaction::new(
+            header.clone(),
+            hex::encode(HashSigner::new().sign(&header).unwrap()),
+            transaction.payload().to_vec(),
+        );
+        assert!(matches!(
+            other_method.into_pair_verified(verifier.as_ref()),
+            Err(TransactionParseError::PayloadHashMismatch(_))
+        ));
+    }
+
     #[test]
     fn transaction_header_fields() {
         let header = TransactionHeader {
@@ -607,6 +919,154 @@ mod tests {
         assert_eq!(BYTES2.to_vec(), transaction.payload());
     }
 
+    #[test]
+    fn hash_payload_methods() {
+        assert_eq!(
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            hex::encode(hash_payload(&HashMethod::SHA512, b"abc"))
+        );
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            hex::encode(hash_payload(&HashMethod::SHA256, b"abc"))
+        );
+        assert_eq!(
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+            hex::encode(hash_payload(&HashMethod::BLAKE2B, b"abc"))
+        );
+        assert_eq!(HashMethod::SHA512, HashMethod::default());
+    }
+
+    #[test]
+    fn transaction_builder_hash_methods() {
+        let signer = HashSigner::new();
+        let verifier = signing::VerifierFactory::create_verifier(signer.algorithm_name()).unwrap();
+
+        for method in &[HashMethod::SHA512, HashMethod::SHA256, HashMethod::BLAKE2B] {
+            let transaction = TransactionBuilder::new()
+                .with_batcher_public_key(hex::decode(KEY1).unwrap())
+                .with_dependencies(vec![])
+                .with_family_name(FAMILY_NAME.to_string())
+                .with_family_version(FAMILY_VERSION.to_string())
+                .with_inputs(vec![])
+                .with_nonce(NONCE.to_string().into_bytes())
+                .with_outputs(vec![])
+                .with_payload_hash_method(method.clone())
+                .with_payload(BYTES2.to_vec())
+                .build(&signer)
+                .unwrap();
+
+            let pair = transaction.into_pair_verified(verifier.as_ref()).unwrap();
+            assert_eq!(method, pair.header().payload_hash_method());
+            assert_eq!(hash_payload(method, &BYTES2), pair.header().payload_hash());
+        }
+    }
+
+    #[test]
+    fn transaction_header_hash_method_field() {
+        let mut proto_header = protos::transaction::TransactionHeader::new();
+        proto_header.set_payload_sha512(HASH.to_string());
+
+        let header: TransactionHeader = proto_header.clone().into_native().unwrap();
+        assert_eq!(HashMethod::SHA512, *header.payload_hash_method());
+
+        proto_header.set_payload_hash_method("blake2b".to_string());
+        let header: TransactionHeader = proto_header.clone().into_native().unwrap();
+        assert_eq!(HashMethod::BLAKE2B, *header.payload_hash_method());
+        let proto: protos::transaction::TransactionHeader = header.into_proto().unwrap();
+        assert_eq!("blake2b", proto.get_payload_hash_method());
+
+        for name in &["md5", "sha512"] {
+            proto_header.set_payload_hash_method(name.to_string());
+            let result: Result<TransactionHeader, _> = proto_header.clone().into_native();
+            assert!(matches!(
+                result,
+                Err(ProtoConversionError::InvalidTypeError(_))
+            ));
+        }
+    }
+
+    /// A header with every field set to random values.
+    fn random_header<R: Rng>(rng: &mut R) -> TransactionHeader {
+        fn bytes<R: Rng>(rng: &mut R, max_len: usize) -> Vec<u8> {
+            let len = rng.gen_range(0, max_len + 1);
+            rng.gen_iter().take(len).collect()
+        }
+
+        fn string<R: Rng>(rng: &mut R, max_len: usize) -> String {
+            let len = rng.gen_range(0, max_len + 1);
+            rng.gen_ascii_chars().take(len).collect()
+        }
+
+        fn addresses<R: Rng>(rng: &mut R) -> Vec<Vec<u8>> {
+            let len = rng.gen_range(0, 4);
+            (0..len).map(|_| bytes(rng, 35)).collect()
+        }
+
+        TransactionHeader {
+            batcher_public_key: bytes(rng, 33),
+            dependencies: (0..rng.gen_range(0, 4)).map(|_| bytes(rng, 64)).collect(),
+            family_name: string(rng, 16),
+            family_version: string(rng, 8),
+            inputs: addresses(rng),
+            outputs: addresses(rng),
+            nonce: string(rng, 16).into_bytes(),
+            payload_hash: bytes(rng, 64),
+            payload_hash_method: HashMethod::SHA512,
+            signer_public_key: bytes(rng, 33),
+        }
+    }
+
+    /// The Sawtooth SDK's header with the same fields as the header.
+    fn sawtooth_header(
+        header: &TransactionHeader,
+    ) -> sawtooth_sdk::messages::transaction::TransactionHeader {
+        let hex_strings = |values: &[Vec<u8>]| {
+            protobuf::RepeatedField::from_vec(values.iter().map(hex::encode).collect())
+        };
+
+        let mut proto = sawtooth_sdk::messages::transaction::TransactionHeader::new();
+        proto.set_batcher_public_key(hex::encode(header.batcher_public_key()));
+        proto.set_dependencies(hex_strings(header.dependencies()));
+        proto.set_family_name(header.family_name().to_string());
+        proto.set_family_version(header.family_version().to_string());
+        proto.set_inputs(hex_strings(header.inputs()));
+        proto.set_nonce(String::from_utf8(header.nonce().to_vec()).unwrap());
+        proto.set_outputs(hex_strings(header.outputs()));
+        proto.set_payload_sha512(hex::encode(header.payload_hash()));
+        proto.set_signer_public_key(hex::encode(header.signer_public_key()));
+        proto
+    }
+
+    #[test]
+    fn transaction_header_into_proto_sawtooth10_compatibility() {
+        let mut rng = thread_rng();
+
+        for _ in 0..256 {
+            let header = random_header(&mut rng);
+
+            let proto: protos::transaction::TransactionHeader =
+                header.clone().into_proto().unwrap();
+            let bytes = proto.write_to_bytes().unwrap();
+
+            // The bytes are those of the Sawtooth SDK, so signatures of them are compatible
+            assert_eq!(
+                sawtooth_header(&header).write_to_bytes().unwrap(),
+                bytes,
+                "{:?}",
+                header
+            );
+
+            let round_trip: TransactionHeader =
+                protos::transaction::TransactionHeader::parse_from_bytes(&bytes)
+                    .unwrap()
+                    .into_native()
+                    .unwrap();
+            assert_eq!(header, round_trip);
+        }
+    }
+
     #[test]
     fn transaction_sawtooth10_compatibility() {
         // Create protobuf bytes using the Sawtooth SDK
@@ -627,6 +1087,24 @@ mod tests {
         assert_eq!(SIGNATURE1, transaction.header_signature());
         assert_eq!(BYTES2.to_vec(), transaction.payload());
     }
+
+    #[test]
+    fn transaction_bytes_sawtooth10_compatibility() {
+        let mut proto = sawtooth_sdk::messages::transaction::Transaction::new();
+        proto.set_header(BYTES1.to_vec());
+        proto.set_header_signature(SIGNATURE1.to_string());
+        proto.set_payload(BYTES2.to_vec());
+        let transaction_bytes = proto.write_to_bytes().unwrap();
+
+        let transaction = Transaction::from_bytes(&transaction_bytes).unwrap();
+        assert_eq!(
+            Transaction::new(BYTES1.to_vec(), SIGNATURE1.to_string(), BYTES2.to_vec()),
+            transaction
+        );
+        assert_eq!(transaction_bytes, transaction.to_bytes().unwrap());
+
+        assert!(Transaction::from_bytes(&[0xff, 0xff]).is_err());
+    }
 }
 
 #[cfg(all(feature = "nightly", test))]